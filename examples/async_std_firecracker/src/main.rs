@@ -1,13 +1,13 @@
 //! Run firecracker instance with async-std runtime
 
-use firecracker_rs_sdk::firecracker::FirecrackerOption;
+use firecracker_rs_sdk::firecracker::{self, FirecrackerOption};
 use firecracker_rs_sdk::models::*;
 use firecracker_rs_sdk::Result;
 
 #[async_std::main]
 async fn main() -> Result<()> {
-    // Path to the `firecracker` binary
-    const FIRECRACKER: &'static str = "/usr/bin/firecracker";
+    // Path to the `firecracker` binary, located via `FIRECRACKER_BIN`, `PATH` or common install locations
+    let firecracker_bin = firecracker::find_binary().expect("`firecracker` binary not found");
 
     // Path at which you want to place the socket at
     const API_SOCK: &'static str = "/tmp/firecracker.socket";
@@ -19,7 +19,7 @@ async fn main() -> Result<()> {
     const ROOTFS: &'static str = "/foo/bar/rootfs.ext4";
 
     // Build an instance with desired options
-    let mut instance = FirecrackerOption::new(FIRECRACKER)
+    let mut instance = FirecrackerOption::new(&firecracker_bin)
         .api_sock(API_SOCK)
         .id("test-instance")
         .build()?;
@@ -27,6 +27,11 @@ async fn main() -> Result<()> {
     // First start the `firecracker` process
     instance.start_vmm().await?;
 
+    // Wait until the API answers before configuring the VM
+    instance
+        .wait_until_ready(std::time::Duration::from_secs(3))
+        .await?;
+
     // Try to get firecracker version as sanity checking
     let version = instance.get_firecracker_version().await?;
     println!("{:?}", version);
@@ -34,39 +39,18 @@ async fn main() -> Result<()> {
     // Then put some configuration to it
     // (1) Machine Configuration
     instance
-        .put_machine_configuration(&MachineConfiguration {
-            cpu_template: None,
-            smt: None,
-            mem_size_mib: 1024,
-            track_dirty_pages: None,
-            vcpu_count: 1,
-            huge_pages: None,
-        })
+        .put_machine_configuration(&MachineConfiguration::new(1, 1024))
         .await?;
 
     // (2) Guest Boot Source
-    instance
-        .put_guest_boot_source(&BootSource {
-            boot_args: Some("console=ttyS0 reboot=k panic=1 pci=off".into()),
-            initrd_path: None,
-            kernel_image_path: KERNEL.into(),
-        })
-        .await?;
+    let mut boot_source = BootSource::new(KERNEL);
+    boot_source.boot_args = Some("console=ttyS0 reboot=k panic=1 pci=off".into());
+    instance.put_guest_boot_source(&boot_source).await?;
 
     // (3) Guest Drives
-    instance
-        .put_guest_drive_by_id(&Drive {
-            drive_id: "rootfs".into(),
-            partuuid: None,
-            is_root_device: true,
-            cache_type: None,
-            is_read_only: false,
-            path_on_host: ROOTFS.into(),
-            rate_limiter: None,
-            io_engine: None,
-            socket: None,
-        })
-        .await?;
+    let mut root_drive = Drive::new("rootfs", ROOTFS);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive).await?;
 
     // Start the instance
     instance.start().await?;
@@ -8,7 +8,184 @@ mod rt_std;
 #[cfg(feature = "_rt-tokio")]
 mod rt_tokio;
 
-pub const MAX_BUFFER_SIZE: usize = 64;
+/// Default size of the reads of a response from the API socket, see
+/// `FirecrackerOption::read_buffer_size`.
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Former name of [`DEFAULT_READ_BUFFER_SIZE`].
+#[deprecated(note = "renamed, use `DEFAULT_READ_BUFFER_SIZE`")]
+pub const MAX_BUFFER_SIZE: usize = DEFAULT_READ_BUFFER_SIZE;
+
+/// Largest response accepted from the API socket, headers included. A larger one, e.g. from
+/// a bogus `Content-Length`, fails with [`crate::Error::Agent`] instead of being allocated.
+pub const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Interval between attempts to connect to the API socket.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+const CONNECT_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Message of the [`crate::Error::Agent`] returned once `firecracker` closed the connection.
+pub(crate) const CONNECTION_CLOSED: &str = "connection closed by firecracker";
+
+/// Size of the scratch buffer receiving the status line and headers of a response,
+/// before its `Content-Length` is known.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+const HEAD_BUFFER_SIZE: usize = 1024;
+
+/// How the end of a response is found from its headers.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+#[derive(Clone, Copy)]
+enum Framing {
+    /// The whole response is this long, from `Content-Length` (no body without it).
+    Length(usize),
+    /// `Transfer-Encoding: chunked`, the body starting at this offset ends after the last chunk.
+    Chunked(usize),
+}
+
+/// Buffer receiving a single response, allocated once its length is known from the headers.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+struct ResponseBuf {
+    head: [u8; HEAD_BUFFER_SIZE],
+    // empty until the headers are complete, then sized to the whole response
+    // (or grown read by read if chunked)
+    response: Vec<u8>,
+    filled: usize,
+    framing: Option<Framing>,
+    // length of the response once complete, the bytes past it belong to the next one
+    end: usize,
+    // most bytes asked for by a single read
+    read_size: usize,
+}
+
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+impl ResponseBuf {
+    fn new(read_size: usize) -> Self {
+        Self {
+            head: [0; HEAD_BUFFER_SIZE],
+            response: Vec::new(),
+            filled: 0,
+            framing: None,
+            end: 0,
+            read_size,
+        }
+    }
+
+    /// Room for the next read, at most `read_size` bytes and never past the end of
+    /// a response of known length.
+    fn spare(&mut self) -> crate::Result<&mut [u8]> {
+        let buf: &mut [u8] = match self.framing {
+            None => &mut self.head,
+            Some(Framing::Length(_)) => &mut self.response,
+            Some(Framing::Chunked(_)) => {
+                let len = (self.filled + self.read_size).min(MAX_RESPONSE_SIZE);
+                self.response.resize(len, 0);
+                &mut self.response
+            }
+        };
+        if self.filled == buf.len() {
+            return Err(match self.framing {
+                None => {
+                    crate::Error::Agent(format!("Response headers exceed {HEAD_BUFFER_SIZE} bytes"))
+                }
+                Some(_) => too_large(),
+            });
+        }
+        let end = buf.len().min(self.filled + self.read_size);
+        Ok(&mut buf[self.filled..end])
+    }
+
+    /// Record `n` bytes read into [`ResponseBuf::spare`], returning whether the response is complete.
+    fn advance(&mut self, n: usize) -> crate::Result<bool> {
+        self.filled += n;
+        let framing = match self.framing {
+            Some(framing) => framing,
+            None => {
+                let Some(framing) = framing(&self.head[..self.filled])? else {
+                    return Ok(false);
+                };
+                let len = match framing {
+                    Framing::Length(len) => len.max(self.filled),
+                    Framing::Chunked(_) => self.filled,
+                };
+                if len > MAX_RESPONSE_SIZE {
+                    return Err(too_large());
+                }
+                let mut response = vec![0; len];
+                response[..self.filled].copy_from_slice(&self.head[..self.filled]);
+                self.response = response;
+                self.framing = Some(framing);
+                framing
+            }
+        };
+        let end = match framing {
+            Framing::Length(len) => (self.filled >= len).then_some(len),
+            Framing::Chunked(body_start) => {
+                let body = &self.response[body_start..self.filled];
+                crate::events::dechunk(body)?.map(|(_, len)| body_start + len)
+            }
+        };
+        self.end = end.unwrap_or_default();
+        Ok(end.is_some())
+    }
+
+    /// Copy `bytes` in as if read from the socket, returning whether they complete the
+    /// response and the bytes past it, not copied.
+    fn feed<'a>(&mut self, mut bytes: &'a [u8]) -> crate::Result<(bool, &'a [u8])> {
+        while !bytes.is_empty() {
+            let spare = self.spare()?;
+            let n = spare.len().min(bytes.len());
+            spare[..n].copy_from_slice(&bytes[..n]);
+            bytes = &bytes[n..];
+            if self.advance(n)? {
+                return Ok((true, bytes));
+            }
+        }
+        Ok((false, bytes))
+    }
+
+    /// The complete response, and the bytes received past its end.
+    fn into_parts(mut self) -> (Vec<u8>, Vec<u8>) {
+        self.response.truncate(self.filled);
+        let trailing = self.response.split_off(self.end);
+        (self.response, trailing)
+    }
+}
+
+/// Framing of the response whose beginning is `head`, or `None` while its headers are
+/// incomplete.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+fn framing(head: &[u8]) -> crate::Result<Option<Framing>> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut res = httparse::Response::new(&mut headers);
+    let body_start = match res.parse(head) {
+        Ok(httparse::Status::Complete(body_start)) => body_start,
+        Ok(httparse::Status::Partial) => return Ok(None),
+        Err(e) => return Err(crate::Error::Agent(format!("Bad HTTP response: {e}"))),
+    };
+    let header = |name: &str| {
+        res.headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+    };
+    if header("transfer-encoding").is_some_and(|h| crate::events::is_chunked(h.value)) {
+        return Ok(Some(Framing::Chunked(body_start)));
+    }
+    let content_length = match header("content-length") {
+        Some(h) => std::str::from_utf8(h.value)
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .ok_or_else(|| crate::Error::Agent("Bad Content-Length in HTTP response".into()))?,
+        None => 0,
+    };
+    Ok(Some(Framing::Length(
+        body_start.saturating_add(content_length),
+    )))
+}
+
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+fn too_large() -> crate::Error {
+    crate::Error::Agent(format!("Response exceeds {MAX_RESPONSE_SIZE} bytes"))
+}
 
 pub(crate) struct SocketAgent {
     #[cfg(feature = "_rt-std")]
@@ -17,6 +194,108 @@ pub(crate) struct SocketAgent {
     stream: tokio::net::UnixStream,
     #[cfg(feature = "_rt-async-std")]
     stream: async_std::os::unix::net::UnixStream,
+
+    /// Path of the API socket, connected to again if `firecracker` closed the connection.
+    #[allow(unused)]
+    socket_path: std::path::PathBuf,
+
+    /// Most bytes asked for by a single read of a response.
+    #[allow(unused)]
+    read_buffer_size: usize,
+
+    /// Set while a request has been sent but its response not read yet.
+    /// Still being set when a new event begins means the two are interleaved (e.g. the
+    /// previous one is a cancelled future), so the new event fails instead of reading the
+    /// response of the other.
+    #[allow(unused)]
+    in_flight: bool,
+
+    /// Set when the last request failed, possibly leaving part of its response unread:
+    /// the next event reconnects first.
+    #[allow(unused)]
+    stale: bool,
+
+    /// Bytes received past the end of the last response, e.g. from a server coalescing
+    /// writes or pipelined requests, taken as the beginning of the next one.
+    #[allow(unused)]
+    leftover: Vec<u8>,
+}
+
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+impl std::os::fd::AsRawFd for SocketAgent {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.stream.as_raw_fd()
+    }
+}
+
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+impl SocketAgent {
+    /// Read responses `read_buffer_size` bytes at a time (at least 1).
+    pub(crate) fn set_read_buffer_size(&mut self, read_buffer_size: usize) {
+        self.read_buffer_size = read_buffer_size.max(1);
+    }
+
+    /// Begin receiving a response with the bytes left over from the previous one, returning
+    /// whether they hold it whole.
+    fn resume_response(&mut self) -> crate::Result<(ResponseBuf, bool)> {
+        let mut buf = ResponseBuf::new(self.read_buffer_size);
+        let leftover = std::mem::take(&mut self.leftover);
+        let (complete, unread) = buf.feed(&leftover)?;
+        self.leftover = unread.to_vec();
+        Ok((buf, complete))
+    }
+
+    /// The response completed in `buf`, keeping the bytes received past it for the next one.
+    fn finish_response(&mut self, buf: ResponseBuf) -> Vec<u8> {
+        let (response, mut trailing) = buf.into_parts();
+        // received before whatever `resume_response` didn't need
+        trailing.append(&mut self.leftover);
+        self.leftover = trailing;
+        response
+    }
+
+    /// `firecracker` closed the connection after `received` bytes of the response.
+    fn closed(&self, received: usize) -> crate::Error {
+        if received == 0 {
+            crate::Error::Agent(CONNECTION_CLOSED.into())
+        } else {
+            crate::Error::Agent(format!(
+                "{CONNECTION_CLOSED} after {received} bytes of the response"
+            ))
+        }
+    }
+}
+
+/// Whether `request`, failed with `e`, can be sent again on a new connection.
+///
+/// Only if `firecracker` closed the connection before sending any of the response, e.g. an
+/// idle connection it dropped between events, and either:
+/// - the request wasn't `sent` whole, so `firecracker` didn't handle it;
+/// - or it's a `GET`, which changes nothing when handled twice.
+///
+/// Any other request might have taken effect before the connection was closed (e.g.
+/// `PUT /actions` starting the guest), so it's never replayed.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+fn can_resend(e: &crate::Error, request: &[u8], sent: bool) -> bool {
+    matches!(e, crate::Error::Agent(e) if e == CONNECTION_CLOSED)
+        && (!sent || request.starts_with(b"GET "))
+}
+
+/// Whether a request failed with `e` may leave part of its response unread on the
+/// connection, i.e. unless `firecracker` closed it.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+fn leaves_stale(e: &crate::Error) -> bool {
+    !matches!(e, crate::Error::Agent(e) if e.starts_with(CONNECTION_CLOSED))
+}
+
+/// Whether the socket error `e` means `firecracker` closed the connection.
+#[cfg(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std"))]
+fn is_closed(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        e.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::NotConnected
+    )
 }
 
 #[cfg(not(any(feature = "_rt-std", feature = "_rt-tokio", feature = "_rt-async-std")))]
@@ -2,71 +2,155 @@ use async_std::{
     io::{ReadExt, WriteExt},
     os::unix::net::UnixStream,
 };
-use std::{fs, io::ErrorKind, path::Path, time::Duration};
+use std::{io::ErrorKind, path::Path, time::Duration};
 
 use crate::{
+    clock::{Clock, SystemClock},
     events::{EventTrait, ResponseTrait},
     Error, Result,
 };
 
-use super::{SocketAgent, MAX_BUFFER_SIZE};
+use super::{
+    can_resend, is_closed, leaves_stale, SocketAgent, CONNECT_RETRY_INTERVAL,
+    DEFAULT_READ_BUFFER_SIZE,
+};
 
 impl SocketAgent {
     pub(crate) async fn new<P: AsRef<Path>>(socket_path: P, timeout: Duration) -> Result<Self> {
-        // wait the socket
-        let wait_future = async { while !fs::exists(&socket_path).is_ok_and(|x| x) {} };
+        Self::new_with_clock(socket_path, timeout, &SystemClock).await
+    }
+
+    /// Connect to `socket_path`, retrying until `timeout` elapsed on `clock`.
+    pub(crate) async fn new_with_clock<P: AsRef<Path>, C: Clock>(
+        socket_path: P,
+        timeout: Duration,
+        clock: &C,
+    ) -> Result<Self> {
+        let start = clock.now();
 
-        match async_std::future::timeout(timeout, wait_future).await {
-            Ok(()) => {
-                let stream = UnixStream::connect(socket_path.as_ref().as_os_str()).await?;
-                Ok(Self { stream })
+        loop {
+            match UnixStream::connect(socket_path.as_ref().as_os_str()).await {
+                Ok(stream) => {
+                    return Ok(Self {
+                        stream,
+                        socket_path: socket_path.as_ref().to_path_buf(),
+                        read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+                        in_flight: false,
+                        stale: false,
+                        leftover: Vec::new(),
+                    });
+                }
+                Err(e)
+                    if e.kind() == ErrorKind::NotFound
+                        || e.kind() == ErrorKind::ConnectionRefused =>
+                {
+                    if clock.now().duration_since(start) >= timeout {
+                        return Err(Error::Timeout {
+                            operation: format!("Connecting to {}", socket_path.as_ref().display()),
+                            after: timeout,
+                        });
+                    }
+                    clock.sleep(CONNECT_RETRY_INTERVAL).await; // wait before retry
+                }
+                Err(e) => return Err(e.into()),
             }
-            Err(e) => Err(Error::Agent(format!("Connection timed out: {e}"))),
         }
     }
 
-    pub(crate) async fn send_request(&mut self, data: &[u8]) -> Result<()> {
-        self.stream.write_all(data).await?;
-        self.stream.flush().await?;
+    /// Connect to the API socket again, replacing the connection closed by `firecracker`
+    /// or out of sync.
+    async fn reconnect(&mut self) -> Result<()> {
+        self.stream = UnixStream::connect(self.socket_path.as_os_str()).await?;
+        self.leftover.clear();
+        self.stale = false;
         Ok(())
     }
 
+    pub(crate) async fn send_request(&mut self, data: &[u8]) -> Result<()> {
+        let result = match self.stream.write_all(data).await {
+            Ok(()) => self.stream.flush().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(ref e) if is_closed(e) => Err(self.closed(0)),
+            result => Ok(result?),
+        }
+    }
+
     pub(crate) async fn recv_response(&mut self) -> Result<Vec<u8>> {
-        let mut buf = [0u8; MAX_BUFFER_SIZE];
-        let mut vec: Vec<u8> = Vec::new();
+        let (mut buf, mut complete) = self.resume_response()?;
 
-        loop {
-            match self.stream.read(&mut buf).await {
-                Ok(0) => break,
-                Ok(n) => {
-                    vec.extend_from_slice(&mut buf);
-                    if n < MAX_BUFFER_SIZE {
-                        // No need for checking again
-                        break;
-                    }
-                }
+        while !complete {
+            match self.stream.read(buf.spare()?).await {
+                Ok(0) => return Err(self.closed(buf.filled)),
+                Ok(n) => complete = buf.advance(n)?,
                 Err(ref e) if e.kind() == ErrorKind::WouldBlock => continue,
-                Err(e) => return Err(Error::Agent(format!("Bad read from socket: {e}"))),
+                Err(ref e) if is_closed(e) => return Err(self.closed(buf.filled)),
+                Err(e) => {
+                    return Err(Error::AgentIO {
+                        context: "Bad read from socket".into(),
+                        source: e,
+                    })
+                }
             }
         }
 
-        Ok(vec)
+        Ok(self.finish_response(buf))
     }
 
     pub(crate) async fn event<E: EventTrait>(
         &mut self,
         event: E,
     ) -> Result<<E as ResponseTrait>::Payload> {
-        self.send_request(&event.encode()?).await?;
-        let response = self.recv_response().await?;
+        let response = self.raw(&event.encode()?).await?;
         E::decode(&response)
     }
+
+    /// Send the encoded `request` and receive its raw response, with the same handling of
+    /// stale and closed connections as [`SocketAgent::event`].
+    pub(crate) async fn raw(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        if self.in_flight {
+            // interleaved with a request still waiting for its response, e.g. through a
+            // cancelled future: both would read the other's response
+            return Err(Error::Agent(
+                "concurrent request on single connection".into(),
+            ));
+        }
+        if self.stale {
+            // the previous request failed halfway, and the rest of its response might
+            // still arrive: start over on a new connection
+            self.reconnect().await?;
+        }
+        match self.round_trip(request).await {
+            // retry once on a new connection if `firecracker` closed this one between events
+            Err((e, sent)) if can_resend(&e, request, sent) => match self.reconnect().await {
+                Ok(()) => self.round_trip(request).await.map_err(|(e, _)| e),
+                Err(_) => Err(e),
+            },
+            result => result.map_err(|(e, _)| e),
+        }
+    }
+
+    /// Send `request` and receive its response. A failure comes with whether the request
+    /// was sent whole, see [`can_resend`].
+    async fn round_trip(&mut self, request: &[u8]) -> std::result::Result<Vec<u8>, (Error, bool)> {
+        self.in_flight = true;
+        let result = match self.send_request(request).await {
+            Ok(()) => self.recv_response().await.map_err(|e| (e, true)),
+            Err(e) => Err((e, false)),
+        };
+        self.in_flight = false;
+        self.stale = result.as_ref().is_err_and(|(e, _)| leaves_stale(e));
+        result
+    }
 }
 
 #[cfg(feature = "_rt-async-std")]
 #[cfg(test)]
 mod tests {
-    use std::{env, fs, process::Command, sync::LazyLock, time::Duration};
+    #[cfg(feature = "models")]
+    use std::{env, process::Command, sync::LazyLock};
+    use std::{fs, time::Duration};
 
     use async_std::{
         io::{ReadExt, WriteExt},
@@ -75,17 +159,33 @@ mod tests {
     };
 
     use crate::{
-        agent::SocketAgent,
+        agent::{SocketAgent, CONNECT_RETRY_INTERVAL, DEFAULT_READ_BUFFER_SIZE},
+        clock::MockClock,
+        Error, Result,
+    };
+    #[cfg(feature = "models")]
+    use crate::{
         events::{GetFirecrackerVersion, ResponseTrait},
         models::Empty,
-        Result,
     };
 
+    #[cfg(feature = "models")]
     const FIRECRACKER: LazyLock<String> = LazyLock::new(|| {
         dotenvy::dotenv().ok();
         env::var("FIRECRACKER").unwrap()
     });
 
+    #[async_std::test]
+    async fn connect_timeout() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-async-std-nonexistent.socket";
+        let clock = MockClock::new();
+
+        let result = SocketAgent::new_with_clock(API_SOCK, Duration::from_secs(3), &clock).await;
+
+        assert!(matches!(result, Err(Error::Timeout { .. })));
+        assert_eq!(clock.sleeps(), vec![CONNECT_RETRY_INTERVAL; 30]);
+    }
+
     async fn echo_server<P: AsRef<Path>>(api_sock: P) -> Result<()> {
         let listener = UnixListener::bind(&api_sock).await?;
         println!("Server listening on {}", api_sock.as_ref().display());
@@ -109,7 +209,7 @@ mod tests {
     #[async_std::test]
     async fn test_echo() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-async-std-echo.socket";
-        const DATA: &'static str = "Hello, world!";
+        const DATA: &'static str = "HTTP/1.1 200 \r\nContent-Length: 13\r\n\r\nHello, world!";
         let _ = fs::remove_file(API_SOCK);
 
         let server_handle = async_std::task::spawn(echo_server(API_SOCK));
@@ -125,6 +225,32 @@ mod tests {
         let _ = fs::remove_file(API_SOCK);
     }
 
+    #[async_std::test]
+    async fn recv_response_length() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-async-std-length.socket";
+        let body = [b'x'; 100];
+        assert_ne!(body.len() % DEFAULT_READ_BUFFER_SIZE, 0);
+        let mut data =
+            format!("HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        data.extend_from_slice(&body);
+        let _ = fs::remove_file(API_SOCK);
+
+        let server_handle = async_std::task::spawn(echo_server(API_SOCK));
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3))
+            .await
+            .unwrap();
+        agent.send_request(&data).await.unwrap();
+        let response = agent.recv_response().await.unwrap();
+
+        assert_eq!(response, data);
+        // sized from `Content-Length` up front, without growing
+        assert_eq!(response.capacity(), response.len());
+
+        server_handle.await.unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
     #[async_std::test]
     async fn test_get_firecracker_version() {
         const DATA: &'static str = "GET /version HTTP/1.0\r\n\r\n";
@@ -154,6 +280,7 @@ mod tests {
         let _ = fs::remove_file(API_SOCK);
     }
 
+    #[cfg(feature = "models")]
     #[async_std::test]
     async fn test_get_firecracker_version_event() {
         const API_SOCK: &'static str =
@@ -1,101 +1,242 @@
 use std::{
     io::{ErrorKind, Read, Write},
-    os::unix::net::UnixStream,
+    os::{fd::AsRawFd, unix::net::UnixStream},
     path::Path,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use crate::{
+    clock::{Clock, SystemClock},
     events::{EventTrait, ResponseTrait},
     Error, Result,
 };
 
-use super::{SocketAgent, MAX_BUFFER_SIZE};
+use super::{
+    can_resend, is_closed, leaves_stale, SocketAgent, CONNECT_RETRY_INTERVAL,
+    DEFAULT_READ_BUFFER_SIZE,
+};
+
+/// Time given to `firecracker` to take in a request once it filled the socket buffer.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
 
 impl SocketAgent {
     pub(crate) fn new<P: AsRef<Path>>(socket_path: P, timeout: Duration) -> Result<Self> {
-        let start = Instant::now();
+        Self::new_with_clock(socket_path, timeout, &SystemClock)
+    }
+
+    /// Connect to `socket_path`, retrying until `timeout` elapsed on `clock`.
+    pub(crate) fn new_with_clock<P: AsRef<Path>, C: Clock>(
+        socket_path: P,
+        timeout: Duration,
+        clock: &C,
+    ) -> Result<Self> {
+        let start = clock.now();
 
         loop {
             match UnixStream::connect(socket_path.as_ref()) {
                 Ok(stream) => {
                     stream.set_nonblocking(true)?;
-                    return Ok(Self { stream });
+                    return Ok(Self {
+                        stream,
+                        socket_path: socket_path.as_ref().to_path_buf(),
+                        read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+                        in_flight: false,
+                        stale: false,
+                        leftover: Vec::new(),
+                    });
                 }
                 Err(e)
                     if e.kind() == ErrorKind::NotFound
                         || e.kind() == ErrorKind::ConnectionRefused =>
                 {
-                    if start.elapsed() >= timeout {
-                        return Err(Error::Agent(format!("Connection timed out: {e}")));
+                    if clock.now().duration_since(start) >= timeout {
+                        return Err(Error::Timeout {
+                            operation: format!("Connecting to {}", socket_path.as_ref().display()),
+                            after: timeout,
+                        });
                     }
-                    std::thread::sleep(Duration::from_millis(100)); // wait before retry
+                    clock.sleep(CONNECT_RETRY_INTERVAL); // wait before retry
                 }
                 Err(e) => return Err(e.into()),
             }
         }
     }
 
-    pub(crate) fn send_request(&mut self, data: &[u8]) -> Result<()> {
-        self.stream.write_all(data)?;
-        self.stream.flush()?;
+    /// Connect to the API socket again, replacing the connection closed by `firecracker`
+    /// or out of sync.
+    fn reconnect(&mut self) -> Result<()> {
+        let stream = UnixStream::connect(&self.socket_path)?;
+        stream.set_nonblocking(true)?;
+        self.stream = stream;
+        self.leftover.clear();
+        self.stale = false;
         Ok(())
     }
 
-    pub(crate) fn recv_response(&mut self) -> Result<Vec<u8>> {
-        let mut buf = [0u8; MAX_BUFFER_SIZE];
-        let mut vec: Vec<u8> = Vec::new();
+    pub(crate) fn send_request(&mut self, data: &[u8]) -> Result<()> {
+        self.send_request_with_clock(data, SEND_TIMEOUT, &SystemClock)
+    }
 
-        loop {
-            match self.stream.read(&mut buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    vec.extend_from_slice(&mut buf);
-                    if n < MAX_BUFFER_SIZE {
-                        // No need for checking again
-                        break;
+    /// Send `data`, failing with [`Error::Timeout`] if the socket buffer stays full for
+    /// `timeout` on `clock`.
+    fn send_request_with_clock<C: Clock>(
+        &mut self,
+        mut data: &[u8],
+        timeout: Duration,
+        clock: &C,
+    ) -> Result<()> {
+        let deadline = clock.now() + timeout;
+        while !data.is_empty() {
+            match self.stream.write(data) {
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::WriteZero).into()),
+                Ok(n) => data = &data[n..],
+                // interrupted by a signal
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                // the socket buffer is full until `firecracker` reads from it
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                    let left = deadline.saturating_duration_since(clock.now());
+                    if left.is_zero() {
+                        return Err(Error::Timeout {
+                            operation: "Sending request to firecracker".into(),
+                            after: timeout,
+                        });
                     }
+                    self.poll(libc::POLLOUT, Some(left))?;
+                }
+                Err(ref e) if is_closed(e) => return Err(self.closed(0)),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn recv_response(&mut self) -> Result<Vec<u8>> {
+        let (mut buf, mut complete) = self.resume_response()?;
+
+        while !complete {
+            match self.stream.read(buf.spare()?) {
+                Ok(0) => return Err(self.closed(buf.filled)),
+                Ok(n) => complete = buf.advance(n)?,
+                // interrupted by a signal
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                // nothing to read yet
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                    self.poll(libc::POLLIN, None)?;
+                }
+                Err(ref e) if is_closed(e) => return Err(self.closed(buf.filled)),
+                Err(e) => {
+                    return Err(Error::AgentIO {
+                        context: "Bad read from socket".into(),
+                        source: e,
+                    })
                 }
-                Err(ref e) if e.kind() == ErrorKind::WouldBlock => continue,
-                Err(e) => return Err(Error::Agent(format!("Bad read from socket: {e}"))),
             }
         }
 
-        Ok(vec)
+        Ok(self.finish_response(buf))
     }
 
     pub(crate) fn event<E: EventTrait>(
         &mut self,
         event: E,
     ) -> Result<<E as ResponseTrait>::Payload> {
-        self.send_request(&event.encode()?)?;
-        let response = self.recv_response()?;
+        let response = self.raw(&event.encode()?)?;
         E::decode(&response)
     }
+
+    /// Send the encoded `request` and receive its raw response, with the same handling of
+    /// stale and closed connections as [`SocketAgent::event`].
+    pub(crate) fn raw(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        if self.in_flight {
+            // interleaved with a request still waiting for its response, e.g. through a
+            // cancelled future: both would read the other's response
+            return Err(Error::Agent(
+                "concurrent request on single connection".into(),
+            ));
+        }
+        if self.stale {
+            // the previous request failed halfway, and the rest of its response might
+            // still arrive: start over on a new connection
+            self.reconnect()?;
+        }
+        match self.round_trip(request) {
+            // retry once on a new connection if `firecracker` closed this one between events
+            Err((e, sent)) if can_resend(&e, request, sent) => match self.reconnect() {
+                Ok(()) => self.round_trip(request).map_err(|(e, _)| e),
+                Err(_) => Err(e),
+            },
+            result => result.map_err(|(e, _)| e),
+        }
+    }
+
+    /// Send `request` and receive its response. A failure comes with whether the request
+    /// was sent whole, see [`can_resend`].
+    fn round_trip(&mut self, request: &[u8]) -> std::result::Result<Vec<u8>, (Error, bool)> {
+        self.in_flight = true;
+        let result = match self.send_request(request) {
+            Ok(()) => self.recv_response().map_err(|e| (e, true)),
+            Err(e) => Err((e, false)),
+        };
+        self.in_flight = false;
+        self.stale = result.as_ref().is_err_and(|(e, _)| leaves_stale(e));
+        result
+    }
+
+    /// Wait until the socket is ready for `events` (`POLLIN` / `POLLOUT`), or for `timeout`
+    /// if any, instead of spinning on the nonblocking socket.
+    fn poll(&self, events: libc::c_short, timeout: Option<Duration>) -> Result<()> {
+        let mut fd = libc::pollfd {
+            fd: self.stream.as_raw_fd(),
+            events,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |timeout| {
+            // rounded up, so that the deadline has passed once it times out
+            timeout
+                .as_micros()
+                .div_ceil(1000)
+                .try_into()
+                .unwrap_or(libc::c_int::MAX)
+        });
+        // SAFETY: `fd` is a valid pointer to a single `pollfd`.
+        if unsafe { libc::poll(&mut fd, 1, timeout) } == -1 {
+            let e = std::io::Error::last_os_error();
+            // interrupted by a signal, the caller tries again
+            if e.kind() != ErrorKind::Interrupted {
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "_rt-std")]
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "models")]
+    use std::{env, process::Command, sync::LazyLock};
     use std::{
-        env, fs,
+        fs,
         io::{Read, Write},
         os::unix::net::UnixListener,
         path::Path,
-        process::Command,
-        sync::LazyLock,
         time::Duration,
     };
 
     use crate::{
-        agent::MAX_BUFFER_SIZE,
+        agent::{CONNECT_RETRY_INTERVAL, DEFAULT_READ_BUFFER_SIZE, MAX_RESPONSE_SIZE},
+        clock::MockClock,
+        Error, Result,
+    };
+    #[cfg(feature = "models")]
+    use crate::{
         events::{GetFirecrackerVersion, ResponseTrait},
         models::Empty,
-        Result,
     };
 
     use super::SocketAgent;
 
+    #[cfg(feature = "models")]
     const FIRECRACKER: LazyLock<String> = LazyLock::new(|| {
         dotenvy::dotenv().ok();
         env::var("FIRECRACKER").unwrap()
@@ -106,7 +247,7 @@ mod tests {
         println!("Server listening on {}", api_sock.as_ref().display());
 
         let (mut stream, _) = listener.accept().unwrap();
-        let mut buffer = [0; MAX_BUFFER_SIZE];
+        let mut buffer = [0; 1024];
         let n = stream.read(&mut buffer)?;
         if n > 0 {
             stream.write_all(&buffer[0..n])?;
@@ -115,10 +256,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn connect_timeout() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-nonexistent.socket";
+        let clock = MockClock::new();
+
+        let result = SocketAgent::new_with_clock(API_SOCK, Duration::from_secs(3), &clock);
+
+        assert!(matches!(result, Err(Error::Timeout { .. })));
+        assert_eq!(clock.sleeps(), vec![CONNECT_RETRY_INTERVAL; 30]);
+    }
+
     #[test]
     fn test_echo() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-std-echo.socket";
-        const DATA: &'static str = "Hello, world!";
+        const DATA: &'static str = "HTTP/1.1 200 \r\nContent-Length: 13\r\n\r\nHello, world!";
         while fs::exists(API_SOCK).unwrap() {
             let _ = fs::remove_file(API_SOCK);
         }
@@ -136,6 +288,426 @@ mod tests {
         }
     }
 
+    #[test]
+    fn recv_response_length() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-length.socket";
+        let body = [b'x'; 100];
+        assert_ne!(body.len() % DEFAULT_READ_BUFFER_SIZE, 0);
+        let mut data =
+            format!("HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        data.extend_from_slice(&body);
+        let _ = fs::remove_file(API_SOCK);
+
+        let server_handle = std::thread::spawn(|| echo_server(API_SOCK));
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        agent.send_request(&data).unwrap();
+        let response = agent.recv_response().unwrap();
+
+        assert_eq!(response, data);
+        // sized from `Content-Length` up front, without growing
+        assert_eq!(response.capacity(), response.len());
+
+        server_handle.join().unwrap().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn recv_response_large() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-large.socket";
+        let body = vec![b'x'; 100 * 1024];
+        let mut data =
+            format!("HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        data.extend_from_slice(&body);
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // a 100 KB response to every request
+        let response = data.clone();
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Ok(1..) = stream.read(&mut [0; 1024]) {
+                stream.write_all(&response).unwrap();
+            }
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        // 0 is taken as 1
+        for read_buffer_size in [DEFAULT_READ_BUFFER_SIZE, 64, 0] {
+            agent.set_read_buffer_size(read_buffer_size);
+            agent
+                .send_request(b"GET /vm/config HTTP/1.1\r\n\r\n")
+                .unwrap();
+            let response = agent.recv_response().unwrap();
+            assert_eq!(response, data);
+        }
+
+        drop(agent);
+        server_handle.join().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn recv_response_too_large() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-too-large.socket";
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // lengths beyond `MAX_RESPONSE_SIZE`, including one overflowing with the headers
+        let server_handle = std::thread::spawn(move || {
+            for content_length in [MAX_RESPONSE_SIZE, usize::MAX] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0; 1024]).unwrap();
+                let response = format!("HTTP/1.1 200 \r\nContent-Length: {content_length}\r\n\r\n");
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        for _ in 0..2 {
+            let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+            agent
+                .send_request(b"GET /vm/config HTTP/1.1\r\n\r\n")
+                .unwrap();
+            assert!(matches!(
+                agent.recv_response(),
+                Err(Error::Agent(e)) if e == format!("Response exceeds {MAX_RESPONSE_SIZE} bytes")
+            ));
+        }
+
+        server_handle.join().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn recv_response_chunked() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-chunked.socket";
+        let mut data = b"HTTP/1.1 200 \r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for _ in 0..3 {
+            data.extend_from_slice(b"28\r\n");
+            data.extend_from_slice(&[b'x'; 0x28]);
+            data.extend_from_slice(b"\r\n");
+        }
+        data.extend_from_slice(b"0\r\n\r\n");
+        let _ = fs::remove_file(API_SOCK);
+
+        let server_handle = std::thread::spawn(|| echo_server(API_SOCK));
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        agent.send_request(&data).unwrap();
+        let response = agent.recv_response().unwrap();
+
+        assert_eq!(response, data);
+
+        server_handle.join().unwrap().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn recv_response_pipelined() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-pipelined.socket";
+        let responses: [&[u8]; 3] = [
+            b"HTTP/1.1 204 \r\n\r\n",
+            b"HTTP/1.1 200 \r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+            b"HTTP/1.1 200 \r\nContent-Length: 13\r\n\r\nHello, world!",
+        ];
+        let _ = fs::remove_file(API_SOCK);
+
+        // all the responses echoed in a single write
+        let server_handle = std::thread::spawn(|| echo_server(API_SOCK));
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        agent.send_request(&responses.concat()).unwrap();
+
+        for response in responses {
+            assert_eq!(agent.recv_response().unwrap(), response);
+        }
+        assert!(agent.leftover.is_empty());
+
+        server_handle.join().unwrap().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
+    #[test]
+    fn connection_close() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-close.socket";
+        const BODY: &str = r#"{"firecracker_version":"1.10.1"}"#;
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // a full response, then the connection is closed right after the body
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            let response = format!(
+                "HTTP/1.0 200 \r\nConnection: close\r\nContent-Length: {}\r\n\r\n{BODY}",
+                BODY.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        let version = agent.event(GetFirecrackerVersion(&Empty)).unwrap();
+        assert_eq!(version.firecracker_version, "1.10.1");
+        server_handle.join().unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                agent.event(GetFirecrackerVersion(&Empty)),
+                Err(Error::Agent(e)) if e == "connection closed by firecracker"
+            ));
+        }
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
+    #[test]
+    fn keep_alive() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-keep-alive.socket";
+        const BODY: &str = r#"{"firecracker_version":"1.10.1"}"#;
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // answer every request on the same connection
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let mut requests = 0;
+            while let Ok(n @ 1..) = stream.read(&mut buf) {
+                let request = String::from_utf8_lossy(&buf[..n]);
+                assert!(request.starts_with("GET /version HTTP/1.1\r\n"));
+                assert!(request.contains("\r\nConnection: keep-alive\r\n"));
+                let response = format!(
+                    "HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n{BODY}",
+                    BODY.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+                requests += 1;
+            }
+            requests
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        for _ in 0..2 {
+            let version = agent.event(GetFirecrackerVersion(&Empty)).unwrap();
+            assert_eq!(version.firecracker_version, "1.10.1");
+        }
+
+        drop(agent);
+        assert_eq!(server_handle.join().unwrap(), 2);
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
+    #[test]
+    fn reconnect() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-reconnect.socket";
+        const BODY: &str = r#"{"firecracker_version":"1.10.1"}"#;
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // the first connection is closed without answering, the second one is answered
+        let server_handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            drop(stream);
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            let response = format!(
+                "HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n{BODY}",
+                BODY.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        let version = agent.event(GetFirecrackerVersion(&Empty)).unwrap();
+        assert_eq!(version.firecracker_version, "1.10.1");
+
+        server_handle.join().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
+    #[test]
+    fn no_resend_after_sent() {
+        use std::io::ErrorKind;
+
+        use crate::{
+            events::CreateSyncAction,
+            models::{ActionType, InstanceActionInfo},
+        };
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-no-resend.socket";
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // the request is read, then the connection is closed without answering
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            drop(stream);
+            std::thread::sleep(Duration::from_millis(100));
+            listener.set_nonblocking(true).unwrap();
+            // no second connection replaying it
+            matches!(listener.accept(), Err(e) if e.kind() == ErrorKind::WouldBlock)
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        let action = InstanceActionInfo::new(ActionType::InstanceStart);
+        assert!(matches!(
+            agent.event(CreateSyncAction(&action)),
+            Err(Error::Agent(e)) if e == "connection closed by firecracker"
+        ));
+
+        assert!(server_handle.join().unwrap());
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
+    #[test]
+    fn concurrent_request() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-concurrent.socket";
+        let _ = fs::remove_file(API_SOCK);
+        let _listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // as left by a request whose response was never read
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        agent.in_flight = true;
+        for _ in 0..2 {
+            assert!(matches!(
+                agent.event(GetFirecrackerVersion(&Empty)),
+                Err(Error::Agent(e)) if e == "concurrent request on single connection"
+            ));
+        }
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
+    #[test]
+    fn reconnect_after_error() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-reconnect-error.socket";
+        const BODY: &str = r#"{"firecracker_version":"1.10.1"}"#;
+
+        // failing a typed event, then a raw request
+        for raw in [false, true] {
+            let _ = fs::remove_file(API_SOCK);
+            let listener = UnixListener::bind(API_SOCK).unwrap();
+
+            // the first connection gets an unparsable response and is left open,
+            // the second one is answered
+            let server_handle = std::thread::spawn(move || {
+                let (mut first, _) = listener.accept().unwrap();
+                let _ = first.read(&mut [0; 1024]).unwrap();
+                first.write_all(b"garbage\r\n\r\n").unwrap();
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0; 1024]).unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n{BODY}",
+                    BODY.len()
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+                first
+            });
+
+            let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+            if raw {
+                let request = crate::events::encode_raw("GET", "/version", None);
+                assert!(agent.raw(&request).is_err());
+            } else {
+                assert!(agent.event(GetFirecrackerVersion(&Empty)).is_err());
+            }
+            let version = agent.event(GetFirecrackerVersion(&Empty)).unwrap();
+            assert_eq!(version.firecracker_version, "1.10.1");
+
+            drop(server_handle.join().unwrap());
+        }
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn send_request_transient() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-transient.socket";
+        const LEN: usize = 1024 * 1024;
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+        // slow to read, so that the request fills the socket buffer
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+            let mut request = vec![0; LEN];
+            stream.read_exact(&mut request).unwrap();
+            request
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        agent.send_request(&vec![b'x'; LEN]).unwrap();
+
+        assert_eq!(server_handle.join().unwrap(), vec![b'x'; LEN]);
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn send_request_timeout() {
+        use crate::clock::{Clock, MockClock};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-send-timeout.socket";
+        const LEN: usize = 1024 * 1024;
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+
+        // the request fills the socket buffer, then the time runs out each time before
+        // `firecracker` reads a bit of it, waking the agent up
+        let clock = MockClock::new();
+        let timeout = Duration::from_secs(5);
+        stream
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        let result = std::thread::scope(|scope| {
+            let sender =
+                scope.spawn(|| agent.send_request_with_clock(&vec![b'x'; LEN], timeout, &clock));
+            while !sender.is_finished() {
+                clock.sleep(timeout);
+                // slower than the agent, which fills the buffer again
+                std::thread::sleep(Duration::from_millis(1));
+                let _ = stream.read(&mut [0; 4096]);
+            }
+            sender.join().unwrap()
+        });
+        assert!(matches!(
+            result,
+            Err(Error::Timeout { after, .. }) if after == timeout
+        ));
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[test]
+    fn premature_eof() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-std-eof.socket";
+        let _ = fs::remove_file(API_SOCK);
+        let listener = UnixListener::bind(API_SOCK).unwrap();
+
+        // the connection is closed halfway through the body
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 \r\nContent-Length: 100\r\n\r\nHello")
+                .unwrap();
+        });
+
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap();
+        agent
+            .send_request(b"GET /version HTTP/1.1\r\n\r\n")
+            .unwrap();
+        assert!(matches!(
+            agent.recv_response(),
+            Err(Error::Agent(e)) if e == "connection closed by firecracker after 43 bytes of the response"
+        ));
+
+        server_handle.join().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
     #[test]
     fn test_get_firecracker_version() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-std-version.socket";
@@ -166,6 +738,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "models")]
     #[test]
     fn test_get_firecracker_version_event() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-std-version-event.socket";
@@ -3,73 +3,157 @@ use std::{io::ErrorKind, path::Path, time::Duration};
 use tokio::{io::AsyncWriteExt, net::UnixStream};
 
 use crate::{
+    clock::{Clock, SystemClock},
     events::{EventTrait, ResponseTrait},
     Error, Result,
 };
 
-use super::{SocketAgent, MAX_BUFFER_SIZE};
+use super::{
+    can_resend, is_closed, leaves_stale, SocketAgent, CONNECT_RETRY_INTERVAL,
+    DEFAULT_READ_BUFFER_SIZE,
+};
 
 impl SocketAgent {
     pub(crate) async fn new<P: AsRef<Path>>(socket_path: P, timeout: Duration) -> Result<Self> {
-        // wait the socket
-        let wait_future = async { while !std::fs::exists(&socket_path).is_ok_and(|x| x) {} };
+        Self::new_with_clock(socket_path, timeout, &SystemClock).await
+    }
 
-        match tokio::time::timeout(timeout, wait_future).await {
-            Ok(()) => {
-                let stream = UnixStream::connect(socket_path).await?;
-                Ok(Self { stream })
+    /// Connect to `socket_path`, retrying until `timeout` elapsed on `clock`.
+    pub(crate) async fn new_with_clock<P: AsRef<Path>, C: Clock>(
+        socket_path: P,
+        timeout: Duration,
+        clock: &C,
+    ) -> Result<Self> {
+        let start = clock.now();
+
+        loop {
+            match UnixStream::connect(socket_path.as_ref()).await {
+                Ok(stream) => {
+                    return Ok(Self {
+                        stream,
+                        socket_path: socket_path.as_ref().to_path_buf(),
+                        read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+                        in_flight: false,
+                        stale: false,
+                        leftover: Vec::new(),
+                    });
+                }
+                Err(e)
+                    if e.kind() == ErrorKind::NotFound
+                        || e.kind() == ErrorKind::ConnectionRefused =>
+                {
+                    if clock.now().duration_since(start) >= timeout {
+                        return Err(Error::Timeout {
+                            operation: format!("Connecting to {}", socket_path.as_ref().display()),
+                            after: timeout,
+                        });
+                    }
+                    clock.sleep(CONNECT_RETRY_INTERVAL).await; // wait before retry
+                }
+                Err(e) => return Err(e.into()),
             }
-            Err(e) => Err(Error::Agent(format!("Connection timed out: {e}"))),
         }
     }
 
-    pub(crate) async fn send_request(&mut self, data: &[u8]) -> Result<()> {
-        self.stream.write_all(data).await?;
-        self.stream.flush().await?;
+    /// Connect to the API socket again, replacing the connection closed by `firecracker`
+    /// or out of sync.
+    async fn reconnect(&mut self) -> Result<()> {
+        self.stream = UnixStream::connect(&self.socket_path).await?;
+        self.leftover.clear();
+        self.stale = false;
         Ok(())
     }
 
+    pub(crate) async fn send_request(&mut self, data: &[u8]) -> Result<()> {
+        let result = match self.stream.write_all(data).await {
+            Ok(()) => self.stream.flush().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(ref e) if is_closed(e) => Err(self.closed(0)),
+            result => Ok(result?),
+        }
+    }
+
     pub(crate) async fn recv_response(&mut self) -> Result<Vec<u8>> {
-        let mut buf = [0u8; MAX_BUFFER_SIZE];
-        let mut vec: Vec<u8> = Vec::new();
+        let (mut buf, mut complete) = self.resume_response()?;
 
-        loop {
-            self.stream
-                .readable()
-                .await
-                .map_err(|e| Error::Agent(format!("Waiting for stream become readable: {e}")))?;
-
-            match self.stream.try_read(&mut buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    vec.extend_from_slice(&mut buf);
-                    if n < MAX_BUFFER_SIZE {
-                        // No need for checking again
-                        break;
-                    }
-                }
+        while !complete {
+            self.stream.readable().await.map_err(|e| Error::AgentIO {
+                context: "Waiting for stream become readable".into(),
+                source: e,
+            })?;
+
+            match self.stream.try_read(buf.spare()?) {
+                Ok(0) => return Err(self.closed(buf.filled)),
+                Ok(n) => complete = buf.advance(n)?,
                 Err(ref e) if e.kind() == ErrorKind::WouldBlock => continue,
-                Err(e) => return Err(Error::Agent(format!("Bad read from socket: {e}"))),
+                Err(ref e) if is_closed(e) => return Err(self.closed(buf.filled)),
+                Err(e) => {
+                    return Err(Error::AgentIO {
+                        context: "Bad read from socket".into(),
+                        source: e,
+                    })
+                }
             }
         }
 
-        Ok(vec)
+        Ok(self.finish_response(buf))
     }
 
     pub(crate) async fn event<E: EventTrait>(
         &mut self,
         event: E,
     ) -> Result<<E as ResponseTrait>::Payload> {
-        self.send_request(&event.encode()?).await?;
-        let response = self.recv_response().await?;
+        let response = self.raw(&event.encode()?).await?;
         E::decode(&response)
     }
+
+    /// Send the encoded `request` and receive its raw response, with the same handling of
+    /// stale and closed connections as [`SocketAgent::event`].
+    pub(crate) async fn raw(&mut self, request: &[u8]) -> Result<Vec<u8>> {
+        if self.in_flight {
+            // interleaved with a request still waiting for its response, e.g. through a
+            // cancelled future: both would read the other's response
+            return Err(Error::Agent(
+                "concurrent request on single connection".into(),
+            ));
+        }
+        if self.stale {
+            // the previous request failed halfway, and the rest of its response might
+            // still arrive: start over on a new connection
+            self.reconnect().await?;
+        }
+        match self.round_trip(request).await {
+            // retry once on a new connection if `firecracker` closed this one between events
+            Err((e, sent)) if can_resend(&e, request, sent) => match self.reconnect().await {
+                Ok(()) => self.round_trip(request).await.map_err(|(e, _)| e),
+                Err(_) => Err(e),
+            },
+            result => result.map_err(|(e, _)| e),
+        }
+    }
+
+    /// Send `request` and receive its response. A failure comes with whether the request
+    /// was sent whole, see [`can_resend`].
+    async fn round_trip(&mut self, request: &[u8]) -> std::result::Result<Vec<u8>, (Error, bool)> {
+        self.in_flight = true;
+        let result = match self.send_request(request).await {
+            Ok(()) => self.recv_response().await.map_err(|e| (e, true)),
+            Err(e) => Err((e, false)),
+        };
+        self.in_flight = false;
+        self.stale = result.as_ref().is_err_and(|(e, _)| leaves_stale(e));
+        result
+    }
 }
 
 #[cfg(feature = "_rt-tokio")]
 #[cfg(test)]
 mod tests {
-    use std::{env, fs, path::Path, process::Command, sync::LazyLock, time::Duration};
+    #[cfg(feature = "models")]
+    use std::{env, process::Command, sync::LazyLock};
+    use std::{fs, path::Path, time::Duration};
 
     use tokio::{
         io::{AsyncReadExt, AsyncWriteExt},
@@ -77,17 +161,33 @@ mod tests {
     };
 
     use crate::{
-        agent::SocketAgent,
+        agent::{SocketAgent, CONNECT_RETRY_INTERVAL, DEFAULT_READ_BUFFER_SIZE},
+        clock::MockClock,
+        Error, Result,
+    };
+    #[cfg(feature = "models")]
+    use crate::{
         events::{GetFirecrackerVersion, ResponseTrait},
         models::Empty,
-        Result,
     };
 
+    #[cfg(feature = "models")]
     const FIRECRACKER: LazyLock<String> = LazyLock::new(|| {
         dotenvy::dotenv().ok();
         env::var("FIRECRACKER").unwrap()
     });
 
+    #[tokio::test]
+    async fn connect_timeout() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-tokio-nonexistent.socket";
+        let clock = MockClock::new();
+
+        let result = SocketAgent::new_with_clock(API_SOCK, Duration::from_secs(3), &clock).await;
+
+        assert!(matches!(result, Err(Error::Timeout { .. })));
+        assert_eq!(clock.sleeps(), vec![CONNECT_RETRY_INTERVAL; 30]);
+    }
+
     async fn echo_server<P: AsRef<Path>>(api_sock: P) -> Result<()> {
         let listener = UnixListener::bind(&api_sock)?;
         println!("Server listening on {}", api_sock.as_ref().display());
@@ -111,7 +211,7 @@ mod tests {
     #[tokio::test(flavor = "multi_thread")] // important to add `flavor = "multi_thread"`
     async fn test_echo() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-tokio-echo.socket";
-        const DATA: &'static str = "Hello, world!";
+        const DATA: &'static str = "HTTP/1.1 200 \r\nContent-Length: 13\r\n\r\nHello, world!";
         let _ = fs::remove_file(API_SOCK);
 
         let server_handle = tokio::spawn(echo_server(API_SOCK));
@@ -127,6 +227,58 @@ mod tests {
         let _ = fs::remove_file(API_SOCK);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn recv_response_length() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-tokio-length.socket";
+        let body = [b'x'; 100];
+        assert_ne!(body.len() % DEFAULT_READ_BUFFER_SIZE, 0);
+        let mut data =
+            format!("HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        data.extend_from_slice(&body);
+        let _ = fs::remove_file(API_SOCK);
+
+        let server_handle = tokio::spawn(echo_server(API_SOCK));
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3))
+            .await
+            .unwrap();
+        agent.send_request(&data).await.unwrap();
+        let response = agent.recv_response().await.unwrap();
+
+        assert_eq!(response, data);
+        // sized from `Content-Length` up front, without growing
+        assert_eq!(response.capacity(), response.len());
+
+        server_handle.await.unwrap().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn recv_response_pipelined() {
+        const API_SOCK: &str = "/tmp/firecracker-sdk-test-agent-tokio-pipelined.socket";
+        let responses: [&[u8]; 3] = [
+            b"HTTP/1.1 204 \r\n\r\n",
+            b"HTTP/1.1 200 \r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+            b"HTTP/1.1 200 \r\nContent-Length: 13\r\n\r\nHello, world!",
+        ];
+        let _ = fs::remove_file(API_SOCK);
+
+        // all the responses echoed in a single write
+        let server_handle = tokio::spawn(echo_server(API_SOCK));
+        let mut agent = SocketAgent::new(API_SOCK, Duration::from_secs(3))
+            .await
+            .unwrap();
+        agent.send_request(&responses.concat()).await.unwrap();
+
+        for response in responses {
+            assert_eq!(agent.recv_response().await.unwrap(), response);
+        }
+        assert!(agent.leftover.is_empty());
+
+        server_handle.await.unwrap().unwrap();
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "models")]
     #[tokio::test]
     async fn test_get_firecracker_version() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-tokio-version.socket";
@@ -156,6 +308,7 @@ mod tests {
         let _ = fs::remove_file(API_SOCK);
     }
 
+    #[cfg(feature = "models")]
     #[tokio::test]
     async fn test_get_firecracker_version_event() {
         const API_SOCK: &'static str = "/tmp/firecracker-sdk-test-agent-tokio-version-event.socket";
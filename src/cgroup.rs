@@ -0,0 +1,210 @@
+//! Post-spawn cgroup resource control of `firecracker` via `cgroups-rs`.
+//!
+//! This is complementary to the `--cgroup` flags of `jailer`, which can only write plain
+//! `<file>=<value>` pairs before `firecracker` is exec'ed. Here `firecracker` is moved into
+//! a dedicated cgroup after it has been spawned, e.g. to shape the I/O weight, `memory.high`
+//! or `cpu.max` of an already running microVM.
+
+use cgroups_rs::{
+    hierarchies,
+    memory::{MemController, SetMemory},
+    Cgroup, CgroupPid, MaxValue, Resources,
+};
+
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Default)]
+pub struct CgroupOption {
+    // Name of the cgroup, relative to the root of the hierarchy.
+    name: String,
+
+    // `cpu.max`: quota and period, in microseconds. A quota of -1 means no limit.
+    cpu_max: Option<(i64, u64)>,
+
+    // `memory.max`, in bytes.
+    memory_max: Option<i64>,
+
+    // `memory.high`, in bytes.
+    memory_high: Option<i64>,
+
+    // `io.weight`, within 1..=10000.
+    io_weight: Option<u16>,
+}
+
+impl CgroupOption {
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        Self {
+            name: name.as_ref().into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn cpu_max(&mut self, quota_us: i64, period_us: u64) -> &mut Self {
+        self.cpu_max = Some((quota_us, period_us));
+        self
+    }
+
+    pub fn memory_max(&mut self, memory_max: i64) -> &mut Self {
+        self.memory_max = Some(memory_max);
+        self
+    }
+
+    pub fn memory_high(&mut self, memory_high: i64) -> &mut Self {
+        self.memory_high = Some(memory_high);
+        self
+    }
+
+    pub fn io_weight(&mut self, io_weight: u16) -> &mut Self {
+        self.io_weight = Some(io_weight);
+        self
+    }
+
+    /// Create the cgroup, set its controllers and move process `pid` into it.
+    pub(crate) fn apply(&self, pid: u32) -> Result<Cgroup> {
+        let resources = self.resources()?;
+        let cgroup = Cgroup::new(hierarchies::auto(), &self.name).map_err(cgroup_error)?;
+
+        if let Err(e) = cgroup
+            .apply(&resources)
+            .map_err(cgroup_error)
+            .and_then(|()| self.set_memory_high(&cgroup))
+            .and_then(|()| {
+                cgroup
+                    .add_task_by_tgid(CgroupPid::from(pid as u64))
+                    .map_err(cgroup_error)
+            })
+        {
+            let _ = cgroup.delete();
+            return Err(e);
+        }
+
+        Ok(cgroup)
+    }
+
+    /// Check the options and map them to the controller values written by `cgroups-rs`.
+    fn resources(&self) -> Result<Resources> {
+        if self.name.is_empty() {
+            return Err(Error::Configuration("cgroup name must not be empty".into()));
+        }
+
+        let mut resources = Resources::default();
+        if let Some((quota, period)) = self.cpu_max {
+            if quota != -1 && quota <= 0 {
+                return Err(Error::Configuration(format!(
+                    "cgroup cpu.max quota must be positive or -1, got {quota}"
+                )));
+            }
+            if !(1_000..=1_000_000).contains(&period) {
+                return Err(Error::Configuration(format!(
+                    "cgroup cpu.max period must be within 1000..=1000000 us, got {period}"
+                )));
+            }
+            resources.cpu.quota = Some(quota);
+            resources.cpu.period = Some(period);
+        }
+        if let Some(memory_max) = self.memory_max {
+            if memory_max <= 0 {
+                return Err(Error::Configuration(format!(
+                    "cgroup memory.max must be positive, got {memory_max}"
+                )));
+            }
+            resources.memory.memory_hard_limit = Some(memory_max);
+        }
+        if let Some(memory_high) = self.memory_high {
+            if memory_high <= 0 {
+                return Err(Error::Configuration(format!(
+                    "cgroup memory.high must be positive, got {memory_high}"
+                )));
+            }
+        }
+        if let Some(io_weight) = self.io_weight {
+            if !(1..=10_000).contains(&io_weight) {
+                return Err(Error::Configuration(format!(
+                    "cgroup io.weight must be within 1..=10000, got {io_weight}"
+                )));
+            }
+            resources.blkio.weight = Some(io_weight);
+        }
+        Ok(resources)
+    }
+
+    fn set_memory_high(&self, cgroup: &Cgroup) -> Result<()> {
+        let Some(memory_high) = self.memory_high else {
+            return Ok(());
+        };
+        let memory: &MemController = cgroup
+            .controller_of()
+            .ok_or_else(|| Error::Configuration("memory controller not available".into()))?;
+        memory
+            .set_mem(SetMemory {
+                low: None,
+                high: Some(MaxValue::Value(memory_high)),
+                min: None,
+                max: None,
+            })
+            .map_err(cgroup_error)
+    }
+}
+
+fn cgroup_error(e: cgroups_rs::error::Error) -> Error {
+    Error::Instance(format!("cgroup: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CgroupOption;
+    use crate::Error;
+
+    #[test]
+    fn resources() {
+        let resources = CgroupOption::new("vm0")
+            .cpu_max(50_000, 100_000)
+            .memory_max(512 << 20)
+            .memory_high(256 << 20)
+            .io_weight(200)
+            .resources()
+            .unwrap();
+        assert_eq!(resources.cpu.quota, Some(50_000));
+        assert_eq!(resources.cpu.period, Some(100_000));
+        assert_eq!(resources.memory.memory_hard_limit, Some(512 << 20));
+        assert_eq!(resources.blkio.weight, Some(200));
+
+        // unset options are left to the defaults of the cgroup
+        let resources = CgroupOption::new("vm0").resources().unwrap();
+        assert_eq!(resources.cpu.quota, None);
+        assert_eq!(resources.cpu.period, None);
+        assert_eq!(resources.memory.memory_hard_limit, None);
+        assert_eq!(resources.blkio.weight, None);
+
+        // no limit on the quota
+        let resources = CgroupOption::new("vm0")
+            .cpu_max(-1, 100_000)
+            .resources()
+            .unwrap();
+        assert_eq!(resources.cpu.quota, Some(-1));
+        assert_eq!(resources.cpu.period, Some(100_000));
+    }
+
+    #[test]
+    fn invalid_resources() {
+        let mut options = vec![CgroupOption::new("")];
+        for (quota, period) in [
+            (0, 100_000),
+            (-2, 100_000),
+            (50_000, 999),
+            (50_000, 1_000_001),
+        ] {
+            options.push(CgroupOption::new("vm0").cpu_max(quota, period).clone());
+        }
+        options.push(CgroupOption::new("vm0").memory_max(0).clone());
+        options.push(CgroupOption::new("vm0").memory_high(-1).clone());
+        options.push(CgroupOption::new("vm0").io_weight(0).clone());
+        options.push(CgroupOption::new("vm0").io_weight(10_001).clone());
+        for option in options {
+            assert!(
+                matches!(option.resources(), Err(Error::Configuration(_))),
+                "{option:?}"
+            );
+        }
+    }
+}
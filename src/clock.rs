@@ -0,0 +1,82 @@
+//! Source of time for the retry and timeout logic, replaced by a virtual clock in tests
+
+use std::time::{Duration, Instant};
+
+pub(crate) trait Clock {
+    fn now(&self) -> Instant;
+
+    #[cfg(feature = "_rt-std")]
+    fn sleep(&self, duration: Duration);
+
+    #[cfg(feature = "_rt-async")]
+    fn sleep(&self, duration: Duration) -> impl std::future::Future<Output = ()> + Send;
+}
+
+/// The real clock. Zero-sized, so the production path is monomorphized to plain
+/// `Instant::now()` and runtime sleeps.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    #[cfg(feature = "_rt-std")]
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+
+    #[cfg(feature = "_rt-async")]
+    async fn sleep(&self, duration: Duration) {
+        #[cfg(feature = "_rt-tokio")]
+        tokio::time::sleep(duration).await;
+        #[cfg(feature = "_rt-async-std")]
+        async_std::task::sleep(duration).await;
+    }
+}
+
+/// Virtual clock whose time only advances when sleeping, recording every sleep.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct MockClock {
+    now: std::sync::Mutex<Instant>,
+    sleeps: std::sync::Mutex<Vec<Duration>>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            now: std::sync::Mutex::new(Instant::now()),
+            sleeps: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+
+    fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+        self.sleeps.lock().unwrap().push(duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    #[cfg(feature = "_rt-std")]
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+
+    #[cfg(feature = "_rt-async")]
+    fn sleep(&self, duration: Duration) -> impl std::future::Future<Output = ()> + Send {
+        self.advance(duration);
+        std::future::ready(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Copier of the guest serial console output of `firecracker`
+
+use std::{
+    io::{Read, Write},
+    process::ChildStdout,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use log::warn;
+
+/// Destination of the console output, shared so that a respawned `firecracker` keeps
+/// writing to it.
+pub(crate) type ConsoleWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// How long teardown waits for the copier to drain the console after `firecracker` exited.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Background thread / task copying the stdout of `firecracker` into a [`ConsoleWriter`].
+/// Dropping it blocks the current thread (even within an async runtime) until the copier
+/// reaches the end of the output, for up to [`JOIN_TIMEOUT`], then detaches the copier.
+pub(crate) struct ConsolePipe {
+    done: Option<mpsc::Receiver<()>>,
+}
+
+impl ConsolePipe {
+    pub(crate) fn spawn(stdout: ChildStdout, writer: ConsoleWriter) -> Self {
+        let (done_tx, done_rx) = mpsc::channel();
+        let copy = move || {
+            copy_console(stdout, &writer);
+            let _ = done_tx.send(());
+        };
+
+        #[cfg(feature = "_rt-std")]
+        std::thread::spawn(copy);
+        #[cfg(feature = "_rt-tokio")]
+        tokio::task::spawn_blocking(copy);
+        #[cfg(feature = "_rt-async-std")]
+        async_std::task::spawn_blocking(copy);
+
+        Self {
+            done: Some(done_rx),
+        }
+    }
+
+    /// Let the copier run on without waiting for it, e.g. when `firecracker` outlives the instance.
+    pub(crate) fn detach(mut self) {
+        self.done = None;
+    }
+}
+
+impl Drop for ConsolePipe {
+    // blocking, but bounded: `firecracker` has exited (or been killed) by now, so the copier
+    // only has what's left in the pipe to copy
+    fn drop(&mut self) {
+        if let Some(ref done) = self.done {
+            if done.recv_timeout(JOIN_TIMEOUT).is_err() {
+                warn!(
+                    "Console output of `firecracker` still open after {JOIN_TIMEOUT:?}, detaching"
+                );
+            }
+        }
+    }
+}
+
+/// Copy `stdout` into `writer` until the end of the output.
+/// Keep draining `stdout` if `writer` fails, so `firecracker` never blocks on a full pipe.
+fn copy_console(mut stdout: ChildStdout, writer: &ConsoleWriter) {
+    let mut buf = [0u8; 4096];
+    let mut writer_ok = true;
+    loop {
+        let n = match stdout.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                warn!("Fail to read console output of `firecracker`: {e}");
+                break;
+            }
+        };
+        if !writer_ok {
+            continue;
+        }
+        let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.write_all(&buf[..n]).and_then(|_| writer.flush()) {
+            warn!("Fail to write console output of `firecracker`, discarding the rest: {e}");
+            writer_ok = false;
+        }
+    }
+}
+
+#[cfg(feature = "_rt-std")]
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+        sync::{Arc, Mutex},
+    };
+
+    use super::ConsolePipe;
+
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pipe_console() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut child = Command::new("echo")
+            .arg("hello console")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let pipe = ConsolePipe::spawn(
+            child.stdout.take().unwrap(),
+            Arc::new(Mutex::new(Box::new(SharedBuf(output.clone())))),
+        );
+        child.wait().unwrap();
+        drop(pipe);
+
+        assert_eq!(&*output.lock().unwrap(), b"hello console\n");
+    }
+}
@@ -0,0 +1,368 @@
+use super::{check_path_segment, EventTrait, RequestTrait, ResponseTrait};
+use crate::models::*;
+
+macro_rules! impl_event_traits {
+    // Other conditions
+    ($struct_name:ident, $method:expr, $path:expr, $req_payload:ty, $res_payload:ty) => {
+        pub struct $struct_name<'a>(pub &'a $req_payload);
+
+        impl<'a> RequestTrait for $struct_name<'a> {
+            type Payload = $req_payload;
+
+            fn method(&self) -> &'static str {
+                $method
+            }
+
+            fn path(&self) -> String {
+                $path.into()
+            }
+
+            fn payload(&self) -> &Self::Payload {
+                &self.0
+            }
+        }
+
+        impl<'a> ResponseTrait for $struct_name<'a> {
+            type Payload = $res_payload;
+        }
+
+        impl<'a> EventTrait for $struct_name<'a> {}
+
+        paste::paste! {
+            pub struct [<$struct_name Owned>](
+                pub $req_payload
+                // field!($req_payload)
+            );
+
+            impl RequestTrait for [<$struct_name Owned>] {
+                type Payload = $req_payload;
+
+                fn method(&self) -> &'static str {
+                    $method
+                }
+
+                fn path(&self) -> String {
+                    $path.into()
+                }
+
+                fn payload(&self) -> &Self::Payload {
+                    &self.0
+                }
+            }
+
+            impl ResponseTrait for [<$struct_name Owned>] {
+                type Payload = $res_payload;
+            }
+
+            impl EventTrait for [<$struct_name Owned>] {}
+        }
+    };
+
+    // Path ending with the `$id` field of the payload, checked to be a single segment
+    ($struct_name:ident, $method:expr, $path:expr, $id:ident, $req_payload:ty, $res_payload:ty) => {
+        pub struct $struct_name<'a>(pub &'a $req_payload);
+
+        impl<'a> RequestTrait for $struct_name<'a> {
+            type Payload = $req_payload;
+
+            fn method(&self) -> &'static str {
+                $method
+            }
+
+            fn path(&self) -> String {
+                format!("{}/{}", $path, self.0.$id)
+            }
+
+            fn check_path(&self) -> crate::Result<()> {
+                check_path_segment(&self.0.$id)
+            }
+
+            fn payload(&self) -> &Self::Payload {
+                &self.0
+            }
+        }
+
+        impl<'a> ResponseTrait for $struct_name<'a> {
+            type Payload = $res_payload;
+        }
+
+        impl<'a> EventTrait for $struct_name<'a> {}
+
+        paste::paste! {
+            pub struct [<$struct_name Owned>](pub $req_payload);
+
+            impl RequestTrait for [<$struct_name Owned>] {
+                type Payload = $req_payload;
+
+                fn method(&self) -> &'static str {
+                    $method
+                }
+
+                fn path(&self) -> String {
+                    format!("{}/{}", $path, self.0.$id)
+                }
+
+                fn check_path(&self) -> crate::Result<()> {
+                    check_path_segment(&self.0.$id)
+                }
+
+                fn payload(&self) -> &Self::Payload {
+                    &self.0
+                }
+            }
+
+            impl ResponseTrait for [<$struct_name Owned>] {
+                type Payload = $res_payload;
+            }
+
+            impl EventTrait for [<$struct_name Owned>] {}
+        }
+    };
+}
+
+const GET: &'static str = "GET";
+const PUT: &'static str = "PUT";
+const PATCH: &'static str = "PATCH";
+
+impl_event_traits!(DescribeInstance, GET, "/", Empty, InstanceInfo);
+impl_event_traits!(CreateSyncAction, PUT, "/actions", InstanceActionInfo, Empty);
+impl_event_traits!(DescribeBalloonConfig, GET, "/balloon", Empty, Balloon);
+impl_event_traits!(PutBalloon, PUT, "/balloon", Balloon, Empty);
+impl_event_traits!(PatchBalloon, PATCH, "/balloon", BalloonUpdate, Empty);
+impl_event_traits!(PutLegacyBalloon, PUT, "/balloon", LegacyBalloon, Empty);
+impl_event_traits!(
+    PatchLegacyBalloon,
+    PATCH,
+    "/balloon",
+    LegacyBalloonUpdate,
+    Empty
+);
+impl_event_traits!(
+    DescribeBalloonStats,
+    GET,
+    "/balloon/statistics",
+    Empty,
+    BalloonStats
+);
+impl_event_traits!(
+    PatchBalloonStatsInterval,
+    PATCH,
+    "/balloon/statistics",
+    BalloonStatsUpdate,
+    Empty
+);
+impl_event_traits!(PutGuestBootSource, PUT, "/boot-source", BootSource, Empty);
+impl_event_traits!(PutCpuConfiguration, PUT, "/cpu-config", CPUConfig, Empty);
+impl_event_traits!(PutGuestDriveByID, PUT, "/drives", drive_id, Drive, Empty);
+impl_event_traits!(
+    PatchGuestDriveByID,
+    PATCH,
+    "/drives",
+    drive_id,
+    PartialDrive,
+    Empty
+);
+impl_event_traits!(PutLogger, PUT, "/logger", Logger, Empty);
+impl_event_traits!(
+    GetMachineConfiguration,
+    GET,
+    "/machine-config",
+    Empty,
+    MachineConfiguration
+);
+impl_event_traits!(
+    PutMachineConfiguration,
+    PUT,
+    "/machine-config",
+    MachineConfiguration,
+    Empty
+);
+impl_event_traits!(
+    PatchMachineConfiguration,
+    PATCH,
+    "/machine-config",
+    MachineConfiguration,
+    Empty
+);
+impl_event_traits!(PutMetrics, PUT, "/metrics", Metrics, Empty);
+impl_event_traits!(PutMmds, PUT, "/mmds", MmdsContentsObject, Empty);
+impl_event_traits!(PatchMmds, PATCH, "/mmds", MmdsContentsObject, Empty);
+impl_event_traits!(GetMmds, GET, "/mmds", Empty, MmdsContentsObject);
+impl_event_traits!(PutMmdsConfig, PUT, "/mmds/config", MmdsConfig, Empty);
+impl_event_traits!(PutEntropyDevice, PUT, "/entropy", EntropyDevice, Empty);
+impl_event_traits!(
+    PutGuestNetworkInterfaceByID,
+    PUT,
+    "/network-interfaces",
+    iface_id,
+    NetworkInterface,
+    Empty
+);
+impl_event_traits!(
+    PatchGuestNetworkInterfaceByID,
+    PATCH,
+    "/network-interfaces",
+    iface_id,
+    PartialNetworkInterface,
+    Empty
+);
+impl_event_traits!(
+    CreateSnapshot,
+    PUT,
+    "/snapshot/create",
+    SnapshotCreateParams,
+    Empty
+);
+impl_event_traits!(
+    LoadSnapshot,
+    PUT,
+    "/snapshot/load",
+    SnapshotLoadParams,
+    Empty
+);
+impl_event_traits!(
+    GetFirecrackerVersion,
+    GET,
+    "/version",
+    Empty,
+    FirecrackerVersion
+);
+impl_event_traits!(PatchVm, PATCH, "/vm", Vm, Empty);
+impl_event_traits!(
+    GetExportVmConfig,
+    GET,
+    "/vm/config",
+    Empty,
+    FullVmConfiguration
+);
+impl_event_traits!(PutGuestVsock, PUT, "/vsock", Vsock, Empty);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{Empty, RequestTrait};
+
+    /// Assert that the event, borrowed and owned, encodes to the request line
+    /// `<method> <path> HTTP/1.1`.
+    macro_rules! assert_endpoint {
+        ($event:ident, $payload:expr, $method_path:literal) => {{
+            let payload = $payload;
+            paste::paste! {
+                for request in [
+                    $event(&payload).encode().unwrap(),
+                    [<$event Owned>](payload.clone()).encode().unwrap(),
+                ] {
+                    let request = String::from_utf8(request).unwrap();
+                    assert_eq!(
+                        request.lines().next(),
+                        Some(concat!($method_path, " HTTP/1.1")),
+                        stringify!($event)
+                    );
+                }
+            }
+        }};
+    }
+
+    #[test]
+    fn endpoints() {
+        let drive = Drive::new("rootfs", "/path/to/rootfs.ext4");
+        let partial_drive = PartialDrive {
+            drive_id: "rootfs".into(),
+            ..Default::default()
+        };
+        let network_interface = NetworkInterface {
+            iface_id: "eth0".into(),
+            ..Default::default()
+        };
+        let partial_network_interface = PartialNetworkInterface {
+            iface_id: "eth0".into(),
+            ..Default::default()
+        };
+
+        assert_endpoint!(DescribeInstance, Empty, "GET /");
+        assert_endpoint!(
+            CreateSyncAction,
+            InstanceActionInfo::new(ActionType::InstanceStart),
+            "PUT /actions"
+        );
+        assert_endpoint!(DescribeBalloonConfig, Empty, "GET /balloon");
+        assert_endpoint!(PutBalloon, Balloon::default(), "PUT /balloon");
+        assert_endpoint!(PatchBalloon, BalloonUpdate::default(), "PATCH /balloon");
+        assert_endpoint!(
+            PutLegacyBalloon,
+            LegacyBalloon::from(&Balloon::default()),
+            "PUT /balloon"
+        );
+        assert_endpoint!(
+            PatchLegacyBalloon,
+            LegacyBalloonUpdate::from(&BalloonUpdate::default()),
+            "PATCH /balloon"
+        );
+        assert_endpoint!(DescribeBalloonStats, Empty, "GET /balloon/statistics");
+        assert_endpoint!(
+            PatchBalloonStatsInterval,
+            BalloonStatsUpdate::default(),
+            "PATCH /balloon/statistics"
+        );
+        assert_endpoint!(
+            PutGuestBootSource,
+            BootSource::default(),
+            "PUT /boot-source"
+        );
+        assert_endpoint!(PutCpuConfiguration, CPUConfig::default(), "PUT /cpu-config");
+        assert_endpoint!(PutGuestDriveByID, drive, "PUT /drives/rootfs");
+        assert_endpoint!(PatchGuestDriveByID, partial_drive, "PATCH /drives/rootfs");
+        // an id that isn't a single path segment is rejected before being sent
+        let drive = Drive::new("../actions", "/path/to/rootfs.ext4");
+        assert!(matches!(
+            PutGuestDriveByID(&drive).encode(),
+            Err(crate::Error::Configuration(_))
+        ));
+        assert!(matches!(
+            PutGuestDriveByIDOwned(drive).encode(),
+            Err(crate::Error::Configuration(_))
+        ));
+        assert_endpoint!(PutLogger, Logger::default(), "PUT /logger");
+        assert_endpoint!(GetMachineConfiguration, Empty, "GET /machine-config");
+        assert_endpoint!(
+            PutMachineConfiguration,
+            MachineConfiguration::default(),
+            "PUT /machine-config"
+        );
+        assert_endpoint!(
+            PatchMachineConfiguration,
+            MachineConfiguration::default(),
+            "PATCH /machine-config"
+        );
+        assert_endpoint!(PutMetrics, Metrics::default(), "PUT /metrics");
+        assert_endpoint!(PutMmds, MmdsContentsObject::default(), "PUT /mmds");
+        assert_endpoint!(PatchMmds, MmdsContentsObject::default(), "PATCH /mmds");
+        assert_endpoint!(GetMmds, Empty, "GET /mmds");
+        assert_endpoint!(PutMmdsConfig, MmdsConfig::default(), "PUT /mmds/config");
+        assert_endpoint!(PutEntropyDevice, EntropyDevice::default(), "PUT /entropy");
+        assert_endpoint!(
+            PutGuestNetworkInterfaceByID,
+            network_interface,
+            "PUT /network-interfaces/eth0"
+        );
+        assert_endpoint!(
+            PatchGuestNetworkInterfaceByID,
+            partial_network_interface,
+            "PATCH /network-interfaces/eth0"
+        );
+        assert_endpoint!(
+            CreateSnapshot,
+            SnapshotCreateParams::default(),
+            "PUT /snapshot/create"
+        );
+        assert_endpoint!(
+            LoadSnapshot,
+            SnapshotLoadParams::default(),
+            "PUT /snapshot/load"
+        );
+        assert_endpoint!(GetFirecrackerVersion, Empty, "GET /version");
+        assert_endpoint!(PatchVm, VM_STATE_PAUSED, "PATCH /vm");
+        assert_endpoint!(GetExportVmConfig, Empty, "GET /vm/config");
+        assert_endpoint!(PutGuestVsock, Vsock::default(), "PUT /vsock");
+    }
+}
@@ -1,10 +1,116 @@
-use std::any::TypeId;
+use std::{any::TypeId, borrow::Cow};
 
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{Error, Result};
 
-const HTTP_VERSION: &'static str = "HTTP/1.0";
+const HTTP_VERSION: &'static str = "HTTP/1.1";
+
+/// Payload type for requests / responses without a body.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Empty;
+
+/// Encodes an HTTP request from its method, path and an optional
+/// already serialized body. The connection is kept alive for the following requests.
+pub fn encode_raw(method: &str, path: &str, body: Option<&[u8]>) -> Vec<u8> {
+    // method uri version
+    // "GET /version HTTP/1.1\r\n\r\n";
+    let mut request = format!("{} {} {}\r\n", method, path, HTTP_VERSION);
+    request.push_str("Connection: keep-alive\r\n");
+
+    match body {
+        Some(body) => {
+            // add `Content-Length` header
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+            // empty line splitting headers and body
+            request.push_str("\r\n");
+            // add body
+            let mut request = request.into_bytes();
+            request.extend_from_slice(body);
+            request
+        }
+        None => {
+            request.push_str("\r\n");
+            request.into_bytes()
+        }
+    }
+}
+
+/// Check that `segment` can be sent as a single path segment, e.g. the id of a drive:
+/// only `[A-Za-z0-9_-]`, as `firecracker` accepts in ids, and not empty. Anything else
+/// would change the path of the request rather than be rejected by `firecracker`.
+#[cfg(feature = "models")]
+pub(crate) fn check_path_segment(segment: &str) -> Result<()> {
+    let valid = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_');
+    if segment.is_empty() || !segment.bytes().all(valid) {
+        return Err(Error::Configuration(format!(
+            "id {segment:?} must be made of [A-Za-z0-9_-] only"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "models")]
+use crate::models::FirecrackerFault as Fault;
+
+/// Error body returned by the `firecracker` API along with a 4xx / 5xx status code, see
+/// `models::FirecrackerFault`.
+#[cfg(not(feature = "models"))]
+#[derive(serde::Deserialize)]
+struct Fault {
+    fault_message: String,
+}
+
+/// Whether the value of a `Transfer-Encoding` header ends with `chunked`, the only
+/// encoding allowed last.
+pub(crate) fn is_chunked(transfer_encoding: &[u8]) -> bool {
+    transfer_encoding
+        .rsplit(|&b| b == b',')
+        .next()
+        .is_some_and(|encoding| encoding.trim_ascii().eq_ignore_ascii_case(b"chunked"))
+}
+
+/// Reassembles a body sent with `Transfer-Encoding: chunked`.
+///
+/// Returns the dechunked body and the number of bytes it spans in `body` (including the
+/// trailers), or [`None`] if `body` ends before the last chunk.
+pub(crate) fn dechunk(body: &[u8]) -> Result<Option<(Vec<u8>, usize)>> {
+    let bad_chunk = || Error::Event("Bad chunk in HTTP response".into());
+    let mut dechunked = Vec::new();
+    let mut pos = 0;
+    loop {
+        let (size_len, size) = match httparse::parse_chunk_size(&body[pos..]) {
+            Ok(httparse::Status::Complete(chunk_size)) => chunk_size,
+            Ok(httparse::Status::Partial) => return Ok(None),
+            Err(_) => return Err(bad_chunk()),
+        };
+        pos += size_len;
+        if size == 0 {
+            break;
+        }
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|size| pos.checked_add(size))
+            .ok_or_else(bad_chunk)?;
+        match body.get(end..end + 2) {
+            Some(b"\r\n") => (),
+            Some(_) => return Err(bad_chunk()),
+            None => return Ok(None),
+        }
+        dechunked.extend_from_slice(&body[pos..end]);
+        pos = end + 2;
+    }
+    // skip the trailers up to the empty line
+    loop {
+        let Some(line_len) = body[pos..].windows(2).position(|w| w == b"\r\n") else {
+            return Ok(None);
+        };
+        pos += line_len + 2;
+        if line_len == 0 {
+            return Ok(Some((dechunked, pos)));
+        }
+    }
+}
 
 /// Trait for encoding a struct into an HTTP request.
 pub trait RequestTrait {
@@ -13,25 +119,14 @@ pub trait RequestTrait {
 
     /// Encodes the payload into an HTTP request.
     fn encode(&self) -> Result<Vec<u8>> {
-        // method uri version
-        // "GET /version HTTP/1.0\r\n\r\n";
-        let mut request = format!("{} {} {}\r\n", self.method(), self.path(), HTTP_VERSION);
-
+        self.check_path()?;
         let request = if TypeId::of::<Self::Payload>() == TypeId::of::<Empty>() {
-            request.push_str("\r\n");
-            request.as_bytes().to_vec()
+            encode_raw(self.method(), &self.path(), None)
         } else {
             let payload = self.payload();
-            let mut payload = serde_json::to_vec(&payload)
+            let payload = serde_json::to_vec(&payload)
                 .map_err(|e| Error::Event(format!("serde_json encode: {e}")))?;
-            // add `Content-Length` header
-            request.push_str(&format!("Content-Length: {}\r\n", payload.len()));
-            // empty line splitting headers and body
-            request.push_str("\r\n");
-            // add body
-            let mut request = request.as_bytes().to_vec();
-            request.append(&mut payload);
-            request
+            encode_raw(self.method(), &self.path(), Some(&payload))
         };
 
         Ok(request)
@@ -43,6 +138,12 @@ pub trait RequestTrait {
     /// Returns the endpoint path (e.g., "/balloon").
     fn path(&self) -> String;
 
+    /// Checks the parts of [`RequestTrait::path`] taken from the payload, e.g. the id of
+    /// a drive, before the request is encoded.
+    fn check_path(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Returns the payload to be serialized.
     fn payload(&self) -> &Self::Payload;
 }
@@ -75,6 +176,16 @@ pub trait ResponseTrait {
         }
         let body_start = body_start.unwrap(); // unwrap safe
 
+        let status = res
+            .code
+            .ok_or_else(|| Error::Event("Bad HTTP response".into()))?;
+
+        let chunked = res
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("transfer-encoding"))
+            .is_some_and(|h| is_chunked(h.value));
+
         let content_length = res
             .headers
             .iter()
@@ -88,10 +199,36 @@ pub trait ResponseTrait {
                 )
             });
 
-        match content_length {
-            Some(content_length) => {
-                let body = &response[body_start..(body_start + content_length)];
-                let payload: Self::Payload = serde_json::from_slice(body)
+        let body: Option<Cow<[u8]>> = match (chunked, content_length) {
+            (true, _) => match dechunk(&response[body_start..])? {
+                Some((body, _)) => Some(Cow::Owned(body)),
+                None => return Err(Error::Event("Incomplete response".into())),
+            },
+            (false, Some(content_length)) => Some(Cow::Borrowed(
+                &response[body_start..(body_start + content_length)],
+            )),
+            (false, None) => None,
+        };
+
+        if status >= 400 {
+            let body = body.as_deref().unwrap_or(&response[body_start..]);
+            // fall back to the raw body if it's not a fault object
+            let fault = serde_json::from_slice::<Fault>(body).ok();
+            let message = match &fault {
+                Some(fault) => fault.fault_message.clone(),
+                None => String::from_utf8_lossy(body).into_owned(),
+            };
+            return Err(Error::FirecrackerApiError {
+                status,
+                message,
+                #[cfg(feature = "models")]
+                fault,
+            });
+        }
+
+        match body {
+            Some(body) => {
+                let payload: Self::Payload = serde_json::from_slice(&body)
                     .map_err(|e| Error::Event(format!("serde_json decode: {e}")))?;
                 Ok(payload)
             }
@@ -109,222 +246,147 @@ pub trait ResponseTrait {
 
 pub trait EventTrait: RequestTrait + ResponseTrait {}
 
-macro_rules! impl_event_traits {
-    // Other conditions
-    ($struct_name:ident, $method:expr, $path:expr, $req_payload:ty, $res_payload:ty) => {
-        pub struct $struct_name<'a>(pub &'a $req_payload);
+#[cfg(feature = "models")]
+mod endpoints;
+#[cfg(feature = "models")]
+pub use endpoints::*;
 
-        impl<'a> RequestTrait for $struct_name<'a> {
-            type Payload = $req_payload;
+#[cfg(test)]
+mod tests {
+    use super::{dechunk, Empty, ResponseTrait};
+    use crate::Error;
 
-            fn method(&self) -> &'static str {
-                $method
-            }
-
-            fn path(&self) -> String {
-                $path.into()
-            }
-
-            fn payload(&self) -> &Self::Payload {
-                &self.0
-            }
-        }
+    struct EmptyResponse;
 
-        impl<'a> ResponseTrait for $struct_name<'a> {
-            type Payload = $res_payload;
-        }
-
-        impl<'a> EventTrait for $struct_name<'a> {}
-
-        paste::paste! {
-            pub struct [<$struct_name Owned>](
-                pub $req_payload
-                // field!($req_payload)
-            );
-
-            impl RequestTrait for [<$struct_name Owned>] {
-                type Payload = $req_payload;
-
-                fn method(&self) -> &'static str {
-                    $method
-                }
-
-                fn path(&self) -> String {
-                    $path.into()
-                }
-
-                fn payload(&self) -> &Self::Payload {
-                    &self.0
-                }
-            }
+    impl ResponseTrait for EmptyResponse {
+        type Payload = Empty;
+    }
 
-            impl ResponseTrait for [<$struct_name Owned>] {
-                type Payload = $res_payload;
+    #[test]
+    fn decode_api_error() {
+        let body = r#"{"fault_message":"The requested operation is not supported after starting the microVM."}"#;
+        let response = format!(
+            "HTTP/1.1 400 \r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        match EmptyResponse::decode(&response.into_bytes()) {
+            Err(Error::FirecrackerApiError {
+                status, message, ..
+            }) => {
+                assert_eq!(status, 400);
+                assert_eq!(
+                    message,
+                    "The requested operation is not supported after starting the microVM."
+                );
             }
-
-            impl EventTrait for [<$struct_name Owned>] {}
+            other => panic!("unexpected {other:?}"),
         }
-    };
-
-    ($struct_name:ident, $method:expr, $path:expr, $id:ident, $req_payload:ty, $res_payload:ty) => {
-        pub struct $struct_name<'a>(pub &'a $req_payload);
-
-        impl<'a> RequestTrait for $struct_name<'a> {
-            type Payload = $req_payload;
 
-            fn method(&self) -> &'static str {
-                $method
+        let body = r#"{"fault_message":"The drive ID does not exist."}"#;
+        let response = format!(
+            "HTTP/1.1 400 \r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let e = EmptyResponse::decode(&response.into_bytes()).unwrap_err();
+        assert!(matches!(e, Error::FirecrackerApiError { status: 400, .. }));
+        #[cfg(feature = "models")]
+        assert_eq!(
+            e.fault(),
+            Some(&crate::models::FirecrackerFault::new(
+                "The drive ID does not exist."
+            ))
+        );
+
+        let response = b"HTTP/1.1 500 \r\n\r\nnot json".to_vec();
+        match EmptyResponse::decode(&response) {
+            Err(Error::FirecrackerApiError {
+                status, message, ..
+            }) => {
+                assert_eq!(status, 500);
+                assert_eq!(message, "not json");
             }
-
-            fn path(&self) -> String {
-                format!("{}/{}", $path, &self.0.$id)
-            }
-
-            fn payload(&self) -> &Self::Payload {
-                &self.0
-            }
-        }
-
-        impl<'a> ResponseTrait for $struct_name<'a> {
-            type Payload = $res_payload;
+            other => panic!("unexpected {other:?}"),
         }
+        #[cfg(feature = "models")]
+        assert_eq!(EmptyResponse::decode(&response).unwrap_err().fault(), None);
 
-        impl<'a> EventTrait for $struct_name<'a> {}
-
-        paste::paste! {
-            pub struct [<$struct_name Owned>](pub $req_payload);
+        let response = b"HTTP/1.1 204 \r\n\r\n".to_vec();
+        assert!(EmptyResponse::decode(&response).is_ok());
+    }
 
-            impl RequestTrait for [<$struct_name Owned>] {
-                type Payload = $req_payload;
+    #[cfg(feature = "models")]
+    #[test]
+    fn decode_chunked() {
+        use super::GetFirecrackerVersion;
+
+        let response = b"HTTP/1.1 200 \r\n\
+            Content-Type: application/json\r\n\
+            Transfer-Encoding: chunked\r\n\r\n\
+            11\r\n{\"firecracker_ver\r\n\
+            f\r\nsion\":\"1.10.1\"}\r\n\
+            0\r\n\r\n"
+            .to_vec();
+        let version = GetFirecrackerVersion::decode(&response).unwrap();
+        assert_eq!(version.firecracker_version, "1.10.1");
+
+        // cut off before the last chunk
+        let incomplete = &response[..response.len() - 5];
+        assert!(GetFirecrackerVersion::decode(&incomplete.to_vec()).is_err());
+    }
 
-                fn method(&self) -> &'static str {
-                    $method
-                }
+    #[cfg(feature = "models")]
+    #[test]
+    fn encode_mmds_config_version() {
+        use super::{PutMmdsConfig, RequestTrait};
+        use crate::models::{MmdsConfig, MmdsVersion};
 
-                fn path(&self) -> String {
-                    format!("{}/{}", $path, &self.0.$id)
-                }
+        let mut config = MmdsConfig::new(vec!["eth0".into()]);
+        let request = String::from_utf8(PutMmdsConfig(&config).encode().unwrap()).unwrap();
+        assert!(request.starts_with("PUT /mmds/config "));
+        assert!(request.contains(r#""version":"V2""#));
 
-                fn payload(&self) -> &Self::Payload {
-                    &self.0
-                }
-            }
+        config.version = Some(MmdsVersion::V1);
+        let request = String::from_utf8(PutMmdsConfig(&config).encode().unwrap()).unwrap();
+        assert!(request.contains(r#""version":"V1""#));
+    }
 
-            impl ResponseTrait for [<$struct_name Owned>] {
-                type Payload = $res_payload;
-            }
+    #[test]
+    fn encode_keep_alive() {
+        use super::encode_raw;
+
+        assert_eq!(
+            encode_raw("GET", "/version", None),
+            b"GET /version HTTP/1.1\r\nConnection: keep-alive\r\n\r\n"
+        );
+        assert_eq!(
+            encode_raw("PATCH", "/vm", Some(b"{}")),
+            b"PATCH /vm HTTP/1.1\r\nConnection: keep-alive\r\nContent-Length: 2\r\n\r\n{}"
+        );
+    }
 
-            impl EventTrait for [<$struct_name Owned>] {}
+    #[cfg(feature = "models")]
+    #[test]
+    fn check_id_segment() {
+        use super::check_path_segment;
+
+        assert!(check_path_segment("rootfs_1").is_ok());
+        assert!(check_path_segment("eth-0").is_ok());
+        for segment in ["", "root/fs", "..", "eth 0?", "é"] {
+            assert!(
+                matches!(check_path_segment(segment), Err(Error::Configuration(_))),
+                "{segment:?}"
+            );
         }
-    };
-}
+    }
 
-use crate::models::*;
-const GET: &'static str = "GET";
-const PUT: &'static str = "PUT";
-const PATCH: &'static str = "PATCH";
-
-impl_event_traits!(DescribeInstance, GET, "/", Empty, InstanceInfo);
-impl_event_traits!(CreateSyncAction, PUT, "/actions", InstanceActionInfo, Empty);
-impl_event_traits!(DescribeBalloonConfig, GET, "/balloon", Empty, Balloon);
-impl_event_traits!(PutBalloon, PUT, "/balloon", Balloon, Empty);
-impl_event_traits!(PatchBalloon, PATCH, "/balloon", BalloonUpdate, Empty);
-impl_event_traits!(
-    DescribeBalloonStats,
-    GET,
-    "/balloon/statistics",
-    Empty,
-    BalloonStats
-);
-impl_event_traits!(
-    PatchBalloonStatsInterval,
-    PATCH,
-    "/balloon/statistics",
-    BalloonStatsUpdate,
-    Empty
-);
-impl_event_traits!(PutGuestBootSource, PUT, "/boot-source", BootSource, Empty);
-impl_event_traits!(PutCpuConfiguration, PUT, "/cpu-config", CPUConfig, Empty);
-impl_event_traits!(PutGuestDriveByID, PUT, "/drives", drive_id, Drive, Empty);
-impl_event_traits!(
-    PatchGuestDriveByID,
-    PATCH,
-    "/drives",
-    drive_id,
-    PartialDrive,
-    Empty
-);
-impl_event_traits!(PutLogger, PUT, "/logger", Logger, Empty);
-impl_event_traits!(
-    GetMachineConfiguration,
-    GET,
-    "/machine-config",
-    Empty,
-    MachineConfiguration
-);
-impl_event_traits!(
-    PutMachineConfiguration,
-    PUT,
-    "/machine-config",
-    MachineConfiguration,
-    Empty
-);
-impl_event_traits!(
-    PatchMachineConfiguration,
-    PATCH,
-    "/machine-config",
-    MachineConfiguration,
-    Empty
-);
-impl_event_traits!(PutMetrics, PUT, "/metrics", Metrics, Empty);
-impl_event_traits!(PutMmds, PUT, "/mmds", MmdsContentsObject, Empty);
-impl_event_traits!(PatchMmds, PATCH, "/mmds", MmdsContentsObject, Empty);
-impl_event_traits!(GetMmds, GET, "/mmds", Empty, MmdsContentsObject);
-impl_event_traits!(PutMmdsConfig, PUT, "/mmds/config", MmdsConfig, Empty);
-impl_event_traits!(PutEntropyDevice, PUT, "/entropy", EntropyDevice, Empty);
-impl_event_traits!(
-    PutGuestNetworkInterfaceByID,
-    PUT,
-    "/network-interfaces",
-    iface_id,
-    NetworkInterface,
-    Empty
-);
-impl_event_traits!(
-    PatchGuestNetworkInterfaceByID,
-    PATCH,
-    "/network-interfaces",
-    iface_id,
-    PartialNetworkInterface,
-    Empty
-);
-impl_event_traits!(
-    CreateSnapshot,
-    PUT,
-    "/snapshot/create",
-    SnapshotCreateParams,
-    Empty
-);
-impl_event_traits!(
-    LoadSnapshot,
-    PUT,
-    "/snapshot/load",
-    SnapshotLoadParams,
-    Empty
-);
-impl_event_traits!(
-    GetFirecrackerVersion,
-    GET,
-    "/version",
-    Empty,
-    FirecrackerVersion
-);
-impl_event_traits!(PatchVm, PATCH, "/vm", Vm, Empty);
-impl_event_traits!(
-    GetExportVmConfig,
-    GET,
-    "/vm/config",
-    Empty,
-    FullVmConfiguration
-);
-impl_event_traits!(PutGuestVsock, PUT, "/vsock", Vsock, Empty);
+    #[test]
+    fn dechunk_trailers() {
+        let body = b"3\r\nabc\r\n0\r\nX-Trailer: 1\r\n\r\nHTTP/1.1";
+        assert_eq!(
+            dechunk(body).unwrap(),
+            Some((b"abc".to_vec(), body.len() - b"HTTP/1.1".len()))
+        );
+        assert_eq!(dechunk(&body[..20]).unwrap(), None);
+        assert!(dechunk(b"3\r\nabcd\r\n0\r\n\r\n").is_err());
+    }
+}
@@ -1,20 +1,234 @@
 //! Option to launch firecracker
 
+#[cfg(not(feature = "debug-full"))]
+use std::fmt;
 use std::{
-    fs::{File, OpenOptions},
+    env,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::ErrorKind,
+    os::unix::{fs::PermissionsExt, process::CommandExt},
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(not(feature = "debug-full"))]
+use crate::redact::{redact_path, RedactedPath};
 use crate::{instance::Instance, Error, Result};
 
 pub const DEFAULT_API_SOCK: &'static str = "/run/firecracker.socket";
 pub const DEFAULT_HTTP_API_MAX_PAYLOAD_SIZE: usize = 51200;
 pub const DEFAULT_ID: &'static str = "anonymous-instance";
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// KVM device `firecracker` runs the microVM with.
+pub const KVM_DEVICE: &str = "/dev/kvm";
+
+/// Environment variable overriding the path of the `firecracker` binary.
+pub const FIRECRACKER_BIN_ENV: &str = "FIRECRACKER_BIN";
+
+/// Common install locations searched after `PATH`.
+pub(crate) const COMMON_BIN_DIRS: [&str; 3] = ["/usr/bin", "/usr/local/bin", "/opt/firecracker"];
+
+/// Locate the `firecracker` binary.
+///
+/// Searches, in order, the path given by [`FIRECRACKER_BIN_ENV`], `PATH` and the
+/// common install locations `/usr/bin`, `/usr/local/bin` and `/opt/firecracker`,
+/// returning the first existing executable.
+pub fn find_binary() -> Option<PathBuf> {
+    find_executable("firecracker", FIRECRACKER_BIN_ENV)
+}
+
+/// Read `clock` in microseconds.
+fn clock_us(clock: libc::clockid_t) -> usize {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid `timespec` and `clock` is a clock supported by Linux.
+    unsafe { libc::clock_gettime(clock, &mut time) };
+    time.tv_sec as usize * 1_000_000 + time.tv_nsec as usize / 1_000
+}
+
+/// Locate executable `name`, see [`find_binary`].
+pub(crate) fn find_executable(name: &str, env_override: &str) -> Option<PathBuf> {
+    find_executable_in(name, env::var_os(env_override), env::var_os("PATH"))
+}
+
+/// [`find_executable`] given the value of the override variable and of `PATH`.
+fn find_executable_in(
+    name: &str,
+    env_override: Option<OsString>,
+    path: Option<OsString>,
+) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        fs::metadata(path)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+
+    if let Some(path) = env_override {
+        let path = PathBuf::from(path);
+        if is_executable(&path) {
+            return Some(path);
+        }
+    }
+
+    let path_dirs = path
+        .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    path_dirs
+        .into_iter()
+        .chain(COMMON_BIN_DIRS.iter().map(PathBuf::from))
+        .map(|dir| dir.join(name))
+        .find(|path| is_executable(path))
+}
+
+/// Check that the binary `path` given as option `name` exists, looking it up in `PATH`
+/// as [`Command`] does if it's a bare file name.
+pub(crate) fn check_binary_exists(name: &str, path: &Path) -> Result<()> {
+    let exists = if path.components().count() == 1 && !path.has_root() {
+        env::var_os("PATH")
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(path).is_file()))
+    } else {
+        path.is_file()
+    };
+    if exists {
+        Ok(())
+    } else {
+        Err(Error::Configuration(format!(
+            "`{name}` {} does not exist",
+            path.display()
+        )))
+    }
+}
+
+/// Check that the file given as option `name` exists, if any.
+pub(crate) fn check_file_exists(name: &str, path: Option<&Path>) -> Result<()> {
+    match path {
+        Some(path) if !path.exists() => Err(Error::Configuration(format!(
+            "`{name}` {} does not exist",
+            path.display()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Check that `id`, given as option `name`, is a valid microVM ID the way `jailer` checks
+/// it: 1 to 64 characters among ASCII letters, digits and `-`. This keeps it a single path
+/// component, so that it can't point to a parent directory when joined to a path.
+pub(crate) fn check_id(name: &str, id: &str) -> Result<()> {
+    if (1..=64).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+        Ok(())
+    } else {
+        Err(Error::Configuration(format!(
+            "`{name}` {id:?} must be 1 to 64 ASCII letters, digits or `-`"
+        )))
+    }
+}
+
+/// Check that the KVM device at `path` exists and can be opened read-write.
+fn check_kvm_device(path: &Path) -> Result<()> {
+    match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(Error::Configuration(format!(
+            "{} does not exist, enable virtualization and load the `kvm_intel` / `kvm_amd` module",
+            path.display()
+        ))),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => Err(Error::Configuration(format!(
+            "{} is not accessible read-write by the current user, add it to the `kvm` group \
+             (`sudo usermod -aG kvm $USER`, then log in again)",
+            path.display()
+        ))),
+        Err(e) => Err(Error::ConfigurationIO {
+            context: format!("Fail to open {}", path.display()),
+            source: e,
+        }),
+    }
+}
+
+/// Metadata of a snapshot state file, see [`describe_snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SnapshotInfo {
+    /// Data format version of the snapshot, e.g. `v1.4.0`.
+    pub version: String,
+
+    /// Whether the snapshot is a full or diff snapshot.
+    /// [`None`] if `firecracker` only reports the version, as current releases do.
+    #[cfg(feature = "models")]
+    pub snapshot_type: Option<crate::models::SnapshotType>,
+}
+
+impl SnapshotInfo {
+    /// Parse the output of `firecracker --describe-snapshot`.
+    fn parse(output: &str) -> Result<Self> {
+        let version = output
+            .split_whitespace()
+            .find(|word| {
+                word.strip_prefix('v')
+                    .is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+            })
+            .ok_or_else(|| {
+                Error::Configuration(format!(
+                    "cannot find snapshot version in `{}`",
+                    output.trim()
+                ))
+            })?;
+        Ok(Self {
+            version: version.to_string(),
+            #[cfg(feature = "models")]
+            snapshot_type: if output.contains("Diff") {
+                Some(crate::models::SnapshotType::Diff)
+            } else if output.contains("Full") {
+                Some(crate::models::SnapshotType::Full)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Parse the version into `(major, minor, patch)`.
+    #[cfg(feature = "models")]
+    pub fn semver(&self) -> Option<(u64, u64, u64)> {
+        crate::models::FirecrackerVersion {
+            firecracker_version: self.version.clone(),
+        }
+        .semver()
+    }
+}
+
+/// Read the metadata of snapshot state file `snapshot_path` with
+/// `firecracker --describe-snapshot`, without restoring it, e.g. to check that a snapshot
+/// is compatible with `firecracker_bin` before loading it.
+///
+/// Returns [`Error::Configuration`] if `firecracker` cannot read the snapshot (missing,
+/// corrupted or of an unsupported version).
+pub fn describe_snapshot<P: AsRef<Path>, Q: AsRef<Path>>(
+    firecracker_bin: P,
+    snapshot_path: Q,
+) -> Result<SnapshotInfo> {
+    let snapshot_path = snapshot_path.as_ref();
+    let output = Command::new(firecracker_bin.as_ref())
+        .arg("--describe-snapshot")
+        .arg(snapshot_path)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::Configuration(format!(
+            "cannot describe snapshot {}: {}",
+            snapshot_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    SnapshotInfo::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The `Debug` output masks absolute paths to their basenames so the option can be
+/// logged safely. Enable feature `debug-full` to print the paths unmodified.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug-full", derive(Debug))]
 pub struct FirecrackerOption {
     firecracker_bin: PathBuf,
 
@@ -28,10 +242,10 @@ pub struct FirecrackerOption {
     config_file: Option<PathBuf>,
 
     // Print the data format version of the provided snapshot state file.
-    describe_snapshot: Option<bool>,
+    describe_snapshot: Option<PathBuf>,
 
     // Http API request payload max size, in bytes. [default: "51200"]
-    http_api_max_payload_size: Option<usize>,
+    pub(crate) http_api_max_payload_size: Option<usize>,
 
     // MicroVM unique identifier. [default: "anonymous-instance"]
     id: Option<String>,
@@ -40,7 +254,7 @@ pub struct FirecrackerOption {
     level: Option<String>,
 
     // Path to a fifo or a file used for configuring the logger on startup.
-    log_path: Option<PathBuf>,
+    pub(crate) log_path: Option<PathBuf>,
 
     // Path to a file that contains metadata in JSON format to add to the mmds.
     metadata: Option<PathBuf>,
@@ -86,9 +300,68 @@ pub struct FirecrackerOption {
 
     // Stderr of the firecracker, ignored when using jailer.
     stderr: Option<PathBuf>,
+
+    // Whether to keep the API socket after the instance is dropped, ignored when using jailer.
+    keep_socket: Option<bool>,
+    // Whether to create the parent directory of the API socket if it does not exist.
+    create_socket_dir: Option<bool>,
+    // Whether to keep inherited file descriptors above stderr from leaking into firecracker, ignored when using jailer.
+    close_fds: Option<bool>,
+    // Per-instance directory holding the API socket, logs and metrics, removed with the instance.
+    workspace_dir: Option<PathBuf>,
+    // Time given to firecracker to exit after SIGTERM before SIGKILL, see `DEFAULT_SHUTDOWN_GRACE`.
+    shutdown_grace: Option<Duration>,
+    // Size of the reads of API responses, see `DEFAULT_READ_BUFFER_SIZE`.
+    read_buffer_size: Option<usize>,
+    // Whether `validate` checks the access to `/dev/kvm`.
+    require_kvm: Option<bool>,
+}
+
+#[cfg(not(feature = "debug-full"))]
+impl fmt::Debug for FirecrackerOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FirecrackerOption")
+            .field("firecracker_bin", &RedactedPath(&self.firecracker_bin))
+            .field("api_sock", &redact_path(self.api_sock.as_ref()))
+            .field("boot_timer", &self.boot_timer)
+            .field("config_file", &redact_path(self.config_file.as_ref()))
+            .field(
+                "describe_snapshot",
+                &redact_path(self.describe_snapshot.as_ref()),
+            )
+            .field("http_api_max_payload_size", &self.http_api_max_payload_size)
+            .field("id", &self.id)
+            .field("level", &self.level)
+            .field("log_path", &redact_path(self.log_path.as_ref()))
+            .field("metadata", &redact_path(self.metadata.as_ref()))
+            .field("metrics_path", &redact_path(self.metrics_path.as_ref()))
+            .field("mmds_size_limit", &self.mmds_size_limit)
+            .field("module", &self.module)
+            .field("no_api", &self.no_api)
+            .field("no_seccomp", &self.no_seccomp)
+            .field("parent_cpu_time_us", &self.parent_cpu_time_us)
+            .field("seccomp_filter", &redact_path(self.seccomp_filter.as_ref()))
+            .field("show_level", &self.show_level)
+            .field("show_log_origin", &self.show_log_origin)
+            .field("start_time_cpu_us", &self.start_time_cpu_us)
+            .field("start_time_us", &self.start_time_us)
+            .field("stdin", &redact_path(self.stdin.as_ref()))
+            .field("stdout", &redact_path(self.stdout.as_ref()))
+            .field("stderr", &redact_path(self.stderr.as_ref()))
+            .field("keep_socket", &self.keep_socket)
+            .field("create_socket_dir", &self.create_socket_dir)
+            .field("close_fds", &self.close_fds)
+            .field("workspace_dir", &redact_path(self.workspace_dir.as_ref()))
+            .field("shutdown_grace", &self.shutdown_grace)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("require_kvm", &self.require_kvm)
+            .finish()
+    }
 }
 
 impl FirecrackerOption {
+    /// Pass an empty path (or use [`FirecrackerOption::default`]) to locate
+    /// the binary with [`find_binary`] when building the instance.
     pub fn new<P: AsRef<Path>>(firecracker_bin: P) -> Self {
         Self {
             firecracker_bin: firecracker_bin.as_ref().into(),
@@ -96,18 +369,58 @@ impl FirecrackerOption {
         }
     }
 
-    fn exec_file_name(&self) -> Result<PathBuf> {
-        let exec_file_name = self
-            .firecracker_bin
-            .file_name()
-            .ok_or_else(|| Error::Configuration("jailer `exec_file` ends with `..`".into()))?;
-        Ok(exec_file_name.into())
+    /// Check that the parent directory of the API socket `socket_on_host` exists,
+    /// creating it (owned by `owner` if given) if `create_socket_dir` is set.
+    pub(crate) fn prepare_socket_dir(
+        &self,
+        socket_on_host: &Path,
+        owner: Option<(u32, u32)>,
+    ) -> Result<()> {
+        let Some(socket_dir) = socket_on_host.parent() else {
+            return Ok(());
+        };
+        if socket_dir.as_os_str().is_empty() || socket_dir.is_dir() {
+            return Ok(());
+        }
+
+        if let Some(true) = self.create_socket_dir {
+            fs::create_dir_all(socket_dir)?;
+            if let Some((uid, gid)) = owner {
+                std::os::unix::fs::chown(socket_dir, Some(uid), Some(gid))?;
+            }
+            Ok(())
+        } else {
+            Err(Error::Configuration(format!(
+                "parent directory {} of `api_sock` does not exist",
+                socket_dir.display()
+            )))
+        }
     }
 
     pub fn build(&mut self) -> Result<Instance> {
+        if self.firecracker_bin.as_os_str().is_empty() {
+            self.firecracker_bin = find_binary()
+                .ok_or_else(|| Error::Configuration("`firecracker` binary not found".into()))?;
+        }
+
+        self.validate()?;
+
+        // only a directory created here is removed with the instance
+        let created_workspace_dir = match self.workspace_dir {
+            Some(ref workspace_dir) if !workspace_dir.exists() => {
+                fs::create_dir_all(workspace_dir)?;
+                true
+            }
+            _ => false,
+        };
+
         // spawn instance directly with firecracker
         let mut command = self.build_cmd();
 
+        if let Some(true) = self.close_fds {
+            close_inherited_fds(&mut command);
+        }
+
         // Redirect stdin, stdout and stderr
         if let Some(ref stdin) = self.stdin {
             command.stdin(Stdio::from(File::open(stdin)?));
@@ -129,15 +442,122 @@ impl FirecrackerOption {
             .api_sock
             .clone()
             .unwrap_or_else(|| DEFAULT_API_SOCK.into());
+        self.prepare_socket_dir(&socket_on_host, None)?;
 
-        Ok(Instance::new(
+        let mut instance = Instance::new(
             socket_on_host,
             None,
             None,
             None,
             command,
-            self.exec_file_name()?,
-        ))
+            None,
+            self.log_path.clone(),
+            self.metrics_path.clone(),
+            self.keep_socket,
+            None,
+            self.shutdown_grace,
+        );
+        if let Some(ref workspace_dir) = self.workspace_dir {
+            instance.set_workspace_dir(workspace_dir.clone(), created_workspace_dir);
+        }
+        if let Some(http_api_max_payload_size) = self.http_api_max_payload_size {
+            instance.set_http_api_max_payload_size(http_api_max_payload_size);
+        }
+        if let Some(read_buffer_size) = self.read_buffer_size {
+            instance.set_read_buffer_size(read_buffer_size);
+        }
+        Ok(instance)
+    }
+
+    /// Check the option before spawning `firecracker`, so that a misconfiguration fails fast
+    /// with an [`Error::Configuration`] naming it rather than an IO error at spawn:
+    ///
+    /// - the `firecracker` binary exists, or can be located with [`find_binary`] if not given,
+    /// - `config_file`, `describe_snapshot`, `metadata`, `seccomp_filter` and `stdin` exist,
+    /// - the `id` given to [`FirecrackerOption::with_workspace`] is a valid microVM ID,
+    /// - the arguments are consistent, see [`FirecrackerOption::validate_args`],
+    /// - `/dev/kvm` is accessible if `require_kvm` is set, see [`FirecrackerOption::check_kvm`].
+    ///
+    /// [`FirecrackerOption::build`] calls this first.
+    pub fn validate(&self) -> Result<()> {
+        if self.firecracker_bin.as_os_str().is_empty() {
+            if find_binary().is_none() {
+                return Err(Error::Configuration(
+                    "`firecracker` binary not found".into(),
+                ));
+            }
+        } else {
+            check_binary_exists("firecracker_bin", &self.firecracker_bin)?;
+        }
+
+        check_file_exists("config_file", self.config_file.as_deref())?;
+        check_file_exists("describe_snapshot", self.describe_snapshot.as_deref())?;
+        check_file_exists("metadata", self.metadata.as_deref())?;
+        check_file_exists(
+            "seccomp_filter",
+            self.seccomp_filter.as_deref().map(Path::new),
+        )?;
+        check_file_exists("stdin", self.stdin.as_deref())?;
+        if let Some(ref workspace_dir) = self.workspace_dir {
+            let id = self.id.as_deref().unwrap_or_default();
+            check_id("id", id)?;
+            if workspace_dir.file_name() != Some(id.as_ref()) {
+                return Err(Error::Configuration(format!(
+                    "`id` {id} changed after `with_workspace`"
+                )));
+            }
+        }
+        if let Some(true) = self.require_kvm {
+            Self::check_kvm()?;
+        }
+
+        self.validate_args()
+    }
+
+    /// Check that [`KVM_DEVICE`] exists and the current user can open it read-write, as
+    /// `firecracker` exits right away otherwise. The error tells how to fix the setup,
+    /// e.g. by joining the `kvm` group.
+    ///
+    /// When spawned with `jailer`, `firecracker` opens the device as the `uid` / `gid` given
+    /// to `jailer` instead.
+    pub fn check_kvm() -> Result<()> {
+        check_kvm_device(Path::new(KVM_DEVICE))
+    }
+
+    /// Check that the arguments passed to `firecracker` are consistent, without touching the
+    /// file system (the paths are inside the jail when spawned with `jailer`):
+    ///
+    /// - `start_time_us` and `start_time_cpu_us` are given together or not at all, and
+    ///   `parent_cpu_time_us` only alongside them,
+    /// - `no_api` is not combined with `api_sock`, and comes with the `config_file`
+    ///   that `firecracker` then requires.
+    pub fn validate_args(&self) -> Result<()> {
+        if let Some(true) = self.no_api {
+            if self.api_sock.is_some() {
+                return Err(Error::Configuration(
+                    "`no_api` and `api_sock` are mutually exclusive".into(),
+                ));
+            }
+            if self.config_file.is_none() {
+                return Err(Error::Configuration(
+                    "`no_api` requires `config_file` to configure the microVM".into(),
+                ));
+            }
+        }
+
+        match (
+            self.start_time_us,
+            self.start_time_cpu_us,
+            self.parent_cpu_time_us,
+        ) {
+            (Some(_), Some(_), _) | (None, None, None) => Ok(()),
+            (None, None, Some(_)) => Err(Error::Configuration(
+                "`parent_cpu_time_us` set without `start_time_us` and `start_time_cpu_us`".into(),
+            )),
+            _ => Err(Error::Configuration(
+                "`start_time_us` and `start_time_cpu_us` must be set together".into(),
+            )),
+        }
     }
 
     pub(crate) fn build_cmd(&self) -> Command {
@@ -164,6 +584,10 @@ impl FirecrackerOption {
             cmd.arg("--config-file").arg(config_file);
         }
 
+        if let Some(ref describe_snapshot) = self.describe_snapshot {
+            cmd.arg("--describe-snapshot").arg(describe_snapshot);
+        }
+
         if let Some(ref http_api_max_payload_size) = self.http_api_max_payload_size {
             cmd.arg("--http-api-max-payload-size")
                 .arg(http_api_max_payload_size.to_string());
@@ -239,6 +663,22 @@ impl FirecrackerOption {
         self
     }
 
+    /// Place the API socket at a path under [`std::env::temp_dir`] unique to this option,
+    /// i.e. `firecracker-<pid>-<n>.socket` where `n` counts the calls in this process,
+    /// so that instances spawned in parallel never bind the same socket.
+    pub fn api_sock_in_tempdir(&mut self) -> &mut Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        self.api_sock =
+            Some(env::temp_dir().join(format!("firecracker-{}-{n}.socket", std::process::id())));
+        self
+    }
+
+    /// Load the boot timer device, which the guest signals once booted.
+    ///
+    /// `firecracker` reports the elapsed time only in its log, as an `Info` line like
+    /// `Guest-boot-time =   1234 us 1 ms, ...`: it isn't part of the metrics output nor
+    /// of any API object, so there's no typed boot duration to read back.
     pub fn boot_timer(&mut self) -> &mut Self {
         self.boot_timer = Some(true);
         self
@@ -249,6 +689,11 @@ impl FirecrackerOption {
         self
     }
 
+    pub fn describe_snapshot<P: AsRef<Path>>(&mut self, describe_snapshot: Option<P>) -> &mut Self {
+        self.describe_snapshot = describe_snapshot.map(|x| x.as_ref().to_path_buf());
+        self
+    }
+
     pub fn http_api_max_payload_size(
         &mut self,
         http_api_max_payload_size: usize,
@@ -332,6 +777,17 @@ impl FirecrackerOption {
         self
     }
 
+    /// Fill `start_time_us` and `start_time_cpu_us` for a `firecracker` spawned now,
+    /// so that its boot timer measures from this point on.
+    ///
+    /// `start_time_us` is read from the monotonic clock, and `start_time_cpu_us` is zero since
+    /// the spawned process starts with no CPU time. `parent_cpu_time_us` is left untouched.
+    pub fn with_auto_start_time(&mut self) -> &mut Self {
+        self.start_time_us = Some(clock_us(libc::CLOCK_MONOTONIC));
+        self.start_time_cpu_us = Some(0);
+        self
+    }
+
     pub fn stdin<P: AsRef<Path>>(&mut self, stdin: P) -> &mut Self {
         self.stdin = Some(stdin.as_ref().into());
         self
@@ -346,4 +802,325 @@ impl FirecrackerOption {
         self.stderr = Some(stderr.as_ref().into());
         self
     }
+
+    /// Keep the API socket when the instance is dropped, instead of removing it.
+    /// Useful when the socket is managed externally, in which case the caller is
+    /// responsible for cleaning it up.
+    pub fn keep_socket(&mut self) -> &mut Self {
+        self.keep_socket = Some(true);
+        self
+    }
+
+    /// Create the parent directory of `api_sock` before spawning if it does not exist.
+    pub fn create_socket_dir(&mut self) -> &mut Self {
+        self.create_socket_dir = Some(true);
+        self
+    }
+
+    /// Don't let `firecracker` inherit the open file descriptors of this process above
+    /// stderr, a slice of the isolation `jailer` provides for bare `firecracker`.
+    /// Ignored when using `jailer`, which sanitizes the descriptors itself.
+    ///
+    /// Linux-only: the descriptors are marked close-on-exec in a `pre_exec` hook,
+    /// see [`close_inherited_fds`].
+    pub fn close_fds(&mut self) -> &mut Self {
+        self.close_fds = Some(true);
+        self
+    }
+
+    /// Lay out the files of the instance under the per-instance directory `<dir>/<id>/`:
+    /// set `id` and derive `api_sock` (`api.socket`), `log_path` (`fc.log`) and
+    /// `metrics_path` (`fc.metrics`) from it.
+    ///
+    /// The directory is created when building the instance and removed, with everything
+    /// in it, when the instance is dropped. A directory that already exists is used as is and
+    /// kept. This isolates the paths of bare `firecracker` instances the way the jailer
+    /// workspace directory does.
+    ///
+    /// `id` must be a valid microVM ID, see [`FirecrackerOption::validate`].
+    pub fn with_workspace<P: AsRef<Path>, S: AsRef<str>>(&mut self, dir: P, id: S) -> &mut Self {
+        let workspace_dir = dir.as_ref().join(id.as_ref());
+        self.id = Some(id.as_ref().to_string());
+        self.api_sock = Some(workspace_dir.join("api.socket"));
+        self.log_path = Some(workspace_dir.join("fc.log"));
+        self.metrics_path = Some(workspace_dir.join("fc.metrics"));
+        self.workspace_dir = Some(workspace_dir);
+        self
+    }
+
+    /// Time given to `firecracker` to exit after `SIGTERM` when the instance is dropped
+    /// (or closed by `Instance::close` with an async runtime, whose drop kills it right
+    /// away), before it's killed with `SIGKILL`. Defaults to
+    /// [`DEFAULT_SHUTDOWN_GRACE`](crate::fstack::DEFAULT_SHUTDOWN_GRACE).
+    pub fn shutdown_grace(&mut self, grace: Duration) -> &mut Self {
+        self.shutdown_grace = Some(grace);
+        self
+    }
+
+    /// Size of the reads of the responses of the API socket, in bytes. Defaults to
+    /// [`DEFAULT_READ_BUFFER_SIZE`](crate::agent::DEFAULT_READ_BUFFER_SIZE), larger buffers
+    /// take fewer syscalls to read large responses such as the exported VM config.
+    pub fn read_buffer_size(&mut self, read_buffer_size: usize) -> &mut Self {
+        self.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
+    /// Check the access to `/dev/kvm` in [`FirecrackerOption::validate`], see
+    /// [`FirecrackerOption::check_kvm`].
+    pub fn require_kvm(&mut self) -> &mut Self {
+        self.require_kvm = Some(true);
+        self
+    }
+}
+
+/// Bound of the descriptors marked one by one by [`close_inherited_fds`] without
+/// `close_range`, as `RLIMIT_NOFILE` may be up to a billion.
+const CLOSE_FDS_FALLBACK_LIMIT: libc::c_int = 65536;
+
+/// Mark every file descriptor above stderr close-on-exec in the child of `command`, so that
+/// only stdin, stdout and stderr are inherited by the executed program.
+///
+/// Marking instead of closing keeps alive the pipe through which `std` reports a failed exec.
+/// Without `close_range` (Linux < 5.11), only the descriptors below
+/// [`CLOSE_FDS_FALLBACK_LIMIT`] are marked.
+///
+/// # Safety
+/// This installs a [`CommandExt::pre_exec`] hook, which runs in the forked child where only
+/// async-signal-safe operations are allowed. The hook only issues the `close_range`, `getrlimit`
+/// and `fcntl` syscalls, without allocating or locking.
+pub(crate) fn close_inherited_fds(command: &mut Command) {
+    // SAFETY: the hook only invokes async-signal-safe syscalls, see above.
+    unsafe {
+        command.pre_exec(|| {
+            // Linux 5.11+
+            let ret = libc::syscall(
+                libc::SYS_close_range,
+                3 as libc::c_uint,
+                libc::c_uint::MAX,
+                libc::CLOSE_RANGE_CLOEXEC,
+            );
+            if ret == 0 {
+                return Ok(());
+            }
+
+            let mut rlimit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            if libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlimit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let max_fd = rlimit
+                .rlim_cur
+                .min(CLOSE_FDS_FALLBACK_LIMIT as libc::rlim_t)
+                as libc::c_int;
+            for fd in 3..max_fd {
+                let flags = libc::fcntl(fd, libc::F_GETFD);
+                if flags >= 0 && flags & libc::FD_CLOEXEC == 0 {
+                    libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::Path};
+
+    use super::{
+        close_inherited_fds, describe_snapshot, find_executable_in, FirecrackerOption, SnapshotInfo,
+    };
+    use crate::Error;
+
+    #[test]
+    fn find_executable_in_env_and_path() {
+        let path = env::var_os("PATH");
+        assert_eq!(
+            find_executable_in("no-such-binary", Some("/bin/sh".into()), path.clone()),
+            Some("/bin/sh".into())
+        );
+
+        // missing, fall back to `PATH`
+        assert!(
+            find_executable_in("sh", Some("/nonexistent/sh".into()), Some("/bin".into()))
+                .is_some_and(|path| path == Path::new("/bin/sh"))
+        );
+
+        assert_eq!(find_executable_in("no-such-binary", None, path), None);
+    }
+
+    #[test]
+    fn validate_start_time() {
+        let mut option = FirecrackerOption::new("/usr/bin/firecracker");
+        assert!(option.validate_args().is_ok());
+
+        option.parent_cpu_time_us(10);
+        assert!(matches!(
+            option.validate_args(),
+            Err(Error::Configuration(_))
+        ));
+
+        option.start_time_us(1000);
+        assert!(matches!(
+            option.validate_args(),
+            Err(Error::Configuration(_))
+        ));
+
+        option.start_time_cpu_us(0);
+        assert!(option.validate_args().is_ok());
+
+        let mut option = FirecrackerOption::new("/usr/bin/firecracker");
+        option.with_auto_start_time();
+        assert!(option.validate_args().is_ok());
+        assert!(option.start_time_us.is_some_and(|us| us > 0));
+    }
+
+    #[test]
+    fn validate_option() {
+        let option = FirecrackerOption::new("/nonexistent/firecracker");
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+
+        let mut option = FirecrackerOption::new("/bin/sh");
+        assert!(option.validate().is_ok());
+
+        option.config_file(Some("/nonexistent/config.json"));
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+        option.config_file(Some("/dev/null"));
+        assert!(option.validate().is_ok());
+
+        option.no_api();
+        assert!(option.validate().is_ok());
+        option.api_sock("/tmp/firecracker.socket");
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+
+        let mut option = FirecrackerOption::new("/bin/sh");
+        option.no_api();
+        assert!(matches!(
+            option.validate_args(),
+            Err(Error::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn check_kvm_device() {
+        use std::path::Path;
+
+        use super::check_kvm_device;
+
+        assert!(check_kvm_device(Path::new("/dev/null")).is_ok());
+        assert!(matches!(
+            check_kvm_device(Path::new("/nonexistent/kvm")),
+            Err(Error::Configuration(e)) if e.contains("does not exist")
+        ));
+    }
+
+    #[test]
+    fn parse_snapshot_info() {
+        let info = SnapshotInfo::parse("v1.4.0\n").unwrap();
+        assert_eq!(info.version, "v1.4.0");
+        #[cfg(feature = "models")]
+        assert_eq!(info.semver(), Some((1, 4, 0)));
+
+        assert!(matches!(
+            SnapshotInfo::parse("Bad snapshot\n"),
+            Err(Error::Configuration(_))
+        ));
+        assert!(matches!(
+            describe_snapshot("false", "/nonexistent/snapshot"),
+            Err(Error::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn close_fds_not_leaked() {
+        use std::{fs::File, os::fd::AsRawFd, process::Command};
+
+        // an inheritable descriptor, like one opened without `O_CLOEXEC` by a C library,
+        // numbered high enough not to be reused by `ls` itself; duplicated close-on-exec
+        // then cleared, rather than `dup2` over a descriptor another test might be using
+        let file = File::open("/dev/null").unwrap();
+        let fd = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 200) };
+        assert!(fd >= 200);
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_SETFD, 0) }, 0);
+        let child_has_fd = |close_fds: bool| {
+            let mut command = Command::new("ls");
+            command.arg("/proc/self/fd");
+            if close_fds {
+                close_inherited_fds(&mut command);
+            }
+            let output = command.output().unwrap();
+            String::from_utf8(output.stdout)
+                .unwrap()
+                .lines()
+                .any(|line| line == fd.to_string())
+        };
+
+        assert!(child_has_fd(false));
+        assert!(!child_has_fd(true));
+        unsafe { libc::close(fd) };
+    }
+
+    #[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+    #[test]
+    fn workspace_layout() {
+        use std::path::Path;
+
+        const DIR: &str = "/tmp/firecracker-sdk-test-workspace";
+        let _ = std::fs::remove_dir_all(DIR);
+
+        let mut option = FirecrackerOption::new("/bin/sh");
+        option.with_workspace(DIR, "vm0");
+        assert_eq!(option.id.as_deref(), Some("vm0"));
+        let workspace_dir = Path::new(DIR).join("vm0");
+        assert_eq!(option.api_sock, Some(workspace_dir.join("api.socket")));
+        assert_eq!(option.log_path, Some(workspace_dir.join("fc.log")));
+        assert_eq!(option.metrics_path, Some(workspace_dir.join("fc.metrics")));
+
+        let instance = option.build().unwrap();
+        assert_eq!(instance.workspace_dir(), Some(workspace_dir.as_path()));
+        assert!(workspace_dir.is_dir());
+
+        drop(instance);
+        assert!(!workspace_dir.exists());
+
+        // an existing directory is kept
+        std::fs::create_dir_all(&workspace_dir).unwrap();
+        drop(option.build().unwrap());
+        assert!(workspace_dir.is_dir());
+
+        // never the directory itself nor one of its parents
+        for id in ["", ".", "..", "vm0/..", "../vm0", "vm_0", &"x".repeat(65)] {
+            assert!(matches!(
+                FirecrackerOption::new("/bin/sh")
+                    .with_workspace(DIR, id)
+                    .build(),
+                Err(Error::Configuration(_))
+            ));
+        }
+        assert!(matches!(
+            FirecrackerOption::new("/bin/sh")
+                .with_workspace(DIR, "..")
+                .id("vm0")
+                .validate(),
+            Err(Error::Configuration(_))
+        ));
+        assert!(Path::new(DIR).is_dir());
+        let _ = std::fs::remove_dir_all(DIR);
+    }
+
+    #[test]
+    fn api_sock_in_tempdir() {
+        let api_sock = |option: &FirecrackerOption| option.api_sock.clone().unwrap();
+
+        let first = api_sock(FirecrackerOption::new("/bin/sh").api_sock_in_tempdir());
+        let second = api_sock(FirecrackerOption::new("/bin/sh").api_sock_in_tempdir());
+        assert_ne!(first, second);
+        for path in [first, second] {
+            assert_eq!(path.parent(), Some(std::env::temp_dir().as_path()));
+            let name = path.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with(&format!("firecracker-{}-", std::process::id())));
+        }
+    }
 }
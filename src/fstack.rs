@@ -1,52 +1,173 @@
-use std::{fs, path::PathBuf, process::Command};
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use log::{error, info};
+use log::{error, info, warn};
+
+/// Time given to a process to exit after `SIGTERM` before it's killed with `SIGKILL`.
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Interval of checking whether a terminated process has exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Time given to a process to exit by itself before giving up reaping it.
+const REAP_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub struct FStack {
     inner: Vec<FStackAction>,
+    on_error: Option<Arc<ErrorHook>>,
+    /// Mount points that failed to be unmounted, whose parent directories are kept
+    /// rather than removed through the mount.
+    mounted: Vec<PathBuf>,
+    /// See [`FStack::nonblocking`].
+    nonblocking: bool,
 }
 
+/// Callback invoked with each cleanup failure, see [`FStack::on_error`].
+pub type ErrorHook = dyn Fn(&FStackError) + Send + Sync;
+
 pub enum FStackAction {
     RemoveDirectory(PathBuf),
     RemoveFile(PathBuf),
-    TerminateProcess(u32),
+    /// Lazily unmount the mount point, e.g. made by [`crate::jailer::ChrootStrategy::BindMountStrategy`].
+    Unmount(PathBuf),
+    /// Send `SIGTERM` to `pid`, then `SIGKILL` if it's still alive after `grace`.
+    TerminateProcess {
+        pid: u32,
+        grace: Duration,
+    },
+    /// Wait for `pid`, a child of this process about to exit by itself (e.g. `jailer` after
+    /// forking `firecracker`), so that it doesn't linger as a zombie.
+    ReapProcess(u32),
+    #[cfg(feature = "cgroups")]
+    RemoveCgroup(cgroups_rs::Cgroup),
+}
+
+/// Failure of an [`FStackAction`] performed when the stack is dropped.
+#[derive(Debug, thiserror::Error)]
+pub enum FStackError {
+    #[error("FStack: fail to remove directory {}", path.display())]
+    RemoveDirectory {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("FStack: fail to remove file {}", path.display())]
+    RemoveFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("FStack: fail to unmount {}", path.display())]
+    Unmount {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// Sending `SIGTERM` failed, the process is left running.
+    #[error("FStack: fail to terminate process {pid}")]
+    TerminateProcess {
+        pid: u32,
+        #[source]
+        source: io::Error,
+    },
+    /// The process outlived the grace period and sending `SIGKILL` failed.
+    #[error("FStack: fail to kill process {pid}")]
+    KillProcess {
+        pid: u32,
+        #[source]
+        source: io::Error,
+    },
+    #[cfg(feature = "cgroups")]
+    #[error("FStack: fail to remove cgroup {path}")]
+    RemoveCgroup {
+        path: String,
+        #[source]
+        source: cgroups_rs::error::Error,
+    },
 }
 
 impl Drop for FStack {
     fn drop(&mut self) {
         while let Some(action) = self.inner.pop() {
-            match action {
+            let result = match action {
+                FStackAction::RemoveDirectory(dir)
+                    if self.mounted.iter().any(|path| path.starts_with(&dir)) =>
+                {
+                    warn!(
+                        "FStack: keep directory {} since a mount point in it failed to be unmounted",
+                        dir.display()
+                    );
+                    Ok(())
+                }
                 FStackAction::RemoveDirectory(dir) => {
                     info!("FStack: performing `RemoveDirectory({})`", dir.display());
-                    let dir: PathBuf = dir.into();
-                    if dir.exists() && dir.is_dir() {
-                        let _ = fs::remove_dir_all(&dir);
+                    let result = if dir.exists() && dir.is_dir() {
+                        fs::remove_dir_all(&dir)
                     } else {
-                        error!("FStack: {} does not exist!", dir.display());
-                    }
+                        Err(io::ErrorKind::NotFound.into())
+                    };
+                    result.map_err(|source| {
+                        error!(
+                            "FStack: fail to remove directory {}: {source}",
+                            dir.display()
+                        );
+                        FStackError::RemoveDirectory { path: dir, source }
+                    })
                 }
                 FStackAction::RemoveFile(path) => {
                     info!("FStack: performing `RemoveFile({})`", path.display());
-                    if let Err(e) = fs::remove_file(&path) {
-                        error!("FStack: fail to remove file {}: {e}", path.display());
-                        /* We could do nothing on error though... */
-                    }
+                    fs::remove_file(&path).map_err(|source| {
+                        error!("FStack: fail to remove file {}: {source}", path.display());
+                        FStackError::RemoveFile { path, source }
+                    })
                 }
-                FStackAction::TerminateProcess(pid) => {
-                    info!("FStack: performing `TerminateProcess({})`", pid);
-                    match Command::new("kill")
-                        .arg("-15")
-                        .arg(pid.to_string())
-                        .output()
-                    {
-                        Ok(_output) => {
-                            info!("FStack: killed process {pid}");
-                        }
-                        Err(e) => {
-                            error!("FStack: fail to terminate process {pid}: {e}");
+                FStackAction::Unmount(path) => self.perform_unmount(path),
+                FStackAction::TerminateProcess { pid, .. } if self.nonblocking => {
+                    info!("FStack: performing `TerminateProcess({pid})` without grace");
+                    kill_process(pid)
+                }
+                FStackAction::TerminateProcess { pid, grace } => {
+                    info!("FStack: performing `TerminateProcess({pid})`");
+                    terminate_process(pid, grace)
+                }
+                FStackAction::ReapProcess(pid) => {
+                    info!("FStack: performing `ReapProcess({pid})`");
+                    let timeout = if self.nonblocking {
+                        Duration::ZERO
+                    } else {
+                        REAP_TIMEOUT
+                    };
+                    reap_process(pid, timeout);
+                    Ok(())
+                }
+                #[cfg(feature = "cgroups")]
+                FStackAction::RemoveCgroup(cgroup) => {
+                    info!("FStack: performing `RemoveCgroup({})`", cgroup.path());
+                    // processes inside the cgroup might still be exiting
+                    let mut result = cgroup.delete();
+                    for _ in 0..10 {
+                        if result.is_ok() {
+                            break;
                         }
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                        result = cgroup.delete();
                     }
+                    result.map_err(|source| {
+                        error!("FStack: fail to remove cgroup {}: {source}", cgroup.path());
+                        FStackError::RemoveCgroup {
+                            path: cgroup.path().to_string(),
+                            source,
+                        }
+                    })
                 }
+            };
+            // best-effort: report the failure and go on with the rest of the actions
+            if let Err(e) = result {
+                self.report(&e);
             }
         }
     }
@@ -54,13 +175,103 @@ impl Drop for FStack {
 
 impl FStack {
     pub fn new() -> Self {
-        FStack { inner: Vec::new() }
+        FStack {
+            inner: Vec::new(),
+            on_error: None,
+            mounted: Vec::new(),
+            nonblocking: false,
+        }
+    }
+
+    /// Set a callback invoked with each failure of the actions performed when the stack is
+    /// dropped, e.g. to log or alert on leaked processes and directories. Cleanup stays
+    /// best-effort: a failed action doesn't stop the others and never panics.
+    pub fn on_error(&mut self, on_error: Box<ErrorHook>) {
+        self.on_error = Some(on_error.into());
+    }
+
+    /// Take out the actions, leaving an empty stack with the same error callback.
+    pub fn take(&mut self) -> FStack {
+        FStack {
+            inner: std::mem::take(&mut self.inner),
+            on_error: self.on_error.clone(),
+            mounted: std::mem::take(&mut self.mounted),
+            nonblocking: self.nonblocking,
+        }
+    }
+
+    /// Don't block the dropping thread, e.g. an async runtime worker: `TerminateProcess`
+    /// kills the process with `SIGKILL` right away instead of giving it the grace period
+    /// after `SIGTERM`, and `ReapProcess` only reaps a process that exited already.
+    pub fn nonblocking(&mut self) {
+        self.nonblocking = true;
+    }
+
+    #[cfg(all(test, feature = "_rt-std"))]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.is_empty()
     }
 
     pub fn push_action(&mut self, action: FStackAction) {
         self.inner.push(action);
     }
 
+    /// Drop the `TerminateProcess` and `ReapProcess` actions, keeping the others,
+    /// e.g. to leave the processes to the caller.
+    pub fn cancel_terminate_process(&mut self) {
+        self.inner.retain(|action| {
+            !matches!(
+                action,
+                FStackAction::TerminateProcess { .. } | FStackAction::ReapProcess(_)
+            )
+        });
+    }
+
+    /// Keep only the actions for which `keep` returns `true`, dropping the others without
+    /// performing them.
+    pub fn retain<F: FnMut(&FStackAction) -> bool>(&mut self, keep: F) {
+        self.inner.retain(keep);
+    }
+
+    /// Perform the `Unmount` actions now, keeping the others, and return whether all the
+    /// mount points were unmounted. Their parent directories are not removed otherwise.
+    pub fn unmount(&mut self) -> bool {
+        let (unmounts, others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.inner)
+            .into_iter()
+            .partition(|action| matches!(action, FStackAction::Unmount(_)));
+        self.inner = others;
+        let mut unmounted = true;
+        for action in unmounts.into_iter().rev() {
+            if let FStackAction::Unmount(path) = action {
+                if let Err(e) = self.perform_unmount(path) {
+                    self.report(&e);
+                    unmounted = false;
+                }
+            }
+        }
+        unmounted
+    }
+
+    fn perform_unmount(&mut self, path: PathBuf) -> Result<(), FStackError> {
+        info!("FStack: performing `Unmount({})`", path.display());
+        unmount(&path).map_err(|source| {
+            error!("FStack: fail to unmount {}: {source}", path.display());
+            self.mounted.push(path.clone());
+            FStackError::Unmount { path, source }
+        })
+    }
+
+    fn report(&self, e: &FStackError) {
+        if let Some(ref on_error) = self.on_error {
+            on_error(e);
+        }
+    }
+
+    /// Push an action to be performed after all the others.
+    pub fn push_action_bottom(&mut self, action: FStackAction) {
+        self.inner.insert(0, action);
+    }
+
     /// Drop this FStackStack without rollback.
     /// Called when we are sure that everything is running well and
     /// do not need rollback.
@@ -69,3 +280,289 @@ impl FStack {
         info!("FStack: stack cancelled, are we going well?");
     }
 }
+
+/// Send `SIGTERM` to `pid` and wait up to `grace` for it to exit, then `SIGKILL` it.
+fn terminate_process(pid: u32, grace: Duration) -> Result<(), FStackError> {
+    if let Err(source) = signal(pid, libc::SIGTERM) {
+        error!("FStack: fail to terminate process {pid}: {source}");
+        return Err(FStackError::TerminateProcess { pid, source });
+    }
+
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        if has_exited(pid) {
+            info!("FStack: terminated process {pid}");
+            return Ok(());
+        }
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+    }
+
+    warn!("FStack: process {pid} still alive {grace:?} after `SIGTERM`, killing it");
+    kill_process(pid)
+}
+
+/// Send `SIGKILL` to `pid` and reap it.
+fn kill_process(pid: u32) -> Result<(), FStackError> {
+    if let Err(source) = signal(pid, libc::SIGKILL) {
+        error!("FStack: fail to kill process {pid}: {source}");
+        return Err(FStackError::KillProcess { pid, source });
+    }
+    // reap it if it's a child of this process, `SIGKILL` can't be ignored so this won't hang
+    let mut status = 0;
+    // SAFETY: `status` is a valid pointer to write the exit status to.
+    unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) };
+    Ok(())
+}
+
+/// Wait up to `timeout` for the child `pid` to exit and reap it.
+fn reap_process(pid: u32, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut status = 0;
+        // SAFETY: `status` is a valid pointer to write the exit status to.
+        match unsafe { libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG) } {
+            // still running
+            0 if Instant::now() < deadline => std::thread::sleep(EXIT_POLL_INTERVAL),
+            0 => {
+                warn!("FStack: process {pid} still alive {timeout:?} later, not reaped");
+                return;
+            }
+            // reaped, or not a child of this process (anymore)
+            _ => return,
+        }
+    }
+}
+
+fn unmount(path: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `path` is a valid NUL-terminated string.
+    match unsafe { libc::umount2(path.as_ptr(), libc::MNT_DETACH) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+pub(crate) fn signal(pid: u32, signal: libc::c_int) -> io::Result<()> {
+    // SAFETY: `kill` has no memory safety requirements.
+    match unsafe { libc::kill(pid as libc::pid_t, signal) } {
+        0 => Ok(()),
+        _ => match io::Error::last_os_error() {
+            // exited already
+            e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            e => Err(e),
+        },
+    }
+}
+
+/// Whether `pid` has exited, reaping it if it's a child of this process since a zombie
+/// would otherwise look alive.
+fn has_exited(pid: u32) -> bool {
+    let pid = pid as libc::pid_t;
+    let mut status = 0;
+    // SAFETY: `status` is a valid pointer to write the exit status to.
+    match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+        0 => return false,
+        reaped if reaped == pid => return true,
+        // not a child of this process
+        _ => (),
+    }
+    // SAFETY: signal 0 only checks the existence of `pid`.
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    !alive && io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{BufRead, BufReader},
+        path::Path,
+        process::{Command, Stdio},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use super::{has_exited, FStack, FStackAction, FStackError};
+
+    // the child is reaped by `FStack`
+    #[allow(clippy::zombie_processes)]
+    fn terminate(command: &mut Command, grace: Duration, nonblocking: bool) -> Duration {
+        let mut child = command.stdout(Stdio::piped()).spawn().unwrap();
+        let pid = child.id();
+        // wait until the signal handling is set up
+        let mut ready = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut ready)
+            .unwrap();
+
+        let mut fstack = FStack::new();
+        if nonblocking {
+            fstack.nonblocking();
+        }
+        fstack.push_action(FStackAction::TerminateProcess { pid, grace });
+
+        let start = Instant::now();
+        drop(fstack);
+        let elapsed = start.elapsed();
+        assert!(has_exited(pid));
+        elapsed
+    }
+
+    #[test]
+    fn terminate_process() {
+        let grace = Duration::from_millis(500);
+
+        // exits on `SIGTERM`
+        let elapsed = terminate(
+            Command::new("sh").args(["-c", "echo ready; exec sleep 10"]),
+            grace,
+            false,
+        );
+        assert!(elapsed < grace);
+
+        // ignores `SIGTERM`, killed after the grace period
+        let elapsed = terminate(
+            Command::new("sh").args(["-c", "trap '' TERM; echo ready; exec sleep 10"]),
+            grace,
+            false,
+        );
+        assert!(elapsed >= grace);
+
+        // killed right away without blocking for the grace period
+        let elapsed = terminate(
+            Command::new("sh").args(["-c", "trap '' TERM; echo ready; exec sleep 10"]),
+            grace,
+            true,
+        );
+        assert!(elapsed < grace);
+    }
+
+    #[test]
+    #[allow(clippy::zombie_processes)]
+    fn reap_process() {
+        // a zombie still has its `/proc` entry, in state `Z`
+        let is_gone = |pid: u32| !Path::new(&format!("/proc/{pid}")).exists();
+
+        // exits by itself shortly, the `Child` is dropped without waiting for it
+        let pids: Vec<_> = ["exit 0", "sleep 0.1"]
+            .into_iter()
+            .map(|script| {
+                Command::new("sh")
+                    .args(["-c", script])
+                    .spawn()
+                    .unwrap()
+                    .id()
+            })
+            .collect();
+        let mut fstack = FStack::new();
+        for &pid in &pids {
+            fstack.push_action(FStackAction::ReapProcess(pid));
+        }
+        drop(fstack);
+        for pid in pids {
+            assert!(is_gone(pid));
+        }
+    }
+
+    #[test]
+    fn on_error() {
+        const FILE: &str = "/tmp/firecracker-sdk-unit-test-fstack.file";
+        const MISSING_FILE: &str = "/tmp/firecracker-sdk-unit-test-fstack-missing.file";
+        const MISSING_DIR: &str = "/tmp/firecracker-sdk-unit-test-fstack-missing.dir";
+        let _ = fs::remove_file(MISSING_FILE);
+        let _ = fs::remove_dir_all(MISSING_DIR);
+        fs::write(FILE, "").unwrap();
+
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reported = errors.clone();
+        let mut fstack = FStack::new();
+        fstack.on_error(Box::new(move |e: &FStackError| {
+            let path = match e {
+                FStackError::RemoveDirectory { path, .. }
+                | FStackError::RemoveFile { path, .. } => path.clone(),
+                _ => unreachable!(),
+            };
+            reported.lock().unwrap().push(path);
+        }));
+        // the error callback is kept by the stack left behind
+        fstack.take().cancel();
+
+        fstack.push_action_bottom(FStackAction::RemoveDirectory(MISSING_DIR.into()));
+        fstack.push_action(FStackAction::RemoveFile(MISSING_FILE.into()));
+        fstack.push_action(FStackAction::RemoveFile(FILE.into()));
+        drop(fstack);
+
+        // performed in reverse order, the failures don't stop the others
+        assert!(!Path::new(FILE).exists());
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [Path::new(MISSING_FILE), Path::new(MISSING_DIR)]
+        );
+    }
+
+    #[test]
+    fn failed_unmount() {
+        const JAIL: &str = "/tmp/firecracker-sdk-unit-test-fstack-jail";
+        const OTHER_DIR: &str = "/tmp/firecracker-sdk-unit-test-fstack-other";
+        let mount_point = Path::new(JAIL).join("root/rootfs");
+        fs::create_dir_all(&mount_point).unwrap();
+        fs::create_dir_all(OTHER_DIR).unwrap();
+
+        // not a mount point, so unmounting it fails
+        let mut fstack = FStack::new();
+        fstack.push_action(FStackAction::RemoveDirectory(JAIL.into()));
+        fstack.push_action(FStackAction::RemoveDirectory(OTHER_DIR.into()));
+        fstack.push_action(FStackAction::Unmount(mount_point.clone()));
+        assert!(!fstack.unmount());
+        drop(fstack);
+
+        // the directory holding the mount point is kept, the others are removed
+        assert!(mount_point.exists());
+        assert!(!Path::new(OTHER_DIR).exists());
+        fs::remove_dir_all(JAIL).unwrap();
+    }
+
+    #[cfg(feature = "cgroups")]
+    #[test]
+    fn remove_cgroup_last() {
+        let mut fstack = FStack::new();
+        // pushed when `firecracker` is spawned
+        fstack.push_action(FStackAction::ReapProcess(2));
+        fstack.push_action(FStackAction::TerminateProcess {
+            pid: 1,
+            grace: Duration::ZERO,
+        });
+        // pushed by `apply_cgroup`
+        fstack.push_action_bottom(FStackAction::RemoveCgroup(cgroups_rs::Cgroup::load(
+            cgroups_rs::hierarchies::auto(),
+            "firecracker-sdk-unit-test-fstack",
+        )));
+        fstack.push_action(FStackAction::RemoveFile("/tmp/socket".into()));
+
+        // performed in reverse order: the cgroup is only removed once it's empty
+        let performed: Vec<_> = fstack
+            .inner
+            .iter()
+            .rev()
+            .map(|action| match action {
+                FStackAction::RemoveFile(_) => "RemoveFile",
+                FStackAction::TerminateProcess { .. } => "TerminateProcess",
+                FStackAction::ReapProcess(_) => "ReapProcess",
+                FStackAction::RemoveCgroup(_) => "RemoveCgroup",
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            performed,
+            [
+                "RemoveFile",
+                "TerminateProcess",
+                "ReapProcess",
+                "RemoveCgroup"
+            ]
+        );
+        fstack.cancel();
+    }
+}
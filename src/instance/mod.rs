@@ -1,33 +1,126 @@
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+use std::collections::BTreeMap;
 #[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
-use std::{path::Path, process::Child};
+use std::{
+    fs,
+    io::Write,
+    os::fd::{AsRawFd, RawFd},
+    path::Path,
+    process::{Child, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+};
 use std::{path::PathBuf, process::Command};
 
 use crate::jailer::ChrootStrategy;
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+use crate::models::{
+    Drive, NetworkInterface, PartialDrive, PartialNetworkInterface, Validate, Vsock,
+};
 #[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
-use crate::{agent::SocketAgent, fstack::FStack, Error, Result};
+use log::warn;
+
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+use crate::{
+    agent::SocketAgent,
+    console::{ConsolePipe, ConsoleWriter},
+    fstack::{FStack, FStackAction, FStackError},
+    log_stream::{LogStream, MetricsStream},
+    Error, Result,
+};
+
+/// Interval and attempts of reaping `jailer` after `firecracker` is up.
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+const JAILER_REAP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+const JAILER_REAP_ATTEMPTS: usize = 50;
+
+/// Interval of polling whether `firecracker` has exited in [`Instance::wait`].
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+const EXIT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// First interval between the attempts of [`Instance::wait_until_ready`], doubled after each
+/// failed attempt.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+const READY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+/// Longest interval between the attempts of [`Instance::wait_until_ready`].
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+const READY_POLL_MAX_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
 
 #[cfg(feature = "_rt-async")]
 mod rt_async;
 #[cfg(feature = "_rt-std")]
 mod rt_std;
+#[cfg(feature = "models")]
+mod vm_config;
+
+#[cfg(all(feature = "_rt-async", feature = "models"))]
+pub use rt_async::{BalloonStatsStream, PauseGuard};
+#[cfg(all(feature = "_rt-std", feature = "models"))]
+pub use rt_std::{BalloonStatsStream, PauseGuard};
+#[cfg(feature = "models")]
+pub use vm_config::VmConfigBuilder;
+
+/// Lifecycle of an [`Instance`] as tracked by the SDK, without querying `firecracker`.
+/// Not to be confused with the state of the guest ([`crate::models::VmState`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum InstanceLifecycle {
+    /// Built, [`Instance::start_vmm`] not called yet.
+    #[default]
+    Created,
+    /// `firecracker` is spawned and accepts the pre-boot configuration.
+    Configured,
+    /// The VM is started (`InstanceStart`) or restored from a snapshot.
+    Started,
+}
 
 #[cfg(not(any(feature = "_rt-std", feature = "_rt-async")))]
 pub struct Instance {}
 
 #[cfg(not(any(feature = "_rt-std", feature = "_rt-async")))]
 impl Instance {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         _socket_on_host: PathBuf,
         _jailer_workspace_dir: Option<PathBuf>,
         _chroot_strategy: Option<ChrootStrategy>,
         _remove_jailer_workspace_dir: Option<bool>,
         _command: Command,
-        _exec_file_name: PathBuf,
+        _pid_file_name: Option<PathBuf>,
+        _log_path: Option<PathBuf>,
+        _metrics_path: Option<PathBuf>,
+        _keep_socket: Option<bool>,
+        _new_pid_ns: Option<bool>,
+        _shutdown_grace: Option<std::time::Duration>,
     ) -> Self {
         crate::missing_rt_panic!()
     }
+
+    pub(crate) fn set_workspace_dir(&mut self, _workspace_dir: PathBuf, _remove: bool) {
+        crate::missing_rt_panic!()
+    }
+
+    pub(crate) fn set_log_on_host(&mut self, _log_on_host: PathBuf) {
+        crate::missing_rt_panic!()
+    }
+
+    pub(crate) fn set_http_api_max_payload_size(&mut self, _http_api_max_payload_size: usize) {
+        crate::missing_rt_panic!()
+    }
+
+    pub(crate) fn set_read_buffer_size(&mut self, _read_buffer_size: usize) {
+        crate::missing_rt_panic!()
+    }
 }
 
+/// A `firecracker` microVM, spawned directly or through `jailer`.
+///
+/// Dropping it cleans up synchronously and best-effort: `firecracker` is sent `SIGTERM`
+/// (then `SIGKILL` after the shutdown grace) and the socket and workspaces are removed,
+/// blocking the dropping thread meanwhile.
+///
+/// With an async runtime, dropping it doesn't wait for the shutdown grace: `firecracker` is
+/// killed with `SIGKILL` right away, see [`FStack::nonblocking`]. Shut it down gracefully
+/// with `Instance::close` instead.
 #[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
 pub struct Instance {
     socket_on_host: PathBuf,
@@ -38,6 +131,12 @@ pub struct Instance {
 
     remove_jailer_workspace_dir: Option<bool>,
 
+    // per-instance directory of bare `firecracker`, see `FirecrackerOption::with_workspace`
+    workspace_dir: Option<PathBuf>,
+
+    // whether `workspace_dir` was created when building the instance, and is removed with it
+    remove_workspace_dir: bool,
+
     command: Command,
 
     child: Option<Child>,
@@ -46,35 +145,157 @@ pub struct Instance {
 
     fstack: FStack,
 
-    exec_file_name: PathBuf,
+    // name of the pid file of `firecracker` in the jailer workspace directory
+    pid_file_name: Option<PathBuf>,
 
     jailer_pid: Option<u32>,
 
+    jailer_exit_status: Option<ExitStatus>,
+
     firecracker_pid: Option<u32>,
+
+    log_path: Option<PathBuf>,
+
+    // on-host path of the log, i.e. `log_path` resolved in the jail or set by `put_logger`
+    log_on_host: Option<PathBuf>,
+
+    log_stream: Option<LogStream>,
+
+    metrics_path: Option<PathBuf>,
+
+    metrics_stream: Option<MetricsStream>,
+
+    keep_socket: Option<bool>,
+
+    new_pid_ns: Option<bool>,
+
+    // time given to `firecracker` to exit after `SIGTERM` before it's killed with `SIGKILL`
+    shutdown_grace: std::time::Duration,
+
+    // `--http-api-max-payload-size` of `firecracker`
+    http_api_max_payload_size: usize,
+
+    // size of the reads of API responses, passed on to the agent
+    read_buffer_size: usize,
+
+    console_writer: Option<ConsoleWriter>,
+
+    // dropped after `fstack` terminated `firecracker`, so the copier reaches the end of output
+    console_pipe: Option<ConsolePipe>,
+
+    lifecycle: InstanceLifecycle,
+
+    #[cfg(feature = "models")]
+    legacy_balloon_amount: Option<bool>,
+
+    #[cfg(feature = "models")]
+    configured: ConfiguredDevices,
+
+    #[cfg(feature = "models")]
+    skip_validation: bool,
+
+    // whether `firecracker` was asked to track dirty guest pages, which diff snapshots need
+    #[cfg(feature = "models")]
+    track_dirty_pages: bool,
+}
+
+/// Devices successfully configured through an [`Instance`], as sent by the SDK
+/// (i.e. with the host paths rather than the jailed ones), keyed by ID.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+#[derive(Debug, Default)]
+struct ConfiguredDevices {
+    drives: BTreeMap<String, Drive>,
+    network_interfaces: BTreeMap<String, NetworkInterface>,
+    vsock: Option<Vsock>,
+}
+
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+impl ConfiguredDevices {
+    fn patch_drive(&mut self, partial_drive: &PartialDrive) {
+        let Some(drive) = self.drives.get_mut(&partial_drive.drive_id) else {
+            return;
+        };
+        if let Some(ref path_on_host) = partial_drive.path_on_host {
+            drive.path_on_host = path_on_host.clone();
+        }
+        if let Some(ref rate_limiter) = partial_drive.rate_limiter {
+            drive.rate_limiter = Some(rate_limiter.clone());
+        }
+    }
+
+    fn patch_network_interface(&mut self, partial_network_interface: &PartialNetworkInterface) {
+        let Some(network_interface) = self
+            .network_interfaces
+            .get_mut(&partial_network_interface.iface_id)
+        else {
+            return;
+        };
+        if let Some(ref rx_rate_limiter) = partial_network_interface.rx_rate_limiter {
+            network_interface.rx_rate_limiter = Some(rx_rate_limiter.clone());
+        }
+        if let Some(ref tx_rate_limiter) = partial_network_interface.tx_rate_limiter {
+            network_interface.tx_rate_limiter = Some(tx_rate_limiter.clone());
+        }
+    }
 }
 
 #[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
 impl Instance {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         socket_on_host: PathBuf,
         jailer_workspace_dir: Option<PathBuf>,
         chroot_strategy: Option<ChrootStrategy>,
         remove_jailer_workspace_dir: Option<bool>,
         command: Command,
-        exec_file_name: PathBuf,
+        pid_file_name: Option<PathBuf>,
+        log_path: Option<PathBuf>,
+        metrics_path: Option<PathBuf>,
+        keep_socket: Option<bool>,
+        new_pid_ns: Option<bool>,
+        shutdown_grace: Option<std::time::Duration>,
     ) -> Self {
+        #[allow(unused_mut)]
+        let mut fstack = FStack::new();
+        // an async runtime worker dropping the instance must not block, see `Instance::close`
+        #[cfg(feature = "_rt-async")]
+        fstack.nonblocking();
         Self {
             socket_on_host,
             jailer_workspace_dir,
             chroot_strategy,
             remove_jailer_workspace_dir,
+            workspace_dir: None,
+            remove_workspace_dir: false,
             command,
             child: None,
             agent: None,
-            fstack: FStack::new(),
-            exec_file_name,
+            fstack,
+            pid_file_name,
             jailer_pid: None,
+            jailer_exit_status: None,
             firecracker_pid: None,
+            log_on_host: log_path.clone(),
+            log_path,
+            log_stream: None,
+            metrics_path,
+            metrics_stream: None,
+            keep_socket,
+            new_pid_ns,
+            shutdown_grace: shutdown_grace.unwrap_or(crate::fstack::DEFAULT_SHUTDOWN_GRACE),
+            http_api_max_payload_size: crate::firecracker::DEFAULT_HTTP_API_MAX_PAYLOAD_SIZE,
+            read_buffer_size: crate::agent::DEFAULT_READ_BUFFER_SIZE,
+            console_writer: None,
+            console_pipe: None,
+            lifecycle: InstanceLifecycle::Created,
+            #[cfg(feature = "models")]
+            legacy_balloon_amount: None,
+            #[cfg(feature = "models")]
+            configured: ConfiguredDevices::default(),
+            #[cfg(feature = "models")]
+            skip_validation: false,
+            #[cfg(feature = "models")]
+            track_dirty_pages: false,
         }
     }
 
@@ -87,12 +308,42 @@ impl Instance {
 
     /// Check whether this instance would remove jailer workspace directory
     /// (i.e. <chroot_base>/exec_file_name/<id>/root/) when it's dropped.
+    /// Only `Some(true)` removes it, `Some(false)` means it's explicitly kept.
     ///
     /// Always returns [`None`] if the instance is not spawned with `jailer` (bare `firecracker`).
     pub fn remove_jailer_workspace_dir(&self) -> Option<bool> {
         self.remove_jailer_workspace_dir
     }
 
+    /// Returns the per-instance directory of bare `firecracker`, which is removed when
+    /// the instance is dropped if it was created when building the instance, see
+    /// [`crate::firecracker::FirecrackerOption::with_workspace`].
+    pub fn workspace_dir(&self) -> Option<&Path> {
+        self.workspace_dir.as_deref()
+    }
+
+    /// Set a callback invoked with each failure of the cleanup performed when the instance is
+    /// dropped (terminating `firecracker`, removing the API socket, the workspace directories,
+    /// ...), which is otherwise only logged. See [`FStack::on_error`].
+    pub fn on_cleanup_error<F>(&mut self, on_error: F) -> &mut Self
+    where
+        F: Fn(&FStackError) + Send + Sync + 'static,
+    {
+        self.fstack.on_error(Box::new(on_error));
+        self
+    }
+
+    /// Set `workspace_dir`, removed after everything else when the instance is dropped
+    /// if `remove`.
+    pub(crate) fn set_workspace_dir(&mut self, workspace_dir: PathBuf, remove: bool) {
+        if remove {
+            self.fstack
+                .push_action_bottom(FStackAction::RemoveDirectory(workspace_dir.clone()));
+        }
+        self.workspace_dir = Some(workspace_dir);
+        self.remove_workspace_dir = remove;
+    }
+
     /// Returns `firecracker` PID of this instance.
     pub fn firecracker_pid(&self) -> Option<u32> {
         self.firecracker_pid
@@ -105,7 +356,394 @@ impl Instance {
         self.jailer_pid
     }
 
-    /// Returns the hard link inside the jailer corresponding to `path`.
+    /// Returns the exit status of `jailer` once [`Instance::start_vmm`] has reaped it, either
+    /// after it forked `firecracker` (e.g. with `--new-pid-ns`) or after it failed before
+    /// `firecracker` came up.
+    ///
+    /// Returns [`None`] if `jailer` exec'ed into `firecracker` (sharing its PID), or if the
+    /// instance is not spawned with `jailer`.
+    pub fn jailer_exit_status(&self) -> Option<ExitStatus> {
+        self.jailer_exit_status
+    }
+
+    /// Returns the on-host path of the `firecracker` log: the `log_path` of the
+    /// `FirecrackerOption` (inside the jailer workspace directory when spawned with `jailer`),
+    /// or the `log_path` of the logger last configured with `put_logger`.
+    pub fn log_path(&self) -> Option<&Path> {
+        self.log_on_host.as_deref()
+    }
+
+    /// Point [`Instance::log_path`] at the log of `firecracker` in the jail.
+    pub(crate) fn set_log_on_host(&mut self, log_on_host: PathBuf) {
+        self.log_on_host = Some(log_on_host);
+    }
+
+    /// Returns the largest request body `firecracker` accepts, in bytes, as set by
+    /// `FirecrackerOption::http_api_max_payload_size`.
+    pub fn http_api_max_payload_size(&self) -> usize {
+        self.http_api_max_payload_size
+    }
+
+    pub(crate) fn set_http_api_max_payload_size(&mut self, http_api_max_payload_size: usize) {
+        self.http_api_max_payload_size = http_api_max_payload_size;
+    }
+
+    pub(crate) fn set_read_buffer_size(&mut self, read_buffer_size: usize) {
+        self.read_buffer_size = read_buffer_size;
+    }
+
+    /// Takes the stream of the log output configured by `FirecrackerOption::log_path`.
+    /// The log path is opened for reading right before `firecracker` is spawned, so a FIFO
+    /// log path would not block `firecracker` at startup.
+    ///
+    /// Returns [`None`] before [`Instance::start_vmm`], if no log path is configured, if the
+    /// instance is spawned with `jailer`, or if the stream has already been taken.
+    pub fn take_log_stream(&mut self) -> Option<LogStream> {
+        self.log_stream.take()
+    }
+
+    /// Takes the stream of the metrics output configured by `FirecrackerOption::metrics_path`.
+    /// Like the log path, the metrics path is opened for reading right before `firecracker`
+    /// is spawned, so a FIFO metrics path would not block `firecracker` at startup.
+    ///
+    /// Returns [`None`] before [`Instance::start_vmm`], if no metrics path is configured, if the
+    /// instance is spawned with `jailer`, or if the stream has already been taken.
+    pub fn take_metrics_stream(&mut self) -> Option<MetricsStream> {
+        self.metrics_stream.take()
+    }
+
+    /// Copy the guest serial console (the stdout of `firecracker`) into `writer`, e.g. a file,
+    /// a channel or a socket, from a background thread (a blocking task with async runtimes).
+    ///
+    /// Must be called before [`Instance::start_vmm`], and overrides `FirecrackerOption::stdout`
+    /// (or `JailerOption::stdout`). The copier is also attached to a `firecracker` respawned by
+    /// `restart_vmm`, and is joined when the instance is dropped: the drop then blocks the
+    /// current thread, even with async runtimes, for up to 1 second while the copier drains
+    /// the console.
+    pub fn pipe_console_to<W: Write + Send + 'static>(&mut self, writer: W) -> Result<()> {
+        if self.child.is_some() {
+            return Err(Error::Instance(
+                "pipe_console_to() must be called before start_vmm()".into(),
+            ));
+        }
+        self.command.stdout(Stdio::piped());
+        self.console_writer = Some(Arc::new(Mutex::new(Box::new(writer))));
+        Ok(())
+    }
+
+    /// Start copying the console output of the just spawned process, if requested.
+    fn spawn_console_pipe(&mut self) {
+        let (Some(writer), Some(child)) = (&self.console_writer, &mut self.child) else {
+            return;
+        };
+        if let Some(stdout) = child.stdout.take() {
+            self.console_pipe = Some(ConsolePipe::spawn(stdout, writer.clone()));
+        }
+    }
+
+    /// Returns the lifecycle of this instance.
+    pub fn state(&self) -> InstanceLifecycle {
+        self.lifecycle
+    }
+
+    /// Fail with [`Error::Instance`] if the VM has started, since `firecracker` only accepts
+    /// `operation` before boot.
+    ///
+    /// Pre-boot-only operations are the `put_*` methods of the balloon, boot source,
+    /// CPU configuration, drives, logger, machine configuration, metrics, MMDS config,
+    /// entropy device, network interfaces and vsock, `patch_machine_configuration` and
+    /// `load_snapshot`. Drives and network interfaces can still be patched after boot.
+    #[cfg(feature = "models")]
+    fn check_pre_boot(&self, operation: &str) -> Result<()> {
+        if self.lifecycle == InstanceLifecycle::Started {
+            return Err(Error::Instance(format!(
+                "cannot {operation} after VM start"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Warn that a diff snapshot requested by `params` will fail, unless dirty page tracking
+    /// was enabled by `track_dirty_pages` of the machine configuration (or by
+    /// `enable_diff_snapshots` of the loaded snapshot).
+    #[cfg(feature = "models")]
+    fn check_diff_snapshot(&self, params: &crate::models::SnapshotCreateParams) {
+        if params.snapshot_type == Some(crate::models::SnapshotType::Diff)
+            && !self.track_dirty_pages
+        {
+            warn!(
+                "diff snapshot requested but dirty page tracking was never enabled \
+                (`track_dirty_pages` of the machine configuration)"
+            );
+        }
+    }
+
+    /// Send the models to `firecracker` as they are, without the [`Validate`] checks
+    /// the `put_*` methods otherwise run first, e.g. to leave it to `firecracker` to judge
+    /// a configuration the SDK is stricter about.
+    #[cfg(feature = "models")]
+    pub fn skip_validation(&mut self) -> &mut Self {
+        self.skip_validation = true;
+        self
+    }
+
+    /// Validate `model` before sending it, unless [`Instance::skip_validation`] is set.
+    #[cfg(feature = "models")]
+    fn validate_model<V: Validate>(&self, model: &V) -> Result<()> {
+        if self.skip_validation {
+            return Ok(());
+        }
+        model.validate()
+    }
+
+    /// Fail before sending if the JSON body of `payload` exceeds
+    /// [`Instance::http_api_max_payload_size`], which `firecracker` would reject with
+    /// a terse `400 Bad Request`.
+    #[cfg(feature = "models")]
+    fn check_payload_size<T: serde::Serialize>(&self, operation: &str, payload: &T) -> Result<()> {
+        let len = serde_json::to_vec(payload)
+            .map_err(|e| Error::Event(format!("serde_json encode: {e}")))?
+            .len();
+        if len > self.http_api_max_payload_size {
+            return Err(Error::Configuration(format!(
+                "cannot {operation}: the request body is {len} bytes, larger than the \
+                 `http_api_max_payload_size` of {} bytes, \
+                 see `FirecrackerOption::http_api_max_payload_size`",
+                self.http_api_max_payload_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the IDs of the drives configured through this instance, in ascending order.
+    ///
+    /// Only drives successfully put with [`Instance::put_guest_drive_by_id`] since `firecracker`
+    /// was (re)spawned are tracked, not those restored from a snapshot.
+    #[cfg(feature = "models")]
+    pub fn drive_ids(&self) -> Vec<String> {
+        self.configured.drives.keys().cloned().collect()
+    }
+
+    /// Returns the drive `drive_id` as configured through this instance, with the patches
+    /// applied by [`Instance::patch_guest_drive_by_id`]. The path is the one on the host even
+    /// when spawned with `jailer`.
+    #[cfg(feature = "models")]
+    pub fn configured_drive(&self, drive_id: &str) -> Option<&Drive> {
+        self.configured.drives.get(drive_id)
+    }
+
+    /// Returns the IDs of the network interfaces configured through this instance, in
+    /// ascending order. See [`Instance::drive_ids`] for what is tracked.
+    #[cfg(feature = "models")]
+    pub fn network_interface_ids(&self) -> Vec<String> {
+        self.configured.network_interfaces.keys().cloned().collect()
+    }
+
+    /// Returns the network interface `iface_id` as configured through this instance, with
+    /// the patches applied by [`Instance::patch_guest_network_interface_by_id`].
+    #[cfg(feature = "models")]
+    pub fn configured_network_interface(&self, iface_id: &str) -> Option<&NetworkInterface> {
+        self.configured.network_interfaces.get(iface_id)
+    }
+
+    /// Returns the vsock device as configured through this instance, with the host path of
+    /// its Unix domain socket.
+    #[cfg(feature = "models")]
+    pub fn configured_vsock(&self) -> Option<&Vsock> {
+        self.configured.vsock.as_ref()
+    }
+
+    /// Returns the raw file descriptor of the connected API socket.
+    ///
+    /// Always returns [`None`] before [`Instance::start_vmm`] succeeded.
+    ///
+    /// Reading from / writing to the descriptor directly bypasses the HTTP framing of this
+    /// crate and would corrupt subsequent requests, so it's only meant for advanced integration
+    /// such as registering the socket in a custom epoll / mio reactor.
+    /// The descriptor changes if `firecracker` closed the connection and it was reconnected.
+    pub fn socket_raw_fd(&self) -> Option<RawFd> {
+        self.agent.as_ref().map(|agent| agent.as_raw_fd())
+    }
+
+    /// Resolve the host-visible PID of `firecracker` from the PID read from the pid file.
+    ///
+    /// With `--new-pid-ns`, the PID in the file may be local to the new PID namespace, so
+    /// look for the process chrooted in our jailer workspace directory whose innermost
+    /// namespace PID matches. The children of `jailer` are checked before scanning `/proc`.
+    fn resolve_firecracker_pid(&self, jailer_pid: u32, pid_in_file: u32) -> u32 {
+        if self.new_pid_ns != Some(true) {
+            return pid_in_file;
+        }
+        let children = fs::read_to_string(format!("/proc/{jailer_pid}/task/{jailer_pid}/children"))
+            .unwrap_or_default();
+        let candidates = children
+            .split_whitespace()
+            .filter_map(|pid| pid.parse::<u32>().ok())
+            .chain(
+                fs::read_dir("/proc")
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok()),
+            );
+        for pid in candidates {
+            if self.is_jailed_firecracker(pid, pid_in_file) {
+                return pid;
+            }
+        }
+        warn!("Cannot resolve host PID of `firecracker` (PID {pid_in_file} in its namespace)");
+        pid_in_file
+    }
+
+    /// Check whether host process `pid` is our jailed `firecracker` with PID `ns_pid`
+    /// in its own PID namespace.
+    fn is_jailed_firecracker(&self, pid: u32, ns_pid: u32) -> bool {
+        let Some(ref jailer_workspace_dir) = self.jailer_workspace_dir else {
+            return false;
+        };
+        let Ok(status) = fs::read_to_string(format!("/proc/{pid}/status")) else {
+            return false;
+        };
+        let innermost_ns_pid = status
+            .lines()
+            .find_map(|line| line.strip_prefix("NSpid:"))
+            .and_then(|ns_pids| ns_pids.split_whitespace().last())
+            .and_then(|ns_pid| ns_pid.parse::<u32>().ok());
+        innermost_ns_pid == Some(ns_pid)
+            && fs::read_link(format!("/proc/{pid}/root")).is_ok_and(|root| {
+                root == fs::canonicalize(jailer_workspace_dir)
+                    .unwrap_or_else(|_| jailer_workspace_dir.clone())
+            })
+    }
+
+    /// Hand over the spawned `firecracker` (or `jailer`) process to the caller, e.g. to put it
+    /// under the supervision of a larger process manager.
+    ///
+    /// After this call the process is no longer managed by the SDK: it is not terminated when
+    /// the instance is dropped. The API socket and the jailer workspace directory are still
+    /// cleaned up as configured by `keep_socket` / `remove_jailer_workspace_dir`, so set
+    /// `keep_socket` (and don't remove the workspace) if the process keeps serving its API.
+    ///
+    /// Returns [`None`] if the VMM is not spawned.
+    pub fn into_child(mut self) -> Option<Child> {
+        self.fstack.cancel_terminate_process();
+        if let Some(console_pipe) = self.console_pipe.take() {
+            console_pipe.detach();
+        }
+        self.child.take()
+    }
+
+    /// Move `firecracker` into the cgroup described by `cgroup_option` and set its controllers.
+    /// The cgroup is removed after `firecracker` is terminated when the instance is dropped.
+    ///
+    /// This is complementary to the `--cgroup` flags of `jailer`, see [`crate::cgroup`].
+    #[cfg(feature = "cgroups")]
+    pub fn apply_cgroup(&mut self, cgroup_option: &crate::cgroup::CgroupOption) -> Result<()> {
+        let Some(firecracker_pid) = self.firecracker_pid else {
+            return Err(Error::NotStarted);
+        };
+        let cgroup = cgroup_option.apply(firecracker_pid)?;
+        self.fstack
+            .push_action_bottom(crate::fstack::FStackAction::RemoveCgroup(cgroup));
+        Ok(())
+    }
+
+    /// Reap `jailer` if it has exited, which it does right after forking `firecracker`
+    /// (e.g. with `--new-pid-ns`) instead of exec'ing into it.
+    /// Returns whether there's no `jailer` left to reap.
+    fn try_reap_jailer(&mut self) -> bool {
+        if self.jailer_exit_status.is_some() {
+            return true;
+        }
+        match (self.jailer_pid, self.firecracker_pid, &mut self.child) {
+            (Some(jailer_pid), Some(firecracker_pid), Some(child))
+                if jailer_pid != firecracker_pid =>
+            {
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        self.jailer_exit_status = Some(status);
+                        true
+                    }
+                    Ok(None) => false,
+                    Err(_) => true,
+                }
+            }
+            _ => true,
+        }
+    }
+
+    /// Fail with [`Error::Instance`] if `jailer` has exited unsuccessfully.
+    ///
+    /// `jailer` failing to set up the jail exits before `firecracker` creates the API socket,
+    /// so this is also checked when connecting fails, to report the actual cause instead of
+    /// the connection error.
+    fn check_jailer_exit_status(&mut self) -> Result<()> {
+        if self.jailer_workspace_dir.is_none() {
+            return Ok(());
+        }
+        if self.jailer_exit_status.is_none() {
+            if let Some(Ok(Some(status))) = self.child.as_mut().map(|child| child.try_wait()) {
+                self.jailer_exit_status = Some(status);
+            }
+        }
+        match self.jailer_exit_status {
+            Some(status) if !status.success() => {
+                Err(Error::Instance(format!("jailer exited with {status}")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check whether `firecracker` has exited without blocking, returning the exit status of
+    /// the spawned process if so, or [`None`] if it is still running.
+    ///
+    /// When `jailer` forked `firecracker` (e.g. with `--new-pid-ns`) instead of exec'ing into it,
+    /// `firecracker` is not our child and its own exit status is unavailable: the exit status
+    /// of `jailer` is returned once `firecracker` is gone.
+    ///
+    /// Once the exit is observed, `firecracker` is no longer signaled when the instance is
+    /// dropped, since its PID may have been reused by then.
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        let Some(ref mut child) = self.child else {
+            return Err(Error::Instance("VMM not spawned".into()));
+        };
+        let status = match self.jailer_exit_status {
+            Some(status) => Some(status),
+            None => child.try_wait()?,
+        };
+        let status = match (self.jailer_pid, self.firecracker_pid) {
+            (Some(jailer_pid), Some(pid)) if jailer_pid != pid => {
+                self.jailer_exit_status = status;
+                if Path::new(&format!("/proc/{pid}")).exists() {
+                    None
+                } else {
+                    status
+                }
+            }
+            _ => status,
+        };
+        if status.is_some() {
+            self.fstack.cancel_terminate_process();
+        }
+        Ok(status)
+    }
+
+    /// Check whether the spawned `firecracker` process is still alive.
+    fn vmm_running(&mut self) -> Result<bool> {
+        let Some(ref mut child) = self.child else {
+            return Err(Error::Instance("VMM not spawned".into()));
+        };
+        // reap the child if it has exited
+        let child_exited = child.try_wait()?.is_some();
+        match (self.jailer_pid, self.firecracker_pid) {
+            // `firecracker` might not be our child if `jailer` daemonized it
+            (Some(_), Some(pid)) => Ok(Path::new(&format!("/proc/{pid}")).exists()),
+            _ => Ok(!child_exited),
+        }
+    }
+
+    /// Returns the hard link inside the jailer corresponding to `path`, or the copy when
+    /// spawned with [`ChrootStrategy::CopyStrategy`] which lays out files the same way as
+    /// [`ChrootStrategy::FullLinkStrategy`].
     /// # Example
     /// ```rust,ignore,no_run
     /// // This is the file that would be seen by other processes
@@ -140,14 +778,1209 @@ impl Instance {
             _ => Err(Error::Instance("Not using jailer".into())),
         }
     }
+
+    /// Returns the path on the host of `path` as seen by `firecracker`: `path` itself for
+    /// bare `firecracker`, or `path` under the jailer workspace directory when spawned with
+    /// `jailer` (whatever the [`ChrootStrategy`], since it's where `firecracker` is chrooted).
+    ///
+    /// This is the reverse of [`Instance::jailed_link`], e.g. to find a file created by
+    /// `firecracker` without branching on how it was spawned.
+    /// # Example
+    /// ```rust,ignore,no_run
+    /// // Bare `firecracker`
+    /// let instance_1: Instance;
+    /// assert_eq!(
+    ///     instance_1.resolve_host_path("/run/fc.log"),
+    ///     PathBuf::from("/run/fc.log")
+    /// );
+    ///
+    /// // Spawned with `jailer`, the jailer workspace directory being
+    /// // "/srv/jailer/firecracker/test-instance/root"
+    /// let instance_2: Instance;
+    /// assert_eq!(
+    ///     instance_2.resolve_host_path("/run/fc.log"),
+    ///     PathBuf::from("/srv/jailer/firecracker/test-instance/root/run/fc.log")
+    /// );
+    /// ```
+    pub fn resolve_host_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        match self.jailer_workspace_dir {
+            Some(ref jailer_workspace_dir) => {
+                jailer_workspace_dir.join(path.strip_prefix("/").unwrap_or(path))
+            }
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+/// Link `path_on_host` into the jail with [`ChrootStrategy::link_file`], scheduling the mount
+/// made by [`ChrootStrategy::BindMountStrategy`] to be unmounted when `fstack` is dropped.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+fn link_into_jail<P: AsRef<Path>>(
+    chroot_strategy: &ChrootStrategy,
+    jailer_workspace_dir: &Path,
+    fstack: &mut FStack,
+    path_on_host: P,
+) -> Result<PathBuf> {
+    let link = chroot_strategy.link_file(jailer_workspace_dir, path_on_host)?;
+    if matches!(chroot_strategy, ChrootStrategy::BindMountStrategy) {
+        fstack.push_action(FStackAction::Unmount(link.clone()));
+    }
+    Ok(link)
+}
+
+/// [`link_into_jail`] returning the path of the link inside the jail, i.e. the path to send
+/// to `firecracker`.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+fn link_into_chroot<P: AsRef<Path>>(
+    chroot_strategy: &ChrootStrategy,
+    jailer_workspace_dir: &Path,
+    fstack: &mut FStack,
+    path_on_host: P,
+) -> Result<PathBuf> {
+    let link = link_into_jail(chroot_strategy, jailer_workspace_dir, fstack, path_on_host)?;
+    link.strip_prefix(jailer_workspace_dir)
+        .map(Path::to_path_buf)
+        .map_err(|_| {
+            Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
+        })
+}
+
+/// `PATCH /drives/{drive_id}` request changing `is_read_only` of a started VM's drive,
+/// which [`crate::models::PartialDrive`] can't express.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+fn drive_readonly_request(drive_id: &str, read_only: bool) -> Result<Vec<u8>> {
+    crate::events::check_path_segment(drive_id)?;
+    let body = serde_json::json!({ "drive_id": drive_id, "is_read_only": read_only });
+    Ok(crate::events::encode_raw(
+        "PATCH",
+        &format!("/drives/{drive_id}"),
+        Some(body.to_string().as_bytes()),
+    ))
+}
+
+/// Decode the response to [`drive_readonly_request`], explaining a rejection by
+/// `firecracker`.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+fn drive_readonly_response(drive_id: &str, response: &Vec<u8>) -> Result<()> {
+    use crate::events::{PatchGuestDriveByID, ResponseTrait};
+
+    match PatchGuestDriveByID::decode(response) {
+        Ok(_) => Ok(()),
+        Err(Error::FirecrackerApiError {
+            status, message, ..
+        }) => Err(Error::Instance(format!(
+            "firecracker cannot change `is_read_only` of drive {drive_id} after VM start \
+             ({status} {message}), only `path_on_host` and `rate_limiter` can be patched"
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read the pid of `firecracker` from the pid file written by `jailer`, whose name may have
+/// been overridden by `JailerOption::pid_file_name`.
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+fn read_pid_file(pid_file: &Path) -> Result<u32> {
+    let content = fs::read_to_string(pid_file).map_err(|source| Error::ConfigurationIO {
+        context: format!("read pid file {}", pid_file.display()),
+        source,
+    })?;
+    content.trim().parse().map_err(|e| {
+        Error::Instance(format!(
+            "Bad pid file {}: {e}, got {content:?}",
+            pid_file.display()
+        ))
+    })
+}
+
+/// Read the `firecracker` JSON config file at `path` (the `--config-file` format).
+#[cfg(all(feature = "_rt-std", feature = "models"))]
+fn read_config_file(path: &Path) -> Result<VmConfigBuilder> {
+    parse_config_file(path, fs::read(path))
+}
+
+/// [`read_config_file`] with the file system of the async runtime.
+#[cfg(all(feature = "_rt-async", feature = "models"))]
+async fn read_config_file_async(path: &Path) -> Result<VmConfigBuilder> {
+    #[cfg(feature = "_rt-tokio")]
+    let content = tokio::fs::read(path).await;
+    #[cfg(feature = "_rt-async-std")]
+    let content = async_std::fs::read(path).await;
+    parse_config_file(path, content)
+}
+
+/// Decode the `content` read from the config file at `path`.
+#[cfg(all(any(feature = "_rt-std", feature = "_rt-async"), feature = "models"))]
+fn parse_config_file(path: &Path, content: std::io::Result<Vec<u8>>) -> Result<VmConfigBuilder> {
+    let content = content.map_err(|source| Error::ConfigurationIO {
+        context: format!("Fail to read config file {}", path.display()),
+        source,
+    })?;
+    let config: crate::models::FullVmConfiguration =
+        serde_json::from_slice(&content).map_err(|e| {
+            Error::Configuration(format!("Invalid config file {}: {e}", path.display()))
+        })?;
+    Ok(config.into())
 }
 
 #[macro_export]
 macro_rules! check_agent_exists {
     ($self:ident) => {{
         if $self.agent.is_none() {
-            return Err(Error::Instance("No agent spawned".into()));
+            return Err(Error::NotStarted);
         }
         $self.agent.as_mut().unwrap()
     }};
 }
+
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+#[cfg(test)]
+mod tests {
+    #[cfg(all(any(feature = "_rt-std", feature = "_rt-tokio"), feature = "models"))]
+    use std::{
+        io::{Read, Write},
+        os::unix::net::UnixListener,
+        sync::mpsc::{self, Receiver},
+        thread::JoinHandle,
+    };
+
+    use super::Instance;
+
+    /// Instance of bare `firecracker` with its API socket at `api_sock`, not spawned.
+    fn test_instance(api_sock: &str) -> Instance {
+        crate::firecracker::FirecrackerOption::new("sh")
+            .api_sock(api_sock)
+            .build()
+            .unwrap()
+    }
+
+    /// Serve a single connection on `api_sock`, answering every request with `204`, see
+    /// [`mock_api_server_with`].
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    fn mock_api_server(api_sock: &str) -> (Receiver<String>, JoinHandle<()>) {
+        mock_api_server_with(api_sock, 1, |_| "HTTP/1.1 204 \r\n\r\n".into())
+    }
+
+    /// Serve `connections` connections on `api_sock` one after the other, answering each
+    /// request with `respond(request)`. Every request is also sent to the returned receiver,
+    /// which is closed once the server is done.
+    #[cfg(all(any(feature = "_rt-std", feature = "_rt-tokio"), feature = "models"))]
+    fn mock_api_server_with<F>(
+        api_sock: &str,
+        connections: usize,
+        mut respond: F,
+    ) -> (Receiver<String>, JoinHandle<()>)
+    where
+        F: FnMut(&str) -> String + Send + 'static,
+    {
+        let _ = std::fs::remove_file(api_sock);
+        let listener = UnixListener::bind(api_sock).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let server_handle = std::thread::spawn(move || {
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0; 4096];
+                while let Ok(n @ 1..) = stream.read(&mut buf) {
+                    let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let response = respond(&request);
+                    let _ = tx.send(request);
+                    if stream.write_all(response.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        (rx, server_handle)
+    }
+
+    /// Request line of `request` without the HTTP version, e.g. `PUT /drives/rootfs`.
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    fn request_line(request: String) -> String {
+        request.split(" HTTP/").next().unwrap().to_string()
+    }
+
+    fn assert_send<T: Send>() {}
+
+    /// `Instance` is commonly stored behind `Arc<Mutex<_>>` and moved across
+    /// threads / tasks, so every runtime must keep it `Send`.
+    #[test]
+    fn instance_is_send() {
+        assert_send::<Instance>();
+    }
+
+    #[test]
+    fn into_child_keeps_process_running() {
+        use std::process::Command;
+
+        use crate::fstack::FStackAction;
+
+        let mut instance = test_instance("/tmp/firecracker-sdk-unit-test-into-child.socket");
+        let child = Command::new("sleep").arg("10").spawn().unwrap();
+        instance.fstack.push_action(FStackAction::TerminateProcess {
+            pid: child.id(),
+            grace: instance.shutdown_grace,
+        });
+        instance.child = Some(child);
+
+        let mut child = instance.into_child().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn configure_after_vm_start() {
+        use super::InstanceLifecycle;
+        use crate::models::Vsock;
+
+        let mut instance =
+            test_instance("/tmp/firecracker-sdk-unit-test-configure-after-vm-start.socket");
+        assert_eq!(instance.state(), InstanceLifecycle::Created);
+        instance.lifecycle = InstanceLifecycle::Started;
+        assert!(matches!(
+            instance.put_guest_vsock(&Vsock::new(3, "/tmp/v.sock")),
+            Err(crate::Error::Instance(e)) if e == "cannot configure vsock after VM start"
+        ));
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn configured_devices() {
+        use std::time::Duration;
+
+        use crate::{agent::SocketAgent, models::*};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-configured-devices.socket";
+        // accept every request, failing the ones for drive `bad`
+        let (_, server_handle) = mock_api_server_with(API_SOCK, 1, |request| {
+            if request.starts_with("PUT /drives/bad ") {
+                "HTTP/1.1 400 \r\nContent-Length: 23\r\n\r\n{\"fault_message\":\"bad\"}".into()
+            } else {
+                "HTTP/1.1 204 \r\n\r\n".into()
+            }
+        });
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+
+        instance
+            .put_guest_drive_by_id(&Drive::new("rootfs", "/foo/rootfs.ext4"))
+            .unwrap();
+        instance
+            .put_guest_drive_by_id(&Drive::new("data", "/foo/data.ext4"))
+            .unwrap();
+        assert!(instance
+            .put_guest_drive_by_id(&Drive::new("bad", "/foo/bad.ext4"))
+            .is_err());
+        let mut partial_drive = PartialDrive::new("data");
+        partial_drive.path_on_host = Some("/foo/data2.ext4".into());
+        instance.patch_guest_drive_by_id(&partial_drive).unwrap();
+        instance
+            .put_guest_network_interface_by_id(&NetworkInterface::new("eth0", "tap0").unwrap())
+            .unwrap();
+
+        assert_eq!(instance.drive_ids(), ["data", "rootfs"]);
+        assert_eq!(
+            instance.configured_drive("data").unwrap().path_on_host,
+            std::path::Path::new("/foo/data2.ext4")
+        );
+        assert!(instance.configured_drive("bad").is_none());
+        assert_eq!(instance.network_interface_ids(), ["eth0"]);
+        assert!(instance.configured_vsock().is_none());
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn restart_keeps_cleanup() {
+        use std::{fs, os::unix::net::UnixListener, path::Path, process::Command};
+
+        use crate::fstack::FStackAction;
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-restart.socket";
+        const FILE: &str = "/tmp/firecracker-sdk-unit-test-restart.file";
+        let _ = fs::remove_file(API_SOCK);
+        fs::write(FILE, "").unwrap();
+        // the socket managed externally, which the respawned process "listens" on
+        let _listener = UnixListener::bind(API_SOCK).unwrap();
+
+        let mut instance = test_instance(API_SOCK);
+        instance.keep_socket = Some(true);
+        let mut child = Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        instance.child = Some(child);
+        instance
+            .fstack
+            .push_action(FStackAction::RemoveFile(FILE.into()));
+        #[cfg(feature = "models")]
+        {
+            instance.track_dirty_pages = true;
+        }
+
+        instance.restart_vmm().unwrap();
+        assert!(Path::new(API_SOCK).exists());
+        assert!(Path::new(FILE).exists());
+        // the new VMM tracks no dirty pages until configured to
+        #[cfg(feature = "models")]
+        assert!(!instance.track_dirty_pages);
+
+        instance.wait().unwrap();
+        drop(instance);
+        assert!(Path::new(API_SOCK).exists());
+        assert!(!Path::new(FILE).exists());
+        let _ = fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn read_pid_file() {
+        use std::{fs, path::Path};
+
+        use crate::Error;
+
+        const PID_FILE: &str = "/tmp/firecracker-sdk-unit-test-read-pid-file.pid";
+        let _ = fs::remove_file(PID_FILE);
+        assert!(matches!(
+            super::read_pid_file(Path::new(PID_FILE)),
+            Err(Error::ConfigurationIO { .. })
+        ));
+
+        fs::write(PID_FILE, "not a pid").unwrap();
+        assert!(matches!(
+            super::read_pid_file(Path::new(PID_FILE)),
+            Err(Error::Instance(_))
+        ));
+
+        fs::write(PID_FILE, "1234\n").unwrap();
+        assert_eq!(super::read_pid_file(Path::new(PID_FILE)).unwrap(), 1234);
+        let _ = fs::remove_file(PID_FILE);
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn wait_for_exit() {
+        use std::process::Command;
+
+        use crate::fstack::FStackAction;
+
+        let mut instance = test_instance("/tmp/firecracker-sdk-unit-test-wait-for-exit.socket");
+        assert!(matches!(
+            instance.try_wait(),
+            Err(crate::Error::Instance(_))
+        ));
+
+        // the reaped process is no longer terminated when the instance is dropped
+        let spawn = |instance: &mut Instance| {
+            let child = Command::new("sh")
+                .args(["-c", "sleep 0.1; exit 3"])
+                .spawn()
+                .unwrap();
+            instance.fstack.push_action(FStackAction::TerminateProcess {
+                pid: child.id(),
+                grace: instance.shutdown_grace,
+            });
+            instance.child = Some(child);
+        };
+
+        spawn(&mut instance);
+        assert!(instance.try_wait().unwrap().is_none());
+        assert!(!instance.fstack.is_empty());
+        assert_eq!(instance.wait().unwrap().code(), Some(3));
+        assert!(instance.fstack.is_empty());
+        assert_eq!(instance.try_wait().unwrap().unwrap().code(), Some(3));
+
+        spawn(&mut instance);
+        while instance.try_wait().unwrap().is_none() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(instance.fstack.is_empty());
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn log_reader() {
+        use std::{io::BufRead, path::Path};
+
+        use crate::{firecracker::FirecrackerOption, jailer::JailerOption};
+
+        const LOG_PATH: &str = "/tmp/firecracker-sdk-unit-test-log-reader.log";
+        const CHROOT_BASE_DIR: &str = "/tmp/firecracker-sdk-unit-test-log-reader-jail";
+        std::fs::write(LOG_PATH, "boot error\n").unwrap();
+
+        let instance = FirecrackerOption::new("sh")
+            .api_sock("/tmp/firecracker-sdk-unit-test-log-reader.socket")
+            .log_path(Some(LOG_PATH))
+            .build()
+            .unwrap();
+        let lines = instance
+            .log_reader()
+            .unwrap()
+            .lines()
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, ["boot error"]);
+        let _ = std::fs::remove_file(LOG_PATH);
+
+        // `firecracker` spawned by `jailer` writes the log inside the jail
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+        let mut firecracker_option = FirecrackerOption::new("");
+        firecracker_option.log_path(Some("/fc.log"));
+        let instance = JailerOption::new("sh", "sh", "vm0", 0, 0)
+            .chroot_base_dir(Some(CHROOT_BASE_DIR))
+            .create_chroot_base()
+            .firecracker_option(Some(&firecracker_option))
+            .build()
+            .unwrap();
+        assert_eq!(
+            instance.log_path(),
+            Some(
+                Path::new(CHROOT_BASE_DIR)
+                    .join("sh/vm0/root/fc.log")
+                    .as_path()
+            )
+        );
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn apply_config() {
+        use std::time::Duration;
+
+        use crate::{agent::SocketAgent, instance::VmConfigBuilder, models::*};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-apply-config.socket";
+        let (rx, server_handle) = mock_api_server(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+
+        // added in another order than sent
+        let config = VmConfigBuilder::new()
+            .mmds_config(MmdsConfig::new(vec!["eth0".into()]))
+            .network_interface(NetworkInterface::new("eth0", "tap0").unwrap())
+            .drive(Drive::new("data", "/foo/data.ext4"))
+            .drive(Drive::new("rootfs", "/foo/rootfs.ext4"))
+            .boot_source(BootSource::new("/foo/vmlinux"))
+            .machine_config(MachineConfiguration::new(1, 256));
+        instance.apply_config(&config).unwrap();
+        assert_eq!(instance.drive_ids(), ["data", "rootfs"]);
+
+        drop(instance);
+        server_handle.join().unwrap();
+        assert_eq!(
+            rx.into_iter().map(request_line).collect::<Vec<_>>(),
+            [
+                "PUT /machine-config",
+                "PUT /boot-source",
+                "PUT /drives/data",
+                "PUT /drives/rootfs",
+                "PUT /network-interfaces/eth0",
+                "PUT /mmds/config",
+            ]
+        );
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn apply_config_file() {
+        use std::time::Duration;
+
+        use crate::{agent::SocketAgent, Error};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-apply-config-file.socket";
+        const CONFIG_FILE: &str = "/tmp/firecracker-sdk-unit-test-apply-config-file.json";
+        let (rx, server_handle) = mock_api_server(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+
+        std::fs::write(CONFIG_FILE, "{").unwrap();
+        assert!(matches!(
+            instance.apply_config_file(CONFIG_FILE),
+            Err(Error::Configuration(_))
+        ));
+        std::fs::write(
+            CONFIG_FILE,
+            r#"{
+                "boot-source": { "kernel_image_path": "/foo/vmlinux" },
+                "drives": [
+                    {
+                        "drive_id": "rootfs",
+                        "path_on_host": "/foo/rootfs.ext4",
+                        "is_root_device": true,
+                        "is_read_only": false
+                    }
+                ],
+                "machine-config": { "vcpu_count": 1, "mem_size_mib": 256 },
+                "metrics": { "metrics_path": "/foo/metrics.fifo" },
+                "vsock": { "guest_cid": 3, "uds_path": "/foo/v.sock" }
+            }"#,
+        )
+        .unwrap();
+        instance.apply_config_file(CONFIG_FILE).unwrap();
+        assert!(matches!(
+            instance.apply_config_file("/tmp/firecracker-sdk-unit-test-no-such-config.json"),
+            Err(Error::ConfigurationIO { .. })
+        ));
+
+        drop(instance);
+        server_handle.join().unwrap();
+        assert_eq!(
+            rx.into_iter().map(request_line).collect::<Vec<_>>(),
+            [
+                "PUT /metrics",
+                "PUT /machine-config",
+                "PUT /boot-source",
+                "PUT /drives/rootfs",
+                "PUT /vsock",
+            ]
+        );
+        let _ = std::fs::remove_file(API_SOCK);
+        let _ = std::fs::remove_file(CONFIG_FILE);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn describe_devices() {
+        use std::{path::Path, time::Duration};
+
+        use crate::agent::SocketAgent;
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-describe-devices.socket";
+        // answer every request with the exported VM config
+        let (_, server_handle) = mock_api_server_with(API_SOCK, 1, |_| {
+            let body = r#"{
+                "drives": [
+                    {
+                        "drive_id": "rootfs",
+                        "path_on_host": "/foo/rootfs.ext4",
+                        "is_root_device": true,
+                        "is_read_only": true
+                    }
+                ],
+                "network-interfaces": [{ "iface_id": "eth0", "host_dev_name": "tap0" }]
+            }"#;
+            format!(
+                "HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+        });
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+
+        let drive = instance.describe_drive("rootfs").unwrap().unwrap();
+        assert!(drive.is_read_only);
+        assert!(instance.describe_drive("data").unwrap().is_none());
+        let network_interface = instance
+            .describe_network_interface("eth0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(network_interface.host_dev_name, Path::new("tap0"));
+        assert!(instance
+            .describe_network_interface("eth1")
+            .unwrap()
+            .is_none());
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn restore_and_resume() {
+        use std::time::Duration;
+
+        use crate::{agent::SocketAgent, models::SnapshotLoadParams};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-restore-and-resume.socket";
+        // the requests of two instances
+        let (rx, server_handle) =
+            mock_api_server_with(API_SOCK, 2, |_| "HTTP/1.1 204 \r\n\r\n".into());
+
+        let mut params = SnapshotLoadParams::new("/foo/snapshot");
+        params.mem_file_path = Some("/foo/mem".into());
+        for resume_vm in [None, Some(true)] {
+            let mut instance = test_instance(API_SOCK);
+            instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+            params.resume_vm = resume_vm;
+            instance.restore_and_resume(&params).unwrap();
+        }
+
+        server_handle.join().unwrap();
+        assert_eq!(
+            rx.into_iter().map(request_line).collect::<Vec<_>>(),
+            ["PUT /snapshot/load", "PATCH /vm", "PUT /snapshot/load"]
+        );
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn track_dirty_pages() {
+        use std::time::Duration;
+
+        use crate::{agent::SocketAgent, models::MachineConfiguration};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-track-dirty-pages.socket";
+        let (_, server_handle) = mock_api_server(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+        assert!(!instance.track_dirty_pages);
+
+        let mut machine_configuration = MachineConfiguration::new(1, 256);
+        machine_configuration.track_dirty_pages = Some(true);
+        instance
+            .put_machine_configuration(&machine_configuration)
+            .unwrap();
+        assert!(instance.track_dirty_pages);
+        // patching other fields keeps tracking dirty pages
+        instance
+            .patch_machine_configuration(&MachineConfiguration::new(2, 256))
+            .unwrap();
+        assert!(instance.track_dirty_pages);
+        // a new configuration replaces the previous one
+        instance
+            .put_machine_configuration(&MachineConfiguration::new(1, 256))
+            .unwrap();
+        assert!(!instance.track_dirty_pages);
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn set_drive_readonly() {
+        use std::{path::Path, time::Duration};
+
+        use super::InstanceLifecycle;
+        use crate::{agent::SocketAgent, models::Drive, Error};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-set-drive-readonly.socket";
+        const FAULT: &str = r#"{"fault_message":"unknown field `is_read_only`"}"#;
+        // rejecting `is_read_only` in a patch, as `firecracker` does
+        let (rx, server_handle) = mock_api_server_with(API_SOCK, 1, |request| {
+            if request.starts_with("PATCH ") && request.contains("is_read_only") {
+                format!(
+                    "HTTP/1.1 400 \r\nContent-Length: {}\r\n\r\n{FAULT}",
+                    FAULT.len()
+                )
+            } else {
+                "HTTP/1.1 204 \r\n\r\n".into()
+            }
+        });
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+        instance
+            .put_guest_drive_by_id(&Drive::new("rootfs", "/foo/rootfs.ext4"))
+            .unwrap();
+        let _ = rx.recv().unwrap();
+
+        // before boot, put again
+        instance.set_drive_readonly("rootfs", true).unwrap();
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("PUT /drives/rootfs "));
+        assert!(request.contains(r#""is_read_only":true"#));
+        assert!(instance.configured_drive("rootfs").unwrap().is_read_only);
+
+        assert!(matches!(
+            instance.set_drive_readonly("data", true),
+            Err(Error::Configuration(_))
+        ));
+
+        // after boot, patched, and rejected by `firecracker`
+        instance.lifecycle = InstanceLifecycle::Started;
+        match instance.set_drive_readonly("rootfs", false) {
+            Err(Error::Instance(e)) => assert!(e.contains("unknown field `is_read_only`")),
+            other => panic!("unexpected {other:?}"),
+        }
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("PATCH /drives/rootfs "));
+        assert!(request.ends_with(r#"{"drive_id":"rootfs","is_read_only":false}"#));
+        assert!(instance.configured_drive("rootfs").unwrap().is_read_only);
+
+        // the path can be patched live
+        instance
+            .set_drive_path("rootfs", "/foo/bigger.ext4")
+            .unwrap();
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("PATCH /drives/rootfs "));
+        assert!(request.ends_with(r#"{"drive_id":"rootfs","path_on_host":"/foo/bigger.ext4"}"#));
+        assert_eq!(
+            instance.configured_drive("rootfs").unwrap().path_on_host,
+            Path::new("/foo/bigger.ext4")
+        );
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn enable_logging() {
+        use std::{path::Path, time::Duration};
+
+        use crate::{agent::SocketAgent, models::LogLevel};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-enable-logging.socket";
+        let (rx, server_handle) = mock_api_server(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+        instance
+            .enable_logging("/tmp/firecracker.log", LogLevel::Debug)
+            .unwrap();
+
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("PUT /logger "));
+        assert!(request.ends_with(r#"{"level":"Debug","log_path":"/tmp/firecracker.log"}"#));
+        assert_eq!(instance.log_path(), Some(Path::new("/tmp/firecracker.log")));
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn restore_from_files() {
+        use std::time::Duration;
+
+        use super::InstanceLifecycle;
+        use crate::agent::SocketAgent;
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-restore-from-files.socket";
+        let (rx, server_handle) = mock_api_server(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+        instance
+            .restore_from_files("/tmp/vm.snap", "/tmp/vm.mem", true)
+            .unwrap();
+
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("PUT /snapshot/load "));
+        assert!(request.ends_with(concat!(
+            r#"{"mem_backend":{"backend_type":"File","backend_path":"/tmp/vm.mem"},"#,
+            r#""resume_vm":true,"snapshot_path":"/tmp/vm.snap"}"#
+        )));
+        assert_eq!(instance.lifecycle, InstanceLifecycle::Started);
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn restore_from_files_jailed() {
+        use std::{path::PathBuf, time::Duration};
+
+        use crate::{agent::SocketAgent, jailer::ChrootStrategy};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-restore-from-files-jailed.socket";
+        const DIR: &str = "/tmp/firecracker-sdk-unit-test-restore-from-files-jailed";
+        let _ = std::fs::remove_dir_all(DIR);
+        std::fs::create_dir_all(format!("{DIR}/host")).unwrap();
+        std::fs::create_dir_all(format!("{DIR}/root")).unwrap();
+        std::fs::write(format!("{DIR}/host/vm.snap"), "snapshot").unwrap();
+        std::fs::write(format!("{DIR}/host/vm.mem"), "memory").unwrap();
+        let (rx, server_handle) = mock_api_server(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+        instance.chroot_strategy = Some(ChrootStrategy::FullLinkStrategy);
+        instance.jailer_workspace_dir = Some(PathBuf::from(format!("{DIR}/root")));
+        instance
+            .restore_from_files(
+                format!("{DIR}/host/vm.snap"),
+                format!("{DIR}/host/vm.mem"),
+                false,
+            )
+            .unwrap();
+
+        // both files are linked into the jail and sent relative to it
+        let jailed = &DIR[1..];
+        let request = rx.recv().unwrap();
+        assert!(request.starts_with("PUT /snapshot/load "));
+        assert!(request.ends_with(&format!(
+            concat!(
+                r#"{{"mem_backend":{{"backend_type":"File","backend_path":"{jailed}/host/vm.mem"}},"#,
+                r#""resume_vm":false,"snapshot_path":"{jailed}/host/vm.snap"}}"#
+            ),
+            jailed = jailed
+        )));
+        assert_eq!(
+            std::fs::read_to_string(format!("{DIR}/root/{jailed}/host/vm.mem")).unwrap(),
+            "memory"
+        );
+        assert_eq!(
+            std::fs::read_to_string(format!("{DIR}/root/{jailed}/host/vm.snap")).unwrap(),
+            "snapshot"
+        );
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+        let _ = std::fs::remove_dir_all(DIR);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn wait_until_ready() {
+        use std::time::Duration;
+
+        use crate::{agent::SocketAgent, clock::MockClock, Error};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-wait-until-ready.socket";
+        const BODY: &str = r#"{"app_name":"Firecracker","id":"anonymous-instance","state":"Not started","vmm_version":"1.10.1"}"#;
+        // failing the first 3 requests, then answering
+        let mut requests = 0;
+        let (_, server_handle) = mock_api_server_with(API_SOCK, 1, move |_| {
+            requests += 1;
+            if requests <= 3 {
+                "HTTP/1.1 503 \r\nContent-Length: 0\r\n\r\n".to_string()
+            } else {
+                format!(
+                    "HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n{BODY}",
+                    BODY.len()
+                )
+            }
+        });
+
+        let mut instance = test_instance(API_SOCK);
+        assert!(matches!(
+            instance.wait_until_ready(Duration::from_secs(1)),
+            Err(Error::NotStarted)
+        ));
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+
+        // times out after the 3rd failure
+        let clock = MockClock::new();
+        assert!(matches!(
+            instance.wait_until_ready_with_clock(Duration::from_millis(30), &clock),
+            Err(Error::Instance(_))
+        ));
+        assert_eq!(
+            clock.sleeps(),
+            [Duration::from_millis(10), Duration::from_millis(20)]
+        );
+
+        let clock = MockClock::new();
+        instance
+            .wait_until_ready_with_clock(Duration::from_secs(1), &clock)
+            .unwrap();
+        assert!(clock.sleeps().is_empty());
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    /// Serve `GET /balloon/statistics` on `api_sock`, with `actual_mib` increasing from 0.
+    #[cfg(all(any(feature = "_rt-std", feature = "_rt-tokio"), feature = "models"))]
+    fn serve_balloon_stats(api_sock: &str) -> JoinHandle<()> {
+        let mut actual_mib = 0;
+        let (_, server_handle) = mock_api_server_with(api_sock, 1, move |request| {
+            assert!(request.starts_with("GET /balloon/statistics "));
+            let body = format!(
+                r#"{{"target_pages":0,"actual_pages":0,"target_mib":0,"actual_mib":{actual_mib}}}"#
+            );
+            actual_mib += 1;
+            format!(
+                "HTTP/1.1 200 \r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+        });
+        server_handle
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn balloon_stats_stream() {
+        use std::time::{Duration, Instant};
+
+        use crate::agent::SocketAgent;
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-balloon-stats-stream.socket";
+        let server_handle = serve_balloon_stats(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(SocketAgent::new(API_SOCK, Duration::from_secs(3)).unwrap());
+
+        let start = Instant::now();
+        let actual_mib: Vec<_> = instance
+            .balloon_stats_stream(Duration::from_millis(20))
+            .take(3)
+            .map(|stats| stats.unwrap().actual_mib)
+            .collect();
+        assert_eq!(actual_mib, [0, 1, 2]);
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "_rt-tokio")]
+    #[tokio::test]
+    async fn close() {
+        use std::{
+            path::Path,
+            process::Command,
+            time::{Duration, Instant},
+        };
+
+        use crate::fstack::FStackAction;
+
+        const SOCKET: &str = "/tmp/firecracker-sdk-unit-test-close.socket";
+        let is_gone = |pid: u32| !Path::new(&format!("/proc/{pid}")).exists();
+
+        // exits on `SIGTERM`, or ignores it and is killed
+        for script in ["exec sleep 10", "trap '' TERM; exec sleep 10"] {
+            std::fs::write(SOCKET, "").unwrap();
+            let mut instance = test_instance(SOCKET);
+            let child = Command::new("sh").args(["-c", script]).spawn().unwrap();
+            let pid = child.id();
+            instance.child = Some(child);
+            instance.firecracker_pid = Some(pid);
+            instance.shutdown_grace = Duration::from_millis(100);
+            instance
+                .fstack
+                .push_action(FStackAction::RemoveFile(SOCKET.into()));
+            instance.fstack.push_action(FStackAction::TerminateProcess {
+                pid,
+                grace: Duration::from_secs(10),
+            });
+
+            instance.close().await.unwrap();
+            assert!(is_gone(pid));
+            assert!(!Path::new(SOCKET).exists());
+        }
+
+        // without a known `firecracker` pid the child is signaled, and with no guest asked
+        // to shut down it's signaled without waiting for the shutdown grace first
+        let mut instance = test_instance(SOCKET);
+        let child = Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = child.id();
+        instance.child = Some(child);
+        instance.shutdown_grace = Duration::from_secs(10);
+        let start = Instant::now();
+        instance.close().await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(is_gone(pid));
+    }
+
+    #[cfg(all(feature = "_rt-tokio", feature = "models"))]
+    #[tokio::test]
+    async fn balloon_stats_stream() {
+        use std::{
+            future::poll_fn,
+            pin::Pin,
+            time::{Duration, Instant},
+        };
+
+        use futures_core::Stream;
+
+        use crate::agent::SocketAgent;
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-balloon-stats-stream-tokio.socket";
+        let server_handle = serve_balloon_stats(API_SOCK);
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(
+            SocketAgent::new(API_SOCK, Duration::from_secs(3))
+                .await
+                .unwrap(),
+        );
+
+        let start = Instant::now();
+        let mut stream = instance.balloon_stats_stream(Duration::from_millis(20));
+        for expected in 0..3 {
+            let stats = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await;
+            assert_eq!(stats.unwrap().unwrap().actual_mib, expected);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        drop(stream);
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(all(feature = "_rt-tokio", feature = "models"))]
+    #[tokio::test]
+    async fn balloon_stats_stream_dropped() {
+        use std::{future::poll_fn, pin::Pin, task::Poll, time::Duration};
+
+        use futures_core::Stream;
+
+        use crate::{agent::SocketAgent, Error};
+
+        const API_SOCK: &str = "/tmp/firecracker-sdk-unit-test-balloon-stats-stream-dropped.socket";
+        // the request is never answered
+        let (rx, server_handle) = mock_api_server_with(API_SOCK, 1, |_| String::new());
+
+        let mut instance = test_instance(API_SOCK);
+        instance.agent = Some(
+            SocketAgent::new(API_SOCK, Duration::from_secs(3))
+                .await
+                .unwrap(),
+        );
+
+        // dropped while waiting for the response
+        let mut stream = instance.balloon_stats_stream(Duration::from_millis(20));
+        let polled = poll_fn(|cx| Poll::Ready(Pin::new(&mut stream).poll_next(cx))).await;
+        assert!(polled.is_pending());
+        drop(stream);
+        assert!(rx.recv().unwrap().starts_with("GET /balloon/statistics "));
+
+        // the next request would read the abandoned response
+        assert!(matches!(
+            instance.describe_balloon_stats().await,
+            Err(Error::Agent(e)) if e == "concurrent request on single connection"
+        ));
+
+        drop(instance);
+        server_handle.join().unwrap();
+        let _ = std::fs::remove_file(API_SOCK);
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn resolve_host_path() {
+        use std::path::Path;
+
+        use crate::jailer::JailerOption;
+
+        const CHROOT_BASE_DIR: &str = "/tmp/firecracker-sdk-unit-test-resolve-host-path-jail";
+
+        let instance = test_instance("/tmp/firecracker-sdk-unit-test-resolve-host-path.socket");
+        assert_eq!(
+            instance.resolve_host_path("/run/fc.log"),
+            Path::new("/run/fc.log")
+        );
+
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+        let instance = JailerOption::new("sh", "sh", "vm0", 0, 0)
+            .chroot_base_dir(Some(CHROOT_BASE_DIR))
+            .create_chroot_base()
+            .build()
+            .unwrap();
+        let jailer_workspace_dir = Path::new(CHROOT_BASE_DIR).join("sh/vm0/root");
+        assert_eq!(
+            instance.resolve_host_path("/run/fc.log"),
+            jailer_workspace_dir.join("run/fc.log")
+        );
+        assert_eq!(
+            instance.resolve_host_path("fc.log"),
+            jailer_workspace_dir.join("fc.log")
+        );
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn pid_file_name() {
+        use std::path::Path;
+
+        use crate::jailer::JailerOption;
+
+        const CHROOT_BASE_DIR: &str = "/tmp/firecracker-sdk-unit-test-pid-file-name-jail";
+
+        let instance = test_instance("/tmp/firecracker-sdk-unit-test-pid-file-name.socket");
+        assert_eq!(instance.pid_file_name, None);
+
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+        let instance = JailerOption::new("sh", "sh", "vm0", 0, 0)
+            .chroot_base_dir(Some(CHROOT_BASE_DIR))
+            .create_chroot_base()
+            .build()
+            .unwrap();
+        assert_eq!(instance.pid_file_name.as_deref(), Some(Path::new("sh.pid")));
+
+        let instance = JailerOption::new("sh", "sh", "vm0", 0, 0)
+            .chroot_base_dir(Some(CHROOT_BASE_DIR))
+            .create_chroot_base()
+            .pid_file_name("firecracker.pid")
+            .build()
+            .unwrap();
+        assert_eq!(
+            instance.pid_file_name.as_deref(),
+            Some(Path::new("firecracker.pid"))
+        );
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn skip_validation() {
+        use crate::models::MachineConfiguration;
+
+        let mut instance = test_instance("/tmp/firecracker-sdk-unit-test-skip-validation.socket");
+        let machine_configuration = MachineConfiguration::new(0, 256);
+        assert!(matches!(
+            instance.put_machine_configuration(&machine_configuration),
+            Err(crate::Error::Configuration(_))
+        ));
+
+        // sent as is, failing only because `firecracker` is not spawned
+        instance.skip_validation();
+        assert!(matches!(
+            instance.put_machine_configuration(&machine_configuration),
+            Err(crate::Error::NotStarted)
+        ));
+    }
+
+    #[cfg(all(feature = "_rt-std", feature = "models"))]
+    #[test]
+    fn mmds_payload_size() {
+        let mut instance = crate::firecracker::FirecrackerOption::new("sh")
+            .api_sock("/tmp/firecracker-sdk-unit-test-mmds-payload-size.socket")
+            .http_api_max_payload_size(64)
+            .build()
+            .unwrap();
+        assert_eq!(instance.http_api_max_payload_size(), 64);
+
+        let content = "x".repeat(64);
+        assert!(matches!(
+            instance.put_mmds(&content),
+            Err(crate::Error::Configuration(_))
+        ));
+        assert!(matches!(
+            instance.patch_mmds(&content),
+            Err(crate::Error::Configuration(_))
+        ));
+
+        // within the limit, failing only because `firecracker` is not spawned
+        let content = "x".repeat(32);
+        assert!(matches!(
+            instance.put_mmds(&content),
+            Err(crate::Error::NotStarted)
+        ));
+    }
+
+    #[cfg(feature = "_rt-std")]
+    #[test]
+    fn request_before_start_vmm() {
+        let mut instance =
+            test_instance("/tmp/firecracker-sdk-unit-test-request-before-start-vmm.socket");
+        assert!(matches!(
+            instance.raw_request("GET", "/version", None),
+            Err(crate::Error::NotStarted)
+        ));
+    }
+}
@@ -1,24 +1,53 @@
-use std::{fs, time::Duration};
+use std::{fs, process::ExitStatus, time::Duration};
+#[cfg(feature = "models")]
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use log::warn;
 
 use crate::{
     agent::SocketAgent,
     check_agent_exists,
-    events::*,
-    events::{EventTrait, ResponseTrait},
-    fstack::FStackAction,
-    models::*,
+    clock::{Clock, SystemClock},
+    events::{encode_raw, EventTrait, ResponseTrait},
+    fstack::{signal, FStackAction},
+    log_stream::{open_log_path, LogLines, LogStream, MetricsStream},
     Error, Result,
 };
+#[cfg(feature = "models")]
+use crate::{events::*, jailer::ChrootStrategy, models::*};
 
-use super::Instance;
+#[cfg(feature = "models")]
+use super::{
+    drive_readonly_request, drive_readonly_response, link_into_chroot, link_into_jail,
+    read_config_file_async, VmConfigBuilder, READY_POLL_INTERVAL, READY_POLL_MAX_INTERVAL,
+};
+use super::{
+    read_pid_file, Instance, InstanceLifecycle, EXIT_POLL_INTERVAL, JAILER_REAP_ATTEMPTS,
+    JAILER_REAP_INTERVAL,
+};
 
 impl Instance {
     /// Start corresponding process `firecracker` / `jailer`
     pub async fn start_vmm(&mut self) -> Result<()> {
+        // open the read end of the log path before `firecracker` opens it
+        if let Some(ref log_path) = self.log_path {
+            self.log_stream = Some(LogStream::new(open_log_path(log_path)?));
+        }
+        if let Some(ref metrics_path) = self.metrics_path {
+            self.metrics_stream = Some(MetricsStream::new(open_log_path(metrics_path)?));
+        }
+
         // spawn vmm process
         let child = self.command.spawn()?;
         let pid = child.id();
         self.child = Some(child);
+        self.spawn_console_pipe();
 
         // if we should remove jailer workspace directory after using / error
         // and there is a jailer workspace directory configuration (spawn by jailer)
@@ -26,41 +55,223 @@ impl Instance {
             (Some(true), Some(path)) => self
                 .fstack
                 .push_action(FStackAction::RemoveDirectory(path.clone())),
-            _ => (),
+            // keep it by default (`None`) or if explicitly asked to (`Some(false)`)
+            (None | Some(false), _) | (Some(true), None) => (),
         }
 
         // connect socket
         println!("start_vmm connecting to {}", self.socket_on_host.display());
-        let socket_agent = SocketAgent::new(&self.socket_on_host, Duration::from_secs(3)).await?;
+        let mut socket_agent =
+            match SocketAgent::new(&self.socket_on_host, Duration::from_secs(3)).await {
+                Ok(socket_agent) => socket_agent,
+                Err(e) => {
+                    self.check_jailer_exit_status()?;
+                    return Err(e);
+                }
+            };
+        socket_agent.set_read_buffer_size(self.read_buffer_size);
         self.agent = Some(socket_agent);
-        self.fstack
-            .push_action(FStackAction::RemoveFile(self.socket_on_host.clone()));
+        self.lifecycle = InstanceLifecycle::Configured;
+        if self.keep_socket != Some(true) {
+            self.fstack
+                .push_action(FStackAction::RemoveFile(self.socket_on_host.clone()));
+        }
 
         // get pids
-        if let Some(ref root) = self.jailer_workspace_dir {
+        if let (Some(root), Some(pid_file_name)) = (&self.jailer_workspace_dir, &self.pid_file_name)
+        {
             // using jailer
-            let pid_file = root.join(format!("{}.pid", self.exec_file_name.display()));
-            // unwrap safe (1): if there's not pid file, there would not be socket too, then method should have returned because of connection failure.
-            // unwrap safe (2): we should trust `jailer` that the pid file should be sound.
-            let firecracker_pid = fs::read_to_string(pid_file)
-                .unwrap()
-                .parse::<u32>()
-                .unwrap();
+            let pid_file = root.join(pid_file_name);
+            let firecracker_pid = read_pid_file(&pid_file)?;
             self.jailer_pid = Some(pid);
-            self.firecracker_pid = Some(firecracker_pid);
+            self.firecracker_pid = Some(self.resolve_firecracker_pid(pid, firecracker_pid));
         } else {
             // bare firecracker
             self.jailer_pid = None;
             self.firecracker_pid = Some(pid);
         }
         // unwrap safe: should be `Some(...)`
-        self.fstack.push_action(FStackAction::TerminateProcess(
-            self.firecracker_pid.unwrap(),
-        ));
+        self.fstack.push_action(FStackAction::TerminateProcess {
+            pid: self.firecracker_pid.unwrap(),
+            grace: self.shutdown_grace,
+        });
+
+        // `jailer` forking `firecracker` exits soon, reap it so it doesn't linger as a zombie
+        for _ in 0..JAILER_REAP_ATTEMPTS {
+            if self.try_reap_jailer() {
+                break;
+            }
+            SystemClock.sleep(JAILER_REAP_INTERVAL).await;
+        }
+        if !self.try_reap_jailer() {
+            // unwrap safe: only a `jailer` forking `firecracker` is left to reap
+            self.fstack
+                .push_action_bottom(FStackAction::ReapProcess(self.jailer_pid.unwrap()));
+        }
+        self.check_jailer_exit_status()?;
 
         Ok(())
     }
 
+    /// Respawn `firecracker` / `jailer` after the previous `firecracker` exited (e.g. crashed).
+    ///
+    /// The guest state is lost and the new `firecracker` boots from scratch, so configuration
+    /// must be re-applied (or restored from a snapshot) before starting the instance again.
+    ///
+    /// The stale API socket is removed unless kept with `keep_socket`, and the jailer
+    /// workspace directory only if it's removed when the instance is dropped too (see
+    /// [`Instance::remove_jailer_workspace_dir`]) and no mount point in it failed to be
+    /// unmounted. Otherwise clear them before restarting, since neither `firecracker` nor
+    /// `jailer` reuses them. The cleanup pending for the instance is kept, apart from
+    /// terminating the exited processes.
+    pub async fn restart_vmm(&mut self) -> Result<()> {
+        if self.vmm_running()? {
+            return Err(Error::Instance("VMM is still running".into()));
+        }
+        self.agent = None;
+        self.lifecycle = InstanceLifecycle::Created;
+        #[cfg(feature = "models")]
+        {
+            self.track_dirty_pages = false;
+        }
+        self.jailer_exit_status = None;
+        #[cfg(feature = "models")]
+        {
+            self.configured = Default::default();
+        }
+
+        // Do not terminate the stale processes since the old PID could have been reused,
+        // and unmount before the jail is removed below or `remove_dir_all` would descend
+        // into bind-mounted directories. `start_vmm` pushes the removal of the socket and
+        // the jail again.
+        let unmounted = self.fstack.unmount();
+        self.fstack.cancel_terminate_process();
+        let (socket_on_host, jailer_workspace_dir) =
+            (&self.socket_on_host, &self.jailer_workspace_dir);
+        self.fstack.retain(|action| match action {
+            FStackAction::RemoveFile(path) => path != socket_on_host,
+            FStackAction::RemoveDirectory(path) => Some(path) != jailer_workspace_dir.as_ref(),
+            _ => true,
+        });
+        if self.keep_socket != Some(true) {
+            let _ = fs::remove_file(&self.socket_on_host);
+        }
+        if let (Some(ref jailer_workspace_dir), Some(true)) =
+            (&self.jailer_workspace_dir, self.remove_jailer_workspace_dir)
+        {
+            if unmounted {
+                let _ = fs::remove_dir_all(jailer_workspace_dir);
+            } else {
+                warn!(
+                    "keep jailer workspace directory {} since a mount point in it failed to be unmounted",
+                    jailer_workspace_dir.display()
+                );
+            }
+        }
+
+        self.start_vmm().await
+    }
+
+    /// Wait until `firecracker` exits, e.g. to detect a microVM that crashed during boot.
+    /// The process is polled without blocking the runtime.
+    /// See [`Instance::try_wait`] for the exit status returned when spawned with `jailer`.
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            SystemClock.sleep(EXIT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Shut down `firecracker` and clean up without blocking the runtime, unlike dropping
+    /// the instance:
+    /// 1. a started guest is asked to shut down with Ctrl+Alt+Del (see [`Instance::stop`]);
+    /// 2. `firecracker` still running after the shutdown grace, or right away if the guest
+    ///    couldn't be asked to, is sent `SIGTERM`, then `SIGKILL` after another grace
+    ///    period, and reaped;
+    /// 3. the socket, the workspaces and the mounts are removed as dropping would, and
+    ///    nothing is left to clean up when the instance is dropped.
+    pub async fn close(mut self) -> Result<()> {
+        // the guest might have no keyboard controller to handle it (e.g. on aarch64)
+        #[cfg(feature = "models")]
+        let stopping = self.lifecycle == InstanceLifecycle::Started && self.stop().await.is_ok();
+        #[cfg(not(feature = "models"))]
+        let stopping = false;
+        if let Some(ref child) = self.child {
+            // the spawned process is `firecracker` itself unless `jailer` daemonized it
+            let pid = self.firecracker_pid.unwrap_or(child.id());
+            let grace = self.shutdown_grace;
+            if !(stopping && self.wait_timeout(grace).await?) {
+                signal(pid, libc::SIGTERM)?;
+                if !self.wait_timeout(grace).await? {
+                    signal(pid, libc::SIGKILL)?;
+                }
+                self.wait().await?;
+            }
+        }
+        self.agent = None;
+        self.fstack.cancel_terminate_process();
+        // perform the rest of the actions now
+        drop(self.fstack.take());
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for `firecracker` to exit, returning whether it did.
+    async fn wait_timeout(&mut self, timeout: Duration) -> Result<bool> {
+        let start = SystemClock.now();
+        loop {
+            if self.try_wait()?.is_some() {
+                return Ok(true);
+            }
+            if SystemClock.now().duration_since(start) >= timeout {
+                return Ok(false);
+            }
+            SystemClock.sleep(EXIT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Open the `firecracker` log at [`Instance::log_path`] as a
+    /// [`Stream`](futures_core::Stream) of its lines, e.g. to surface guest boot errors.
+    /// A regular log file is read from the beginning.
+    ///
+    /// Each line written to a FIFO log path is delivered to a single reader, so don't read it
+    /// along with the stream taken by [`Instance::take_log_stream`].
+    pub fn log_reader(&self) -> Result<LogLines> {
+        let Some(ref log_path) = self.log_on_host else {
+            return Err(Error::Configuration("log path not configured".into()));
+        };
+        Ok(LogStream::new(open_log_path(log_path)?).into_lines())
+    }
+
+    /// Wrapper around [`SocketAgent::event`].
+    /// Usually you should not invoke this method manully because other methods
+    /// have already covered whatever available manipulation of `firecracker` while
+    /// handling messy details for you such as hard link devices, files into the
+    /// jailer directory (if jailer is used)
+    pub async fn event<E: EventTrait>(
+        &mut self,
+        event: E,
+    ) -> Result<<E as ResponseTrait>::Payload> {
+        let agent = check_agent_exists!(self);
+        agent.event(event).await
+    }
+
+    /// Send a pre-serialized request to the API socket and return the raw HTTP response.
+    /// Useful when the typed models are disabled (without feature `models`).
+    pub async fn raw_request(
+        &mut self,
+        method: &str,
+        path: &str,
+        body: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let agent = check_agent_exists!(self);
+        agent.raw(&encode_raw(method, path, body)).await
+    }
+}
+
+#[cfg(feature = "models")]
+impl Instance {
     /// Utility method for starting the instance.
     /// Wrapper around [`Instance::create_sync_action`] with parameter [`ActionType::InstanceStart`].
     pub async fn start(&mut self) -> Result<()> {
@@ -90,24 +301,54 @@ impl Instance {
         Ok(())
     }
 
-    /// Utility method for stopping the instance.
-    /// Wrapper around [`Instance::create_sync_action`] with parameter [`ActionType::SendCtrlAtlDel`].
-    pub async fn stop(&mut self) -> Result<()> {
-        let _ = self.create_sync_action(ActionType::SendCtrlAtlDel).await?;
+    /// Pause the instance until the returned [`PauseGuard`] is resumed.
+    ///
+    /// `Drop` cannot be async, so the guard does NOT resume the instance when dropped:
+    /// always call [`PauseGuard::resume`], including on the error path, otherwise the
+    /// instance stays paused (a warning is logged).
+    pub async fn pause_scope(&mut self) -> Result<PauseGuard<'_>> {
+        self.pause().await?;
+        Ok(PauseGuard {
+            instance: self,
+            resumed: false,
+        })
+    }
+
+    /// Restore the VM from a snapshot and run it, i.e. [`Instance::load_snapshot`] followed by
+    /// [`Instance::resume`] unless `params.resume_vm` already asks `firecracker` to resume it.
+    pub async fn restore_and_resume(&mut self, params: &SnapshotLoadParams) -> Result<()> {
+        let _ = self.load_snapshot(params).await?;
+        if params.resume_vm != Some(true) {
+            self.resume().await?;
+        }
         Ok(())
     }
 
-    /// Wrapper around [`SocketAgent::event`].
-    /// Usually you should not invoke this method manully because other methods
-    /// have already covered whatever available manipulation of `firecracker` while
-    /// handling messy details for you such as hard link devices, files into the
-    /// jailer directory (if jailer is used)
-    pub async fn event<E: EventTrait>(
+    /// Restore the VM from the snapshot files written by [`Instance::create_snapshot`]: the
+    /// microVM state at `snapshot_path` and the guest memory file at `mem_path`, both linked
+    /// into the jail when spawned with `jailer`. The VM is resumed if `resume`, and left
+    /// paused otherwise.
+    pub async fn restore_from_files<P: AsRef<Path>, Q: AsRef<Path>>(
         &mut self,
-        event: E,
-    ) -> Result<<E as ResponseTrait>::Payload> {
-        let agent = check_agent_exists!(self);
-        agent.event(event).await
+        snapshot_path: P,
+        mem_path: Q,
+        resume: bool,
+    ) -> Result<()> {
+        let mut params = SnapshotLoadParams::new(snapshot_path.as_ref());
+        params.mem_backend = Some(MemoryBackend::new(BackendType::File, mem_path.as_ref()));
+        params.resume_vm = Some(resume);
+        if resume {
+            return self.restore_and_resume(&params).await;
+        }
+        let _ = self.load_snapshot(&params).await?;
+        Ok(())
+    }
+
+    /// Utility method for stopping the instance.
+    /// Wrapper around [`Instance::create_sync_action`] with parameter [`ActionType::SendCtrlAltDel`].
+    pub async fn stop(&mut self) -> Result<()> {
+        let _ = self.create_sync_action(ActionType::SendCtrlAltDel).await?;
+        Ok(())
     }
 
     /// operationId: describeInstance
@@ -116,12 +357,49 @@ impl Instance {
         agent.event(DescribeInstance(&Empty)).await
     }
 
+    /// Poll [`Instance::describe_instance`] until the API answers, backing off between attempts,
+    /// e.g. to check `firecracker` is responsive before configuring it.
+    /// Fails with [`Error::Instance`] holding the last error once `timeout` elapsed.
+    pub async fn wait_until_ready(&mut self, timeout: Duration) -> Result<()> {
+        self.wait_until_ready_with_clock(timeout, &SystemClock)
+            .await
+    }
+
+    /// [`Instance::wait_until_ready`] measuring `timeout` on `clock`.
+    pub(crate) async fn wait_until_ready_with_clock<C: Clock>(
+        &mut self,
+        timeout: Duration,
+        clock: &C,
+    ) -> Result<()> {
+        let start = clock.now();
+        let mut interval = READY_POLL_INTERVAL;
+        loop {
+            match self.describe_instance().await {
+                Ok(_) => return Ok(()),
+                Err(Error::NotStarted) => return Err(Error::NotStarted),
+                Err(e) if clock.now().duration_since(start) >= timeout => {
+                    return Err(Error::Instance(format!(
+                        "`firecracker` API not ready after {timeout:?}: {e}"
+                    )))
+                }
+                Err(_) => (),
+            }
+            clock.sleep(interval).await;
+            interval = (interval * 2).min(READY_POLL_MAX_INTERVAL);
+        }
+    }
+
     /// operationId: createSyncAction
     pub async fn create_sync_action(&mut self, action_type: ActionType) -> Result<Empty> {
         let agent = check_agent_exists!(self);
-        agent
+        let is_instance_start = action_type == ActionType::InstanceStart;
+        let response = agent
             .event(CreateSyncAction(&InstanceActionInfo { action_type }))
-            .await
+            .await?;
+        if is_instance_start {
+            self.lifecycle = InstanceLifecycle::Started;
+        }
+        Ok(response)
     }
 
     /// operationId: describeBalloonConfig
@@ -130,16 +408,43 @@ impl Instance {
         agent.event(DescribeBalloonConfig(&Empty)).await
     }
 
+    /// Whether `firecracker` expects the legacy `amount_mb` balloon field.
+    /// Detected from the version of `firecracker` once and cached.
+    async fn legacy_balloon_amount(&mut self) -> Result<bool> {
+        if let Some(legacy_balloon_amount) = self.legacy_balloon_amount {
+            return Ok(legacy_balloon_amount);
+        }
+        let legacy_balloon_amount = self
+            .get_firecracker_version()
+            .await?
+            .legacy_balloon_amount();
+        self.legacy_balloon_amount = Some(legacy_balloon_amount);
+        Ok(legacy_balloon_amount)
+    }
+
     /// operationId: putBalloon
     pub async fn put_balloon(&mut self, balloon: &Balloon) -> Result<Empty> {
+        self.check_pre_boot("configure the balloon device")?;
+        let legacy_balloon_amount = self.legacy_balloon_amount().await?;
         let agent = check_agent_exists!(self);
-        agent.event(PutBalloon(balloon)).await
+        if legacy_balloon_amount {
+            agent.event(PutLegacyBalloonOwned(balloon.into())).await
+        } else {
+            agent.event(PutBalloon(balloon)).await
+        }
     }
 
     /// operationId: patchBalloon
     pub async fn patch_balloon(&mut self, balloon_update: &BalloonUpdate) -> Result<Empty> {
+        let legacy_balloon_amount = self.legacy_balloon_amount().await?;
         let agent = check_agent_exists!(self);
-        agent.event(PatchBalloon(balloon_update)).await
+        if legacy_balloon_amount {
+            agent
+                .event(PatchLegacyBalloonOwned(balloon_update.into()))
+                .await
+        } else {
+            agent.event(PatchBalloon(balloon_update)).await
+        }
     }
 
     /// operationId: describeBalloonStats
@@ -148,6 +453,20 @@ impl Instance {
         agent.event(DescribeBalloonStats(&Empty)).await
     }
 
+    /// Poll the balloon statistics every `interval`, the first right away, e.g. to watch the
+    /// memory of the guest while inflating the balloon. Statistics must be enabled with
+    /// [`Balloon::stats_polling_interval_s`].
+    ///
+    /// The stream never ends: stop it with `take` / `take_while`, or break out of the loop.
+    pub fn balloon_stats_stream(&mut self, interval: Duration) -> BalloonStatsStream<'_> {
+        BalloonStatsStream {
+            instance: Some(self),
+            poll: None,
+            interval,
+            polled: false,
+        }
+    }
+
     /// operationId: patchBalloonStatsInterval
     pub async fn patch_balloon_stats_interval(
         &mut self,
@@ -161,29 +480,30 @@ impl Instance {
 
     /// operationId: putGuestBootSource
     pub async fn put_guest_boot_source(&mut self, boot_source: &BootSource) -> Result<Empty> {
+        self.check_pre_boot("configure the boot source")?;
+        self.validate_model(boot_source)?;
         let agent = check_agent_exists!(self);
 
         match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
                 // link the file
                 let chroot_initrd_path = if let Some(ref path) = boot_source.initrd_path {
-                    Some(chroot_strategy.link_file(jailer_workspace_dir, path)?
-                    .strip_prefix(jailer_workspace_dir)
-                    .and_then(|x| Ok(x.to_path_buf()))
-                    .map_err(|_| {
-                        Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                    })?)
+                    Some(link_into_chroot(
+                        chroot_strategy,
+                        jailer_workspace_dir,
+                        &mut self.fstack,
+                        path,
+                    )?)
                 } else {
                     None
                 };
 
-                let chroot_kernel_image_path = chroot_strategy
-                    .link_file(jailer_workspace_dir, &boot_source.kernel_image_path)?
-                    .strip_prefix(jailer_workspace_dir)
-                    .and_then(|x| Ok(x.to_path_buf()))
-                    .map_err(|_| {
-                        Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                    })?;
+                let chroot_kernel_image_path = link_into_chroot(
+                    chroot_strategy,
+                    jailer_workspace_dir,
+                    &mut self.fstack,
+                    &boot_source.kernel_image_path,
+                )?;
 
                 let boot_source = &BootSource {
                     boot_args: boot_source.boot_args.clone(),
@@ -199,23 +519,25 @@ impl Instance {
 
     /// operationId: putCpuConfiguration
     pub async fn put_cpu_configuration(&mut self, cpu_config: &CPUConfig) -> Result<Empty> {
+        self.check_pre_boot("configure the CPU template")?;
         let agent = check_agent_exists!(self);
         agent.event(PutCpuConfiguration(cpu_config)).await
     }
 
     /// operationId: putGuestDriveByID
     pub async fn put_guest_drive_by_id(&mut self, drive: &Drive) -> Result<Empty> {
+        self.check_pre_boot("configure a new drive")?;
+        self.validate_model(drive)?;
         let agent = check_agent_exists!(self);
 
-        match (&self.chroot_strategy, &self.jailer_workspace_dir) {
+        let response = match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
-                let chroot_drive_path = chroot_strategy
-                    .link_file(jailer_workspace_dir, &drive.path_on_host)?
-                    .strip_prefix(jailer_workspace_dir)
-                    .and_then(|x| Ok(x.to_path_buf()))
-                    .map_err(|_| {
-                        Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                    })?;
+                let chroot_drive_path = link_into_chroot(
+                    chroot_strategy,
+                    jailer_workspace_dir,
+                    &mut self.fstack,
+                    &drive.path_on_host,
+                )?;
 
                 let drive = Drive {
                     path_on_host: chroot_drive_path,
@@ -225,23 +547,26 @@ impl Instance {
                 agent.event(PutGuestDriveByID(&drive)).await
             }
             _ => agent.event(PutGuestDriveByID(drive)).await,
-        }
+        }?;
+        self.configured
+            .drives
+            .insert(drive.drive_id.clone(), drive.clone());
+        Ok(response)
     }
 
     /// operationId: patchGuestDriveByID
     pub async fn patch_guest_drive_by_id(&mut self, partial_drive: &PartialDrive) -> Result<Empty> {
         let agent = check_agent_exists!(self);
 
-        match (&self.chroot_strategy, &self.jailer_workspace_dir) {
+        let response = match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
                 if let Some(ref path) = partial_drive.path_on_host {
-                    let chroot_drive_path = chroot_strategy
-                        .link_file(jailer_workspace_dir, path)?
-                        .strip_prefix(jailer_workspace_dir)
-                        .and_then(|x| Ok(x.to_path_buf()))
-                        .map_err(|_| {
-                            Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                        })?;
+                    let chroot_drive_path = link_into_chroot(
+                        chroot_strategy,
+                        jailer_workspace_dir,
+                        &mut self.fstack,
+                        path,
+                    )?;
                     let partial_drive = PartialDrive {
                         path_on_host: Some(chroot_drive_path),
                         ..partial_drive.clone()
@@ -252,32 +577,130 @@ impl Instance {
                 }
             }
             _ => agent.event(PatchGuestDriveByID(partial_drive)).await,
+        }?;
+        self.configured.patch_drive(partial_drive);
+        Ok(response)
+    }
+
+    /// Make the drive `drive_id` read-only or read-write.
+    ///
+    /// Before boot, the drive is put again as configured through this instance (see
+    /// [`Instance::configured_drive`]) with `is_read_only` changed.
+    ///
+    /// Once started, it's patched instead (`PATCH /drives`). `firecracker` only patches
+    /// `path_on_host` (see [`Instance::set_drive_path`]) and `rate_limiter` of a live drive,
+    /// so unless a later version allows it, this fails with [`Error::Instance`] explaining
+    /// the rejection.
+    pub async fn set_drive_readonly(&mut self, drive_id: &str, read_only: bool) -> Result<()> {
+        if self.lifecycle == InstanceLifecycle::Started {
+            let request = drive_readonly_request(drive_id, read_only)?;
+            let agent = check_agent_exists!(self);
+            let response = agent.raw(&request).await?;
+            drive_readonly_response(drive_id, &response)?;
+            if let Some(drive) = self.configured.drives.get_mut(drive_id) {
+                drive.is_read_only = read_only;
+            }
+            return Ok(());
         }
+        let Some(drive) = self.configured.drives.get(drive_id) else {
+            return Err(Error::Configuration(format!(
+                "drive {drive_id} not configured through this instance"
+            )));
+        };
+        let drive = Drive {
+            is_read_only: read_only,
+            ..drive.clone()
+        };
+        self.put_guest_drive_by_id(&drive).await?;
+        Ok(())
+    }
+
+    /// Swap the backing file of the drive `drive_id` for `path_on_host`, before or after
+    /// boot: `firecracker` rescans the device and notifies the guest of its new size.
+    /// Wrapper around [`Instance::patch_guest_drive_by_id`].
+    pub async fn set_drive_path<P: AsRef<Path>>(
+        &mut self,
+        drive_id: &str,
+        path_on_host: P,
+    ) -> Result<()> {
+        let mut partial_drive = PartialDrive::new(drive_id);
+        partial_drive.path_on_host = Some(path_on_host.as_ref().to_path_buf());
+        let _ = self.patch_guest_drive_by_id(&partial_drive).await?;
+        Ok(())
     }
 
     /// operationId: putLogger
     pub async fn put_logger(&mut self, logger: &Logger) -> Result<Empty> {
+        self.check_pre_boot("configure the logger")?;
         let agent = check_agent_exists!(self);
 
-        match (&self.chroot_strategy, &self.jailer_workspace_dir) {
+        let response = match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
-                let chroot_log_path = chroot_strategy
-                    .link_file(jailer_workspace_dir, &logger.log_path)?
-                    .strip_prefix(jailer_workspace_dir)
-                    .and_then(|x| Ok(x.to_path_buf()))
-                    .map_err(|_| {
-                        Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                    })?;
+                let chroot_log_path = link_into_chroot(
+                    chroot_strategy,
+                    jailer_workspace_dir,
+                    &mut self.fstack,
+                    &logger.log_path,
+                )?;
 
                 let logger = Logger {
                     log_path: chroot_log_path,
                     ..logger.clone()
                 };
 
-                agent.event(PutLogger(&logger)).await
+                agent.event(PutLogger(&logger)).await?
             }
-            _ => agent.event(PutLogger(logger)).await,
-        }
+            _ => agent.event(PutLogger(logger)).await?,
+        };
+        self.log_on_host = Some(logger.log_path.clone());
+        Ok(response)
+    }
+
+    /// Utility method for configuring the logger to write to `log_path` at `level`,
+    /// with `firecracker` defaults for the other settings. With `jailer`, the path is linked
+    /// into the jail as by [`Instance::put_logger`].
+    pub async fn enable_logging<P: AsRef<Path>>(
+        &mut self,
+        log_path: P,
+        level: LogLevel,
+    ) -> Result<()> {
+        self.put_logger(&Logger::new(log_path.as_ref()).level(level))
+            .await?;
+        Ok(())
+    }
+
+    /// Utility method for configuring the logger and streaming its output.
+    /// FIFO and regular file `log_path` are told apart automatically. The read end of
+    /// `log_path` is opened before `firecracker` opens it, so a FIFO never blocks either side.
+    pub async fn configure_and_stream_logs<P: AsRef<Path>>(
+        &mut self,
+        log_path: P,
+        level: LogLevel,
+    ) -> Result<LogStream> {
+        let file = open_log_path(&log_path)?;
+        self.put_logger(&Logger {
+            level: Some(level),
+            log_path: log_path.as_ref().into(),
+            show_level: None,
+            show_log_origin: None,
+            module: None,
+        })
+        .await?;
+        Ok(LogStream::new(file))
+    }
+
+    /// Utility method for configuring the metrics and streaming its output.
+    /// FIFO and regular file `metrics_path` are told apart automatically. The read end of
+    /// `metrics_path` is opened before `firecracker` opens it, so a FIFO never blocks either side.
+    /// With `jailer`, the path is linked into the jail and `firecracker` writes to the same file.
+    pub async fn configure_and_stream_metrics<P: AsRef<Path>>(
+        &mut self,
+        metrics_path: P,
+    ) -> Result<MetricsStream> {
+        let file = open_log_path(&metrics_path)?;
+        self.put_metrics(&Metrics::new(metrics_path.as_ref()))
+            .await?;
+        Ok(MetricsStream::new(file))
     }
 
     /// operationId: getMachineConfiguration
@@ -291,10 +714,14 @@ impl Instance {
         &mut self,
         machine_configuration: &MachineConfiguration,
     ) -> Result<Empty> {
+        self.check_pre_boot("configure the machine")?;
+        self.validate_model(machine_configuration)?;
         let agent = check_agent_exists!(self);
-        agent
+        let response = agent
             .event(PutMachineConfiguration(&machine_configuration))
-            .await
+            .await?;
+        self.track_dirty_pages = machine_configuration.track_dirty_pages == Some(true);
+        Ok(response)
     }
 
     /// operationId: patchMachineConfiguration
@@ -302,25 +729,30 @@ impl Instance {
         &mut self,
         machine_configuration: &MachineConfiguration,
     ) -> Result<Empty> {
+        self.check_pre_boot("configure the machine")?;
         let agent = check_agent_exists!(self);
-        agent
+        let response = agent
             .event(PatchMachineConfiguration(machine_configuration))
-            .await
+            .await?;
+        if let Some(track_dirty_pages) = machine_configuration.track_dirty_pages {
+            self.track_dirty_pages = track_dirty_pages;
+        }
+        Ok(response)
     }
 
     /// operationId: putMetrics
     pub async fn put_metrics(&mut self, metrics: &Metrics) -> Result<Empty> {
+        self.check_pre_boot("configure the metrics")?;
         let agent = check_agent_exists!(self);
 
         match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
-                let chroot_metrics_path = chroot_strategy
-                    .link_file(jailer_workspace_dir, &metrics.metrics_path)?
-                    .strip_prefix(jailer_workspace_dir)
-                    .and_then(|x| Ok(x.to_path_buf()))
-                    .map_err(|_| {
-                        Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                    })?;
+                let chroot_metrics_path = link_into_chroot(
+                    chroot_strategy,
+                    jailer_workspace_dir,
+                    &mut self.fstack,
+                    &metrics.metrics_path,
+                )?;
 
                 let metrics = Metrics {
                     metrics_path: chroot_metrics_path,
@@ -334,12 +766,14 @@ impl Instance {
 
     /// operationId: putMmds
     pub async fn put_mmds(&mut self, content: &MmdsContentsObject) -> Result<Empty> {
+        self.check_payload_size("put the MMDS contents", content)?;
         let agent = check_agent_exists!(self);
         agent.event(PutMmds(content)).await
     }
 
     /// operationId: patchMmds
     pub async fn patch_mmds(&mut self, content: &MmdsContentsObject) -> Result<Empty> {
+        self.check_payload_size("patch the MMDS contents", content)?;
         let agent = check_agent_exists!(self);
         agent.event(PatchMmds(content)).await
     }
@@ -350,14 +784,45 @@ impl Instance {
         agent.event(GetMmds(&Empty)).await
     }
 
+    /// Utility method for waiting until the MMDS contents satisfy `pred`.
+    /// Repeatedly invokes [`Instance::get_mmds`] every `interval` and returns the contents
+    /// that satisfied `pred`, or an error if `timeout` elapsed before that.
+    /// Useful when the guest signals its readiness through MMDS.
+    pub async fn poll_mmds_until<F: Fn(&MmdsContentsObject) -> bool>(
+        &mut self,
+        pred: F,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<MmdsContentsObject> {
+        let start = SystemClock.now();
+        loop {
+            let content = self.get_mmds().await?;
+            if pred(&content) {
+                return Ok(content);
+            }
+            if SystemClock.now().duration_since(start) >= timeout {
+                return Err(Error::Timeout {
+                    operation: "Polling MMDS".into(),
+                    after: timeout,
+                });
+            }
+            SystemClock.sleep(interval).await;
+        }
+    }
+
     /// operationId: putMmdsConfig
     pub async fn put_mmds_config(&mut self, mmds_config: &MmdsConfig) -> Result<Empty> {
+        self.check_pre_boot("configure MMDS")?;
+        self.validate_model(mmds_config)?;
+        self.check_payload_size("configure MMDS", mmds_config)?;
         let agent = check_agent_exists!(self);
         agent.event(PutMmdsConfig(mmds_config)).await
     }
 
     /// operationId: putEntropyDevice
     pub async fn put_entropy_device(&mut self, entropy_device: &EntropyDevice) -> Result<Empty> {
+        self.check_pre_boot("configure the entropy device")?;
+        self.validate_model(entropy_device)?;
         let agent = check_agent_exists!(self);
         agent.event(PutEntropyDevice(entropy_device)).await
     }
@@ -367,10 +832,17 @@ impl Instance {
         &mut self,
         network_interface: &NetworkInterface,
     ) -> Result<Empty> {
+        self.check_pre_boot("configure a new network interface")?;
+        self.validate_model(network_interface)?;
         let agent = check_agent_exists!(self);
-        agent
+        let response = agent
             .event(PutGuestNetworkInterfaceByID(network_interface))
-            .await
+            .await?;
+        self.configured.network_interfaces.insert(
+            network_interface.iface_id.clone(),
+            network_interface.clone(),
+        );
+        Ok(response)
     }
 
     /// operationId: patchGuestNetworkInterfaceByID
@@ -379,9 +851,12 @@ impl Instance {
         partial_network_interface: &PartialNetworkInterface,
     ) -> Result<Empty> {
         let agent = check_agent_exists!(self);
-        agent
+        let response = agent
             .event(PatchGuestNetworkInterfaceByID(partial_network_interface))
-            .await
+            .await?;
+        self.configured
+            .patch_network_interface(partial_network_interface);
+        Ok(response)
     }
 
     /// operationId: createSnapshot
@@ -389,6 +864,7 @@ impl Instance {
         &mut self,
         snapshot_create_params: &SnapshotCreateParams,
     ) -> Result<Empty> {
+        self.check_diff_snapshot(snapshot_create_params);
         let agent = check_agent_exists!(self);
 
         match (&self.chroot_strategy, &self.jailer_workspace_dir) {
@@ -405,14 +881,41 @@ impl Instance {
                     ..snapshot_create_params.clone()
                 };
 
+                let bind_mount = matches!(chroot_strategy, ChrootStrategy::BindMountStrategy);
+                if bind_mount {
+                    // let `firecracker` write through bind mounts of the files on the host
+                    for path in [
+                        &snapshot_create_params.mem_file_path,
+                        &snapshot_create_params.snapshot_path,
+                    ] {
+                        fs::OpenOptions::new()
+                            .create(true)
+                            .truncate(false)
+                            .write(true)
+                            .open(path)?;
+                        link_into_jail(
+                            chroot_strategy,
+                            jailer_workspace_dir,
+                            &mut self.fstack,
+                            path,
+                        )?;
+                    }
+                }
+
                 let res = agent
                     .event(CreateSnapshot(&chroot_snapshot_create_params))
                     .await;
 
-                chroot_strategy
-                    .perform_link(chroot_mem_file_path, &snapshot_create_params.mem_file_path)?;
-                chroot_strategy
-                    .perform_link(chroot_snapshot_path, &snapshot_create_params.snapshot_path)?;
+                if !bind_mount {
+                    chroot_strategy.perform_link(
+                        chroot_mem_file_path,
+                        &snapshot_create_params.mem_file_path,
+                    )?;
+                    chroot_strategy.perform_link(
+                        chroot_snapshot_path,
+                        &snapshot_create_params.snapshot_path,
+                    )?;
+                }
 
                 res
             }
@@ -425,35 +928,51 @@ impl Instance {
         &mut self,
         snapshot_load_params: &SnapshotLoadParams,
     ) -> Result<Empty> {
+        self.check_pre_boot("load a snapshot")?;
         let agent = check_agent_exists!(self);
 
-        match (&self.chroot_strategy, &self.jailer_workspace_dir) {
+        let response = match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
-                let chroot_mem_file_path = if let Some(ref path) =
-                    snapshot_load_params.mem_file_path
-                {
-                    let x = chroot_strategy
-                    .link_file(jailer_workspace_dir, path)?
-                    .strip_prefix(jailer_workspace_dir)
-                    .and_then(|x| Ok(x.to_path_buf()))
-                    .map_err(|_| {
-                        Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                    })?;
-                    Some(x)
-                } else {
-                    None
+                let chroot_mem_file_path =
+                    if let Some(ref path) = snapshot_load_params.mem_file_path {
+                        let x = link_into_chroot(
+                            chroot_strategy,
+                            jailer_workspace_dir,
+                            &mut self.fstack,
+                            path,
+                        )?;
+                        Some(x)
+                    } else {
+                        None
+                    };
+
+                // a `File` backend is the memory file, read like `mem_file_path`
+                let chroot_mem_backend = match snapshot_load_params.mem_backend {
+                    Some(ref backend) if backend.backend_type == BackendType::File => {
+                        let x = link_into_chroot(
+                            chroot_strategy,
+                            jailer_workspace_dir,
+                            &mut self.fstack,
+                            &backend.backend_path,
+                        )?;
+                        Some(MemoryBackend {
+                            backend_path: x,
+                            ..backend.clone()
+                        })
+                    }
+                    ref backend => backend.clone(),
                 };
 
-                let chroot_snapshot_path = chroot_strategy
-                .link_file(jailer_workspace_dir, &snapshot_load_params.snapshot_path)?
-                .strip_prefix(jailer_workspace_dir)
-                .and_then(|x| Ok(x.to_path_buf()))
-                .map_err(|_| {
-                    Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                })?;
+                let chroot_snapshot_path = link_into_chroot(
+                    chroot_strategy,
+                    jailer_workspace_dir,
+                    &mut self.fstack,
+                    &snapshot_load_params.snapshot_path,
+                )?;
 
                 let snapshot_load_params = SnapshotLoadParams {
                     mem_file_path: chroot_mem_file_path,
+                    mem_backend: chroot_mem_backend,
                     snapshot_path: chroot_snapshot_path,
                     ..snapshot_load_params.clone()
                 };
@@ -461,7 +980,11 @@ impl Instance {
                 agent.event(LoadSnapshot(&snapshot_load_params)).await
             }
             _ => agent.event(LoadSnapshot(snapshot_load_params)).await,
-        }
+        }?;
+        // a loaded snapshot is a booted VM to `firecracker`
+        self.lifecycle = InstanceLifecycle::Started;
+        self.track_dirty_pages = snapshot_load_params.enable_diff_snapshots == Some(true);
+        Ok(response)
     }
 
     /// operationId: getFirecrackerVersion
@@ -482,19 +1005,45 @@ impl Instance {
         agent.event(GetExportVmConfig(&Empty)).await
     }
 
+    /// Returns the drive `drive_id` as accepted by `firecracker`, read back through
+    /// [`Instance::get_export_vm_config`], or [`None`] if there is no such drive.
+    pub async fn describe_drive(&mut self, drive_id: &str) -> Result<Option<Drive>> {
+        let config = self.get_export_vm_config().await?;
+        Ok(config
+            .drives
+            .unwrap_or_default()
+            .into_iter()
+            .find(|drive| drive.drive_id == drive_id))
+    }
+
+    /// Returns the network interface `iface_id` as accepted by `firecracker`, read back through
+    /// [`Instance::get_export_vm_config`], or [`None`] if there is no such interface.
+    pub async fn describe_network_interface(
+        &mut self,
+        iface_id: &str,
+    ) -> Result<Option<NetworkInterface>> {
+        let config = self.get_export_vm_config().await?;
+        Ok(config
+            .network_interfaces
+            .unwrap_or_default()
+            .into_iter()
+            .find(|network_interface| network_interface.iface_id == iface_id))
+    }
+
     /// operationId: putGuestVsock
     pub async fn put_guest_vsock(&mut self, vsock: &Vsock) -> Result<Empty> {
+        self.check_pre_boot("configure vsock")?;
+        self.validate_model(vsock)?;
         let agent = check_agent_exists!(self);
 
-        match (&self.chroot_strategy, &self.jailer_workspace_dir) {
+        let response = match (&self.chroot_strategy, &self.jailer_workspace_dir) {
             (Some(chroot_strategy), Some(jailer_workspace_dir)) => {
-                let chroot_uds_path = chroot_strategy
-                .link_file(jailer_workspace_dir, &vsock.uds_path)?
-                .strip_prefix(jailer_workspace_dir)
-                .and_then(|x| Ok(x.to_path_buf()))
-                .map_err(|_| {
-                    Error::Instance("Fail to strip prefix `jailer_workspace_dir`, the chroot strategy should always link the file under `jailer_workspace_dir`!".into())
-                })?;
+                let chroot_uds_path = link_into_chroot(
+                    chroot_strategy,
+                    jailer_workspace_dir,
+                    &mut self.fstack,
+                    &vsock.uds_path,
+                )?;
 
                 let vsock = Vsock {
                     uds_path: chroot_uds_path,
@@ -504,6 +1053,149 @@ impl Instance {
                 agent.event(PutGuestVsock(&vsock)).await
             }
             _ => agent.event(PutGuestVsock(vsock)).await,
+        }?;
+        self.configured.vsock = Some(vsock.clone());
+        Ok(response)
+    }
+
+    /// Send the pre-boot configuration collected in `config`, in the order documented by
+    /// [`VmConfigBuilder`], stopping at the first failure.
+    pub async fn apply_config(&mut self, config: &VmConfigBuilder) -> Result<()> {
+        if let Some(ref logger) = config.logger {
+            self.put_logger(logger).await?;
+        }
+        if let Some(ref metrics) = config.metrics {
+            self.put_metrics(metrics).await?;
+        }
+        if let Some(ref machine_config) = config.machine_config {
+            self.put_machine_configuration(machine_config).await?;
+        }
+        if let Some(ref boot_source) = config.boot_source {
+            self.put_guest_boot_source(boot_source).await?;
+        }
+        for drive in &config.drives {
+            self.put_guest_drive_by_id(drive).await?;
+        }
+        for network_interface in &config.network_interfaces {
+            self.put_guest_network_interface_by_id(network_interface)
+                .await?;
+        }
+        if let Some(ref mmds_config) = config.mmds_config {
+            self.put_mmds_config(mmds_config).await?;
+        }
+        if let Some(ref vsock) = config.vsock {
+            self.put_guest_vsock(vsock).await?;
+        }
+        if let Some(ref balloon) = config.balloon {
+            self.put_balloon(balloon).await?;
+        }
+        if let Some(ref entropy) = config.entropy {
+            self.put_entropy_device(entropy).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply the `firecracker` JSON config file at `path`, i.e. the file `firecracker` would
+    /// take with `--config-file`, through the API as [`Instance::apply_config`] does.
+    /// Paths in the file are paths on the host: when spawned with `jailer`, they are linked
+    /// into the jail like those given to the `put_*` methods.
+    pub async fn apply_config_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let config = read_config_file_async(path.as_ref()).await?;
+        self.apply_config(&config).await
+    }
+}
+
+/// Request of the balloon statistics in flight, handing the instance back along with them.
+#[cfg(feature = "models")]
+type BalloonStatsPoll<'a> =
+    Pin<Box<dyn Future<Output = (&'a mut Instance, Result<BalloonStats>)> + Send + 'a>>;
+
+/// [`Stream`](futures_core::Stream) of the balloon statistics polled every interval,
+/// returned by [`Instance::balloon_stats_stream`].
+///
+/// Dropping it while a request is in flight leaves that request unfinished on the API
+/// connection: the following requests of the instance then fail with [`Error::Agent`]
+/// rather than taking the abandoned response for their own.
+#[cfg(feature = "models")]
+pub struct BalloonStatsStream<'a> {
+    // lent to `poll` while a request is in flight
+    instance: Option<&'a mut Instance>,
+    poll: Option<BalloonStatsPoll<'a>>,
+    interval: Duration,
+    polled: bool,
+}
+
+#[cfg(feature = "models")]
+impl futures_core::Stream for BalloonStatsStream<'_> {
+    type Item = Result<BalloonStats>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let poll = match this.poll {
+            Some(ref mut poll) => poll,
+            None => {
+                // unwrap safe: handed back by the previous request
+                let instance = this.instance.take().unwrap();
+                let interval = this.polled.then_some(this.interval);
+                this.polled = true;
+                this.poll.insert(Box::pin(async move {
+                    if let Some(interval) = interval {
+                        SystemClock.sleep(interval).await;
+                    }
+                    let stats = instance.describe_balloon_stats().await;
+                    (instance, stats)
+                }))
+            }
+        };
+        match poll.as_mut().poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready((instance, stats)) => {
+                this.instance = Some(instance);
+                this.poll = None;
+                Poll::Ready(Some(stats))
+            }
         }
     }
 }
+
+/// Guard of a paused [`Instance`] returned by [`Instance::pause_scope`].
+/// Dereferences to the [`Instance`] for operations performed while paused.
+#[cfg(feature = "models")]
+pub struct PauseGuard<'a> {
+    instance: &'a mut Instance,
+    resumed: bool,
+}
+
+#[cfg(feature = "models")]
+impl PauseGuard<'_> {
+    /// Resume the instance.
+    pub async fn resume(mut self) -> Result<()> {
+        self.resumed = true;
+        self.instance.resume().await
+    }
+}
+
+#[cfg(feature = "models")]
+impl Drop for PauseGuard<'_> {
+    fn drop(&mut self) {
+        if !self.resumed {
+            warn!("PauseGuard dropped without `resume`, the instance stays paused");
+        }
+    }
+}
+
+#[cfg(feature = "models")]
+impl Deref for PauseGuard<'_> {
+    type Target = Instance;
+
+    fn deref(&self) -> &Self::Target {
+        self.instance
+    }
+}
+
+#[cfg(feature = "models")]
+impl DerefMut for PauseGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.instance
+    }
+}
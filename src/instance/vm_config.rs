@@ -0,0 +1,153 @@
+use crate::models::{
+    Balloon, BootSource, Drive, EntropyDevice, FullVmConfiguration, Logger, MachineConfiguration,
+    Metrics, MmdsConfig, NetworkInterface, Vsock,
+};
+
+/// Pre-boot configuration of a VM, sent at once by `Instance::apply_config` in this order:
+/// 1. logger and metrics, so that the following failures are logged;
+/// 2. machine configuration;
+/// 3. boot source;
+/// 4. drives, in the order they were added;
+/// 5. network interfaces, in the order they were added;
+/// 6. MMDS configuration, which refers to the network interfaces;
+/// 7. vsock;
+/// 8. balloon;
+/// 9. entropy device.
+///
+/// Converts from the [`FullVmConfiguration`] of a `firecracker` config file.
+///
+/// ```
+/// # use firecracker_rs_sdk::{instance::VmConfigBuilder, models::*};
+/// let mut root_drive = Drive::new("rootfs", "/path/to/rootfs.ext4");
+/// root_drive.is_root_device = true;
+/// let config = VmConfigBuilder::new()
+///     .machine_config(MachineConfiguration::new(1, 256))
+///     .boot_source(BootSource::new("/path/to/vmlinux"))
+///     .drive(root_drive)
+///     .network_interface(NetworkInterface::new("eth0", "tap0").unwrap())
+///     .mmds_config(MmdsConfig::new(vec!["eth0".into()]));
+/// // instance.apply_config(&config)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VmConfigBuilder {
+    pub(super) logger: Option<Logger>,
+    pub(super) metrics: Option<Metrics>,
+    pub(super) machine_config: Option<MachineConfiguration>,
+    pub(super) boot_source: Option<BootSource>,
+    pub(super) drives: Vec<Drive>,
+    pub(super) network_interfaces: Vec<NetworkInterface>,
+    pub(super) mmds_config: Option<MmdsConfig>,
+    pub(super) vsock: Option<Vsock>,
+    pub(super) balloon: Option<Balloon>,
+    pub(super) entropy: Option<EntropyDevice>,
+}
+
+impl VmConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn logger(mut self, logger: Logger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn machine_config(mut self, machine_config: MachineConfiguration) -> Self {
+        self.machine_config = Some(machine_config);
+        self
+    }
+
+    pub fn boot_source(mut self, boot_source: BootSource) -> Self {
+        self.boot_source = Some(boot_source);
+        self
+    }
+
+    /// Add a drive, replacing the one added with the same `drive_id` (if any).
+    pub fn drive(mut self, drive: Drive) -> Self {
+        match self
+            .drives
+            .iter_mut()
+            .find(|x| x.drive_id == drive.drive_id)
+        {
+            Some(x) => *x = drive,
+            None => self.drives.push(drive),
+        }
+        self
+    }
+
+    /// Add a network interface, replacing the one added with the same `iface_id` (if any).
+    pub fn network_interface(mut self, network_interface: NetworkInterface) -> Self {
+        match self
+            .network_interfaces
+            .iter_mut()
+            .find(|x| x.iface_id == network_interface.iface_id)
+        {
+            Some(x) => *x = network_interface,
+            None => self.network_interfaces.push(network_interface),
+        }
+        self
+    }
+
+    pub fn mmds_config(mut self, mmds_config: MmdsConfig) -> Self {
+        self.mmds_config = Some(mmds_config);
+        self
+    }
+
+    pub fn vsock(mut self, vsock: Vsock) -> Self {
+        self.vsock = Some(vsock);
+        self
+    }
+
+    pub fn balloon(mut self, balloon: Balloon) -> Self {
+        self.balloon = Some(balloon);
+        self
+    }
+
+    pub fn entropy(mut self, entropy: EntropyDevice) -> Self {
+        self.entropy = Some(entropy);
+        self
+    }
+}
+
+impl From<FullVmConfiguration> for VmConfigBuilder {
+    fn from(config: FullVmConfiguration) -> Self {
+        Self {
+            logger: config.logger,
+            metrics: config.metrics,
+            machine_config: config.machine_config,
+            boot_source: config.boot_source,
+            drives: config.drives.unwrap_or_default(),
+            network_interfaces: config.network_interfaces.unwrap_or_default(),
+            mmds_config: config.mmds_config,
+            vsock: config.vsock,
+            balloon: config.balloon,
+            entropy: config.entropy,
+        }
+    }
+}
+
+/// The [`FullVmConfiguration`] of a `firecracker` config file, see
+/// [`FullVmConfiguration::to_config_json`].
+impl From<VmConfigBuilder> for FullVmConfiguration {
+    fn from(config: VmConfigBuilder) -> Self {
+        Self {
+            logger: config.logger,
+            metrics: config.metrics,
+            machine_config: config.machine_config,
+            boot_source: config.boot_source,
+            drives: (!config.drives.is_empty()).then_some(config.drives),
+            network_interfaces: (!config.network_interfaces.is_empty())
+                .then_some(config.network_interfaces),
+            mmds_config: config.mmds_config,
+            vsock: config.vsock,
+            balloon: config.balloon,
+            cpu_config: None,
+            entropy: config.entropy,
+        }
+    }
+}
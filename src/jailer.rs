@@ -1,15 +1,22 @@
 //! Option to launch jailer
 
+#[cfg(not(feature = "debug-full"))]
+use std::fmt;
 use std::{
-    fs::{self, File, OpenOptions},
+    fs::{self, DirBuilder, File, OpenOptions},
+    os::unix::fs::DirBuilderExt,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    time::Duration,
 };
 
+use log::warn;
 use serde::{Deserialize, Serialize};
 
+#[cfg(not(feature = "debug-full"))]
+use crate::redact::{redact, redact_path, RedactedPath};
 use crate::{
-    firecracker::{FirecrackerOption, DEFAULT_API_SOCK, DEFAULT_ID},
+    firecracker::{self, find_executable, FirecrackerOption, DEFAULT_API_SOCK, DEFAULT_ID},
     instance::Instance,
     Error, Result,
 };
@@ -17,7 +24,25 @@ use crate::{
 pub const DEFAULT_CGROUP_VERSION: usize = 1;
 pub const DEFAULT_CHROOT_BASE_DIR: &'static str = "/srv/jailer";
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Environment variable overriding the path of the `jailer` binary.
+pub const JAILER_BIN_ENV: &str = "JAILER_BIN";
+
+/// Locate the `jailer` binary.
+///
+/// Searches, in order, the path given by [`JAILER_BIN_ENV`], `PATH` and the
+/// common install locations `/usr/bin`, `/usr/local/bin` and `/opt/firecracker`,
+/// returning the first existing executable.
+pub fn find_binary() -> Option<PathBuf> {
+    find_executable("jailer", JAILER_BIN_ENV)
+}
+
+/// Name of the file under `<chroot_base>/<exec_file_name>/<id>/` recording the `exec_file`.
+const EXEC_FILE_MARKER: &str = ".exec_file";
+
+/// The `Debug` output masks absolute paths to their basenames and hides uid / gid so the
+/// option can be logged safely. Enable feature `debug-full` to print everything unmodified.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug-full", derive(Debug))]
 pub struct JailerOption<'f> {
     jailer_bin: PathBuf,
 
@@ -67,8 +92,12 @@ pub struct JailerOption<'f> {
     chroot_strategy: ChrootStrategy,
 
     // Whether to remove the jailer directory of the instance after using / error.
+    // `None` (default) and `Some(false)` keep it, `Some(true)` removes it.
     remove_jailer_workspace_dir: Option<bool>,
 
+    // Whether to create the chroot base directory if it does not exist.
+    create_chroot_base_dir: Option<bool>,
+
     // Stdin of the jailer
     stdin: Option<PathBuf>,
 
@@ -77,9 +106,55 @@ pub struct JailerOption<'f> {
 
     // Stderr of the jailer
     stderr: Option<PathBuf>,
+
+    // Time given to firecracker to exit after SIGTERM before SIGKILL, see `DEFAULT_SHUTDOWN_GRACE`.
+    shutdown_grace: Option<Duration>,
+    // Size of the reads of API responses, see `DEFAULT_READ_BUFFER_SIZE`.
+    read_buffer_size: Option<usize>,
+    // Name of the pid file `jailer` writes into the jail, `<exec_file_name>.pid` by default.
+    pid_file_name: Option<String>,
+}
+
+#[cfg(not(feature = "debug-full"))]
+impl fmt::Debug for JailerOption<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JailerOption")
+            .field("jailer_bin", &RedactedPath(&self.jailer_bin))
+            .field("exec_file", &redact_path(self.exec_file.as_ref()))
+            .field("gid", &redact(self.gid.as_ref()))
+            .field("id", &self.id)
+            .field("uid", &redact(self.uid.as_ref()))
+            .field("cgroup", &self.cgroup)
+            .field("cgroup_version", &self.cgroup_version)
+            .field(
+                "chroot_base_dir",
+                &redact_path(self.chroot_base_dir.as_ref()),
+            )
+            .field("daemonize", &self.daemonize)
+            .field("netns", &redact_path(self.netns.as_ref()))
+            .field("new_pid_ns", &self.new_pid_ns)
+            .field("parent_cgroup", &self.parent_cgroup)
+            .field("resource_limit", &self.resource_limit)
+            .field("firecracker_option", &self.firecracker_option)
+            .field("chroot_strategy", &self.chroot_strategy)
+            .field(
+                "remove_jailer_workspace_dir",
+                &self.remove_jailer_workspace_dir,
+            )
+            .field("create_chroot_base_dir", &self.create_chroot_base_dir)
+            .field("stdin", &redact_path(self.stdin.as_ref()))
+            .field("stdout", &redact_path(self.stdout.as_ref()))
+            .field("stderr", &redact_path(self.stderr.as_ref()))
+            .field("shutdown_grace", &self.shutdown_grace)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("pid_file_name", &self.pid_file_name)
+            .finish()
+    }
 }
 
 impl<'f> JailerOption<'f> {
+    /// Pass an empty `jailer_bin` / `exec_file` to locate the binaries with [`find_binary`] /
+    /// [`firecracker::find_binary`] when building the instance.
     pub fn new<P, Q, S>(jailer_bin: P, exec_file: Q, id: S, gid: usize, uid: usize) -> Self
     where
         P: AsRef<Path>,
@@ -106,6 +181,88 @@ impl<'f> JailerOption<'f> {
         Ok(exec_file_name.into())
     }
 
+    /// Name of the pid file of `firecracker` in the jailer workspace directory.
+    fn resolved_pid_file_name(&self) -> Result<PathBuf> {
+        match self.pid_file_name {
+            Some(ref pid_file_name) => Ok(pid_file_name.into()),
+            None => {
+                let mut pid_file_name = self.exec_file_name()?.into_os_string();
+                pid_file_name.push(".pid");
+                Ok(pid_file_name.into())
+            }
+        }
+    }
+
+    /// Check that the chroot base directory exists and is writable,
+    /// unless it's yet to be created because `create_chroot_base` is set.
+    fn check_chroot_base_dir(&self) -> Result<()> {
+        let chroot_base_dir = match self.chroot_base_dir {
+            Some(ref chroot_base_dir) => chroot_base_dir,
+            None => &PathBuf::from(DEFAULT_CHROOT_BASE_DIR),
+        };
+
+        if !chroot_base_dir.exists() {
+            if let Some(true) = self.create_chroot_base_dir {
+                return Ok(());
+            }
+            return Err(Error::Configuration(format!(
+                "chroot base directory {} does not exist",
+                chroot_base_dir.display()
+            )));
+        }
+
+        if !chroot_base_dir.is_dir() {
+            return Err(Error::Configuration(format!(
+                "chroot base directory {} is not a directory",
+                chroot_base_dir.display()
+            )));
+        }
+
+        // probe writability by creating and removing a file
+        let probe = chroot_base_dir.join(format!(".probe-{}", std::process::id()));
+        match OpenOptions::new().write(true).create_new(true).open(&probe) {
+            Ok(_) => {
+                let _ = fs::remove_file(&probe);
+                Ok(())
+            }
+            Err(e) => Err(Error::ConfigurationIO {
+                context: format!(
+                    "chroot base directory {} is not writable",
+                    chroot_base_dir.display()
+                ),
+                source: e,
+            }),
+        }
+    }
+
+    /// Create the chroot base directory (`/srv/jailer` by default) if it does not exist,
+    /// with mode `0755` so that the jailed `firecracker` can reach its workspace directory,
+    /// then check that it's writable. Fails with an [`Error::ConfigurationIO`] naming the
+    /// directory, e.g. when not run as root on first use.
+    ///
+    /// [`JailerOption::build`] calls this if `create_chroot_base` is set.
+    pub fn ensure_chroot_base(&self) -> Result<()> {
+        let chroot_base_dir = match self.chroot_base_dir {
+            Some(ref chroot_base_dir) => chroot_base_dir,
+            None => &PathBuf::from(DEFAULT_CHROOT_BASE_DIR),
+        };
+
+        if !chroot_base_dir.exists() {
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o755)
+                .create(chroot_base_dir)
+                .map_err(|source| Error::ConfigurationIO {
+                    context: format!(
+                        "Fail to create chroot base directory {}",
+                        chroot_base_dir.display()
+                    ),
+                    source,
+                })?;
+        }
+        self.check_chroot_base_dir()
+    }
+
     fn jailer_workspace_dir(&self) -> Result<PathBuf> {
         let chroot_base_dir = match self.chroot_base_dir {
             Some(ref chroot_base_dir) => chroot_base_dir,
@@ -124,13 +281,144 @@ impl<'f> JailerOption<'f> {
             .join(ROOT_FOLDER_NAME);
 
         if jailer_workspace_dir.exists() {
-            return Err(Error::Configuration(format!("conflict jailer ID {id}")));
+            return match self.marked_exec_file(&jailer_workspace_dir) {
+                Some(marked) if Some(&marked) != self.exec_file.as_ref() => {
+                    Err(Error::Configuration(format!(
+                        "conflict jailer ID {id}, workspace is in use by another `exec_file` {}",
+                        marked.display()
+                    )))
+                }
+                _ => Err(Error::Configuration(format!("conflict jailer ID {id}"))),
+            };
         }
 
         Ok(jailer_workspace_dir)
     }
 
+    /// Returns the `exec_file` recorded in the marker file next to `jailer_workspace_dir`.
+    fn marked_exec_file(&self, jailer_workspace_dir: &Path) -> Option<PathBuf> {
+        let marker = jailer_workspace_dir.parent()?.join(EXEC_FILE_MARKER);
+        fs::read_to_string(marker).ok().map(PathBuf::from)
+    }
+
+    /// Record `exec_file` in a marker file next to `jailer_workspace_dir`.
+    ///
+    /// Different binaries sharing the same basename (e.g. `/opt/a/firecracker` and
+    /// `/opt/b/firecracker`) share the same `<chroot_base>/<exec_file_name>/<id>`,
+    /// so a warning is logged when the recorded `exec_file` is a different one.
+    fn mark_exec_file(&self, jailer_workspace_dir: &Path) -> Result<()> {
+        let Some(ref exec_file) = self.exec_file else {
+            return Err(Error::Configuration("`exec_file` not set".into()));
+        };
+        let Some(id_dir) = jailer_workspace_dir.parent() else {
+            return Ok(());
+        };
+
+        if let Some(marked) = self.marked_exec_file(jailer_workspace_dir) {
+            if &marked != exec_file {
+                warn!(
+                    "jailer workspace {} was previously used by `exec_file` {}, now by {}",
+                    id_dir.display(),
+                    marked.display(),
+                    exec_file.display()
+                );
+            }
+        }
+
+        fs::create_dir_all(id_dir)?;
+        fs::write(
+            id_dir.join(EXEC_FILE_MARKER),
+            exec_file.as_os_str().as_encoded_bytes(),
+        )?;
+        Ok(())
+    }
+
+    /// Check the option before spawning `jailer`, so that a misconfiguration fails fast
+    /// with an [`Error::Configuration`] naming it rather than an IO error at spawn:
+    ///
+    /// - the `jailer` and `exec_file` binaries exist, or can be located with [`find_binary`] /
+    ///   [`firecracker::find_binary`] if not given,
+    /// - `exec_file`, `gid`, `id` and `uid` are set,
+    /// - `cgroup_version` is 1 or 2 and `netns` exists,
+    /// - `daemonize` is not combined with `stdin`, `stdout` or `stderr`, since `jailer`
+    ///   redirects them to `/dev/null` when daemonizing,
+    /// - the chroot base directory is writable,
+    /// - the arguments passed on to `firecracker` are consistent, see
+    ///   [`FirecrackerOption::validate_args`].
+    ///
+    /// [`JailerOption::build`] calls this first.
+    pub fn validate(&self) -> Result<()> {
+        if self.jailer_bin.as_os_str().is_empty() {
+            if find_binary().is_none() {
+                return Err(Error::Configuration("`jailer` binary not found".into()));
+            }
+        } else {
+            firecracker::check_binary_exists("jailer_bin", &self.jailer_bin)?;
+        }
+
+        match self.exec_file {
+            None => return Err(Error::Configuration("`exec_file` not set".into())),
+            Some(ref exec_file) if exec_file.as_os_str().is_empty() => {
+                if firecracker::find_binary().is_none() {
+                    return Err(Error::Configuration(
+                        "`firecracker` binary not found".into(),
+                    ));
+                }
+            }
+            Some(ref exec_file) => firecracker::check_binary_exists("exec_file", exec_file)?,
+        }
+        if self.gid.is_none() {
+            return Err(Error::Configuration("`gid` not set".into()));
+        }
+        if self.id.is_none() {
+            return Err(Error::Configuration("`id` not set".into()));
+        }
+        if self.uid.is_none() {
+            return Err(Error::Configuration("`uid` not set".into()));
+        }
+
+        if let Some(ref pid_file_name) = self.pid_file_name {
+            if matches!(pid_file_name.as_str(), "" | "." | "..") || pid_file_name.contains('/') {
+                return Err(Error::Configuration(format!(
+                    "`pid_file_name` must be a file name, got {pid_file_name:?}"
+                )));
+            }
+        }
+
+        if let Some(cgroup_version) = self.cgroup_version {
+            if cgroup_version != 1 && cgroup_version != 2 {
+                return Err(Error::Configuration(format!(
+                    "`cgroup_version` must be 1 or 2, got {cgroup_version}"
+                )));
+            }
+        }
+        firecracker::check_file_exists("netns", self.netns.as_deref())?;
+
+        if let Some(true) = self.daemonize {
+            let redirected = [
+                ("stdin", &self.stdin),
+                ("stdout", &self.stdout),
+                ("stderr", &self.stderr),
+            ];
+            if let Some((name, _)) = redirected.iter().find(|(_, path)| path.is_some()) {
+                return Err(Error::Configuration(format!(
+                    "`daemonize` and `{name}` are mutually exclusive"
+                )));
+            }
+        }
+        firecracker::check_file_exists("stdin", self.stdin.as_deref())?;
+
+        self.check_chroot_base_dir()?;
+
+        if let Some(firecracker_option) = self.firecracker_option {
+            firecracker_option.validate_args()?;
+        }
+        Ok(())
+    }
+
     pub fn build(&mut self) -> Result<Instance> {
+        self.validate()?;
+
         // spawn instance with jailer
         let mut command = self.build_cmd()?;
 
@@ -151,7 +439,11 @@ impl<'f> JailerOption<'f> {
             ));
         }
 
+        if let Some(true) = self.create_chroot_base_dir {
+            self.ensure_chroot_base()?;
+        }
         let jailer_workspace_dir = self.jailer_workspace_dir()?;
+        self.mark_exec_file(&jailer_workspace_dir)?;
         let firecracker_api_sock = match self
             .firecracker_option
             .and_then(|opt| opt.api_sock.as_ref())
@@ -166,17 +458,74 @@ impl<'f> JailerOption<'f> {
         let socket_on_host = ChrootStrategy::FullLinkStrategy
             .chroot_path(&jailer_workspace_dir, firecracker_api_sock)?;
 
-        Ok(Instance::new(
+        // `jailer` prepares `/run` inside the jail, other directories are left to us
+        let socket_dir_in_jail = firecracker_api_sock.parent();
+        if let (Some(firecracker_option), Some(socket_dir_in_jail)) =
+            (self.firecracker_option, socket_dir_in_jail)
+        {
+            if socket_dir_in_jail != Path::new("/") && socket_dir_in_jail != Path::new("/run") {
+                let owner = match (self.uid, self.gid) {
+                    (Some(uid), Some(gid)) => Some((uid as u32, gid as u32)),
+                    _ => None,
+                };
+                firecracker_option.prepare_socket_dir(&socket_on_host, owner)?;
+            }
+        }
+
+        let log_on_host = match self
+            .firecracker_option
+            .and_then(|opt| opt.log_path.as_ref())
+        {
+            Some(log_path) => Some(
+                ChrootStrategy::FullLinkStrategy.chroot_path(&jailer_workspace_dir, log_path)?,
+            ),
+            None => None,
+        };
+
+        let mut instance = Instance::new(
             socket_on_host,
             Some(jailer_workspace_dir),
             Some(self.chroot_strategy.clone()),
             self.remove_jailer_workspace_dir,
             command,
-            self.exec_file_name()?,
-        ))
+            Some(self.resolved_pid_file_name()?),
+            None,
+            None,
+            None,
+            self.new_pid_ns,
+            self.shutdown_grace,
+        );
+        if let Some(log_on_host) = log_on_host {
+            instance.set_log_on_host(log_on_host);
+        }
+        if let Some(http_api_max_payload_size) = self
+            .firecracker_option
+            .and_then(|opt| opt.http_api_max_payload_size)
+        {
+            instance.set_http_api_max_payload_size(http_api_max_payload_size);
+        }
+        if let Some(read_buffer_size) = self.read_buffer_size {
+            instance.set_read_buffer_size(read_buffer_size);
+        }
+        Ok(instance)
     }
 
     pub fn build_cmd(&mut self) -> Result<Command> {
+        if self.jailer_bin.as_os_str().is_empty() {
+            self.jailer_bin = find_binary()
+                .ok_or_else(|| Error::Configuration("`jailer` binary not found".into()))?;
+        }
+        if self
+            .exec_file
+            .as_ref()
+            .is_some_and(|exec_file| exec_file.as_os_str().is_empty())
+        {
+            self.exec_file =
+                Some(firecracker::find_binary().ok_or_else(|| {
+                    Error::Configuration("`firecracker` binary not found".into())
+                })?);
+        }
+
         let mut cmd = Command::new(&self.jailer_bin);
 
         let Some(ref exec_file) = self.exec_file else {
@@ -233,6 +582,7 @@ impl<'f> JailerOption<'f> {
         }
 
         if let Some(firecracker_option) = self.firecracker_option {
+            firecracker_option.validate_args()?;
             let firecracker_cmd = firecracker_option.build_cmd();
             cmd.arg("--").args(firecracker_cmd.get_args());
         }
@@ -313,8 +663,27 @@ impl<'f> JailerOption<'f> {
         self
     }
 
+    /// Remove the jailer workspace directory when the instance is dropped,
+    /// shorthand for `set_remove_jailer_workspace_dir(true)`.
     pub fn remove_jailer_workspace_dir(&mut self) -> &mut Self {
-        self.remove_jailer_workspace_dir = Some(true);
+        self.set_remove_jailer_workspace_dir(true)
+    }
+
+    /// Set whether to remove the jailer workspace directory when the instance is dropped.
+    /// The directory is kept unless this is set to `true`; setting `false` explicitly keeps it,
+    /// e.g. to override an earlier `remove_jailer_workspace_dir()`.
+    pub fn set_remove_jailer_workspace_dir(
+        &mut self,
+        remove_jailer_workspace_dir: bool,
+    ) -> &mut Self {
+        self.remove_jailer_workspace_dir = Some(remove_jailer_workspace_dir);
+        self
+    }
+
+    /// Create the chroot base directory when building the instance if it does not exist,
+    /// see [`JailerOption::ensure_chroot_base`].
+    pub fn create_chroot_base(&mut self) -> &mut Self {
+        self.create_chroot_base_dir = Some(true);
         self
     }
 
@@ -332,6 +701,32 @@ impl<'f> JailerOption<'f> {
         self.stderr = Some(stderr.as_ref().into());
         self
     }
+
+    /// Time given to `firecracker` to exit after `SIGTERM` when the instance is dropped
+    /// (or closed by `Instance::close` with an async runtime, whose drop kills it right
+    /// away), before it's killed with `SIGKILL`. Defaults to
+    /// [`DEFAULT_SHUTDOWN_GRACE`](crate::fstack::DEFAULT_SHUTDOWN_GRACE).
+    pub fn shutdown_grace(&mut self, grace: Duration) -> &mut Self {
+        self.shutdown_grace = Some(grace);
+        self
+    }
+
+    /// Size of the reads of the responses of the API socket, in bytes. Defaults to
+    /// [`DEFAULT_READ_BUFFER_SIZE`](crate::agent::DEFAULT_READ_BUFFER_SIZE), larger buffers
+    /// take fewer syscalls to read large responses such as the exported VM config.
+    pub fn read_buffer_size(&mut self, read_buffer_size: usize) -> &mut Self {
+        self.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
+    /// Name of the pid file `jailer` writes `firecracker`'s pid to in the jail, read by
+    /// [`Instance::start_vmm`]. Defaults to `<exec_file_name>.pid`, where `exec_file_name`
+    /// is the basename of `exec_file`; override it if `jailer` names the file otherwise,
+    /// e.g. when `exec_file` is a symlink or a renamed binary.
+    pub fn pid_file_name<S: AsRef<str>>(&mut self, pid_file_name: S) -> &mut Self {
+        self.pid_file_name = Some(pid_file_name.as_ref().into());
+        self
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -339,6 +734,19 @@ pub enum ChrootStrategy {
     #[default]
     NaiveLinkStrategy,
     FullLinkStrategy,
+    /// Lay out files like [`ChrootStrategy::FullLinkStrategy`] but copy them into the jail
+    /// instead of hard-linking, for a chroot base on another filesystem than the files.
+    ///
+    /// The copy is not kept in sync with the original, e.g. the writes of the guest to
+    /// a drive land in the copy. FIFOs and sockets (e.g. a log FIFO) are still hard-linked.
+    CopyStrategy,
+    /// Lay out files like [`ChrootStrategy::FullLinkStrategy`] but bind mount them (files or
+    /// directories) into the jail, e.g. for large rootfs images on another filesystem.
+    ///
+    /// Requires `CAP_SYS_ADMIN`. The mounts made through an [`Instance`] are unmounted when
+    /// it's dropped, those made by calling [`ChrootStrategy::perform_link`] directly are left
+    /// to the caller.
+    BindMountStrategy,
 }
 
 impl ChrootStrategy {
@@ -358,7 +766,7 @@ impl ChrootStrategy {
                 );
                 Ok(link)
             }
-            Self::FullLinkStrategy => {
+            Self::FullLinkStrategy | Self::CopyStrategy | Self::BindMountStrategy => {
                 let path: &Path = path_on_host.as_ref();
                 let path = if path.is_absolute() {
                     path.strip_prefix("/").map_err(|e| {
@@ -374,11 +782,53 @@ impl ChrootStrategy {
         }
     }
 
-    /// Perform actual link behavior
+    /// Perform actual link behavior.
+    /// All strategies but [`ChrootStrategy::NaiveLinkStrategy`] create the missing parent
+    /// directories of `link`.
     pub fn perform_link<P: AsRef<Path>, Q: AsRef<Path>>(&self, origin: P, link: Q) -> Result<()> {
         match self {
             Self::NaiveLinkStrategy => fs::hard_link(origin.as_ref(), &link)?,
-            Self::FullLinkStrategy => fs::hard_link(origin.as_ref(), &link)?,
+            Self::FullLinkStrategy => {
+                if let Some(parent) = link.as_ref().parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::hard_link(origin.as_ref(), &link)?
+            }
+            Self::CopyStrategy => {
+                if let Some(parent) = link.as_ref().parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                // copying a FIFO would block reading it
+                if fs::metadata(origin.as_ref())?.is_file() {
+                    fs::copy(origin.as_ref(), &link)?;
+                } else {
+                    fs::hard_link(origin.as_ref(), &link)?
+                }
+            }
+            Self::BindMountStrategy => {
+                let link = link.as_ref();
+                if let Some(parent) = link.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                // the mount point must exist, as the same type of file as `origin`
+                if fs::metadata(origin.as_ref())?.is_dir() {
+                    fs::create_dir_all(link)?;
+                } else {
+                    OpenOptions::new()
+                        .create(true)
+                        .truncate(false)
+                        .write(true)
+                        .open(link)?;
+                }
+                bind_mount(origin.as_ref(), link).map_err(|source| Error::ConfigurationIO {
+                    context: format!(
+                        "Fail to bind mount {} on {} (requires `CAP_SYS_ADMIN`)",
+                        origin.as_ref().display(),
+                        link.display()
+                    ),
+                    source,
+                })?;
+            }
         }
         Ok(())
     }
@@ -393,3 +843,196 @@ impl ChrootStrategy {
         Ok(link)
     }
 }
+
+fn bind_mount(origin: &Path, target: &Path) -> std::io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let origin = CString::new(origin.as_os_str().as_bytes())?;
+    let target = CString::new(target.as_os_str().as_bytes())?;
+    // SAFETY: `origin` and `target` are valid NUL-terminated strings, the file system type
+    // and data are ignored for a bind mount.
+    let ret = unsafe {
+        libc::mount(
+            origin.as_ptr(),
+            target.as_ptr(),
+            std::ptr::null(),
+            libc::MS_BIND,
+            std::ptr::null(),
+        )
+    };
+    match ret {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChrootStrategy, JailerOption};
+    use crate::{firecracker::FirecrackerOption, Error};
+
+    #[test]
+    fn validate_option() {
+        const CHROOT_BASE_DIR: &str = "/tmp/firecracker-sdk-test-jailer-validate";
+        let _ = std::fs::remove_dir_all(CHROOT_BASE_DIR);
+
+        let mut option = JailerOption::new("/bin/sh", "/bin/sh", "vm0", 0, 0);
+        option.chroot_base_dir(Some(CHROOT_BASE_DIR));
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+        option.create_chroot_base();
+        assert!(option.validate().is_ok());
+
+        option.exec_file(Some("/nonexistent/firecracker"));
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+        option.exec_file(Some("/bin/sh"));
+
+        option.cgroup_version(Some(3));
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+        option.cgroup_version(Some(2));
+
+        for pid_file_name in ["run/firecracker.pid", "", ".", ".."] {
+            option.pid_file_name(pid_file_name);
+            assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+        }
+        option.pid_file_name("firecracker.pid");
+        assert!(option.validate().is_ok());
+
+        option.daemonize().stdout("/dev/null");
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+
+        let mut option = JailerOption::new("/nonexistent/jailer", "/bin/sh", "vm0", 0, 0);
+        option
+            .chroot_base_dir(Some(CHROOT_BASE_DIR))
+            .create_chroot_base();
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+
+        let mut firecracker_option = FirecrackerOption::new("");
+        firecracker_option.no_api();
+        let mut option = JailerOption::new("/bin/sh", "/bin/sh", "vm0", 0, 0);
+        option
+            .chroot_base_dir(Some(CHROOT_BASE_DIR))
+            .create_chroot_base()
+            .firecracker_option(Some(&firecracker_option));
+        assert!(matches!(option.validate(), Err(Error::Configuration(_))));
+    }
+
+    #[test]
+    fn ensure_chroot_base() {
+        use std::os::unix::fs::PermissionsExt;
+
+        const DIR: &str = "/tmp/firecracker-sdk-test-jailer-ensure-chroot-base";
+        let _ = std::fs::remove_dir_all(DIR);
+
+        let chroot_base_dir = format!("{DIR}/srv/jailer");
+        let mut option = JailerOption::new("/bin/sh", "/bin/sh", "vm0", 0, 0);
+        option.chroot_base_dir(Some(&chroot_base_dir));
+        option.ensure_chroot_base().unwrap();
+        let metadata = std::fs::metadata(&chroot_base_dir).unwrap();
+        assert!(metadata.is_dir());
+        // at most `0755`, depending on the umask
+        assert_eq!(metadata.permissions().mode() & 0o777 & !0o755, 0);
+        // already there
+        option.ensure_chroot_base().unwrap();
+
+        // under a regular file
+        std::fs::write(format!("{DIR}/file"), "").unwrap();
+        option.chroot_base_dir(Some(format!("{DIR}/file/jailer")));
+        assert!(matches!(
+            option.ensure_chroot_base(),
+            Err(Error::ConfigurationIO { .. })
+        ));
+
+        let _ = std::fs::remove_dir_all(DIR);
+    }
+
+    #[test]
+    fn full_link_nested_path() {
+        use std::os::unix::fs::MetadataExt;
+
+        const DIR: &str = "/tmp/firecracker-sdk-test-jailer-full-link";
+        let _ = std::fs::remove_dir_all(DIR);
+        let path_on_host = format!("{DIR}/host/opt/data/foo.ext4");
+        std::fs::create_dir_all(format!("{DIR}/host/opt/data")).unwrap();
+        std::fs::write(&path_on_host, "rootfs").unwrap();
+        let rootfs = format!("{DIR}/firecracker/vm0/root");
+        std::fs::create_dir_all(&rootfs).unwrap();
+
+        let link = ChrootStrategy::FullLinkStrategy
+            .link_file(&rootfs, &path_on_host)
+            .unwrap();
+        assert_eq!(link, std::path::Path::new(&rootfs).join(&path_on_host[1..]));
+        assert_eq!(
+            std::fs::metadata(&link).unwrap().ino(),
+            std::fs::metadata(&path_on_host).unwrap().ino()
+        );
+
+        let _ = std::fs::remove_dir_all(DIR);
+    }
+
+    #[test]
+    fn copy_strategy() {
+        use std::os::unix::fs::MetadataExt;
+
+        const DIR: &str = "/tmp/firecracker-sdk-test-jailer-copy";
+        let _ = std::fs::remove_dir_all(DIR);
+        let path_on_host = format!("{DIR}/host/opt/data/foo.ext4");
+        std::fs::create_dir_all(format!("{DIR}/host/opt/data")).unwrap();
+        std::fs::write(&path_on_host, "rootfs").unwrap();
+        let rootfs = format!("{DIR}/firecracker/vm0/root");
+        std::fs::create_dir_all(&rootfs).unwrap();
+
+        let copy = ChrootStrategy::CopyStrategy
+            .link_file(&rootfs, &path_on_host)
+            .unwrap();
+        assert_eq!(
+            copy,
+            ChrootStrategy::FullLinkStrategy
+                .chroot_path(&rootfs, &path_on_host)
+                .unwrap()
+        );
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "rootfs");
+        assert_ne!(
+            std::fs::metadata(&copy).unwrap().ino(),
+            std::fs::metadata(&path_on_host).unwrap().ino()
+        );
+
+        let _ = std::fs::remove_dir_all(DIR);
+    }
+
+    #[test]
+    fn bind_mount_strategy() {
+        use crate::fstack::{FStack, FStackAction};
+
+        const DIR: &str = "/tmp/firecracker-sdk-test-jailer-bind-mount";
+        let _ = std::fs::remove_dir_all(DIR);
+        let path_on_host = format!("{DIR}/host/opt/data/foo.ext4");
+        std::fs::create_dir_all(format!("{DIR}/host/opt/data")).unwrap();
+        std::fs::write(&path_on_host, "rootfs").unwrap();
+        let rootfs = format!("{DIR}/firecracker/vm0/root");
+        std::fs::create_dir_all(&rootfs).unwrap();
+
+        let mount_point = match ChrootStrategy::BindMountStrategy.link_file(&rootfs, &path_on_host)
+        {
+            Ok(mount_point) => mount_point,
+            Err(Error::ConfigurationIO { source, .. })
+                if source.kind() == std::io::ErrorKind::PermissionDenied =>
+            {
+                eprintln!("skipping `bind_mount_strategy`: bind mounting requires CAP_SYS_ADMIN");
+                return;
+            }
+            Err(e) => panic!("{e}"),
+        };
+        let mut fstack = FStack::new();
+        fstack.push_action(FStackAction::Unmount(mount_point.clone()));
+
+        // writes go through to the file on the host
+        std::fs::write(&mount_point, "updated").unwrap();
+        assert_eq!(std::fs::read_to_string(&path_on_host).unwrap(), "updated");
+
+        // the empty mount point is left behind
+        drop(fstack);
+        assert_eq!(std::fs::read_to_string(&mount_point).unwrap(), "");
+
+        let _ = std::fs::remove_dir_all(DIR);
+    }
+}
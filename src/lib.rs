@@ -1,10 +1,25 @@
 pub mod agent;
+#[cfg(feature = "cgroups")]
+pub mod cgroup;
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+mod clock;
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+mod console;
 pub mod events;
 pub mod firecracker;
 pub mod fstack;
 pub mod instance;
 pub mod jailer;
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+pub mod log_stream;
+#[cfg(feature = "models")]
 pub mod models;
+#[cfg(not(feature = "debug-full"))]
+mod redact;
+#[cfg(feature = "spec")]
+pub mod spec;
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+pub mod vsock;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -12,16 +27,101 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error("Agent: {0}")]
     Agent(String),
+    #[error("Agent: {context}")]
+    AgentIO {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Timeout: {operation} timed out after {after:?}")]
+    Timeout {
+        operation: String,
+        after: std::time::Duration,
+    },
     #[error("Configuraion: {0}")]
     Configuration(String),
+    #[error("Configuraion: {context}")]
+    ConfigurationIO {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
     #[error("Event: {0}")]
     Event(String),
+    /// Request rejected by the `firecracker` API: `message` is the `fault_message` of the
+    /// error body (see [`Error::fault`]), or the raw body if it is not a fault object.
+    #[error("Firecracker API: {status} {message}")]
+    FirecrackerApiError {
+        status: u16,
+        message: String,
+        /// The error body, if it decoded as a fault object.
+        #[cfg(feature = "models")]
+        fault: Option<models::FirecrackerFault>,
+    },
     #[error("Instance: {0}")]
     Instance(String),
+    #[error("Instance: call start_vmm() before issuing API requests")]
+    NotStarted,
     #[error("{0}")]
     FeatureNone(String),
 }
 
+impl Error {
+    /// Whether the operation failing with this error is worth retrying.
+    ///
+    /// Retryable errors are transient:
+    /// - [`Error::Timeout`];
+    /// - [`Error::AgentIO`], i.e. failures reading from / writing to the API socket, and
+    ///   [`Error::Agent`] telling `firecracker` closed the connection;
+    /// - [`Error::IO`] of kind interrupted, would-block, timed-out, or a refused / reset /
+    ///   aborted / broken connection.
+    ///
+    /// All the others are fatal and would fail the same way again: [`Error::Configuration`],
+    /// [`Error::ConfigurationIO`],
+    /// [`Error::FeatureNone`], [`Error::NotStarted`], [`Error::Instance`], other IO errors,
+    /// [`Error::Event`] and [`Error::FirecrackerApiError`], i.e. requests rejected by the
+    /// `firecracker` API. So are the other [`Error::Agent`], e.g. a malformed HTTP response or
+    /// a vsock connection refused by the guest.
+    pub fn is_retryable(&self) -> bool {
+        use std::io::ErrorKind;
+        match self {
+            Error::Timeout { .. } | Error::AgentIO { .. } => true,
+            Error::Agent(message) => message.starts_with(agent::CONNECTION_CLOSED),
+            Error::IO(e) => matches!(
+                e.kind(),
+                ErrorKind::Interrupted
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::TimedOut
+                    | ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotConnected
+                    | ErrorKind::BrokenPipe
+            ),
+            Error::Configuration(_)
+            | Error::ConfigurationIO { .. }
+            | Error::Event(_)
+            | Error::FirecrackerApiError { .. }
+            | Error::Instance(_)
+            | Error::NotStarted
+            | Error::FeatureNone(_) => false,
+        }
+    }
+
+    /// The error body of a request rejected by the `firecracker` API, i.e. of
+    /// [`Error::FirecrackerApiError`].
+    ///
+    /// [`None`] if `firecracker` didn't answer with a fault object, the raw body is then the
+    /// `message` of the error.
+    #[cfg(feature = "models")]
+    pub fn fault(&self) -> Option<&models::FirecrackerFault> {
+        match self {
+            Error::FirecrackerApiError { fault, .. } => fault.as_ref(),
+            _ => None,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, crate::Error>;
 
 #[macro_export]
@@ -39,3 +139,62 @@ macro_rules! missing_rt_panic {
         panic!(r#"Must enable one of these features: "_rt-std", "_rt-tokio", "_rt-async-std""#)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use super::Error;
+
+    #[test]
+    fn error_is_retryable() {
+        assert!(Error::Timeout {
+            operation: "connect".into(),
+            after: Duration::from_secs(3),
+        }
+        .is_retryable());
+        assert!(Error::IO(io::ErrorKind::ConnectionReset.into()).is_retryable());
+        assert!(!Error::IO(io::ErrorKind::NotFound.into()).is_retryable());
+        assert!(!Error::Configuration("`id` not set".into()).is_retryable());
+        assert!(!Error::Event("serde_json decode: fault_message".into()).is_retryable());
+        assert!(!Error::FirecrackerApiError {
+            status: 400,
+            message: "The requested operation is not supported after starting the microVM.".into(),
+            #[cfg(feature = "models")]
+            fault: None,
+        }
+        .is_retryable());
+
+        // the connection was closed, rather than a response being malformed
+        assert!(Error::Agent("connection closed by firecracker".into()).is_retryable());
+        assert!(Error::Agent(
+            "connection closed by firecracker after 43 bytes of the response".into()
+        )
+        .is_retryable());
+        for message in [
+            "Bad HTTP response: invalid HTTP version",
+            "Bad Content-Length in HTTP response",
+            "Response headers exceed 1024 bytes",
+            "vsock connection refused: FAILURE",
+        ] {
+            assert!(!Error::Agent(message.into()).is_retryable(), "{message}");
+        }
+    }
+
+    #[test]
+    fn error_source() {
+        use std::error::Error as _;
+
+        let e = Error::AgentIO {
+            context: "Bad read from socket".into(),
+            source: io::ErrorKind::ConnectionReset.into(),
+        };
+        assert_eq!(e.to_string(), "Agent: Bad read from socket");
+        let source = e.source().unwrap().downcast_ref::<io::Error>().unwrap();
+        assert_eq!(source.kind(), io::ErrorKind::ConnectionReset);
+        assert!(e.is_retryable());
+
+        let e = Error::Agent("invalid status line".into());
+        assert!(e.source().is_none());
+    }
+}
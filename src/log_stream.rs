@@ -0,0 +1,240 @@
+//! Reader of the `firecracker` log output
+
+use std::{
+    fs::{File, OpenOptions},
+    os::unix::fs::FileTypeExt,
+    path::Path,
+};
+#[cfg(feature = "_rt-async")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "_rt-async-std")]
+use async_std::io::BufRead as AsyncBufRead;
+#[cfg(feature = "_rt-tokio")]
+use tokio::io::AsyncBufRead;
+
+use crate::Result;
+
+/// Open the read end of a `firecracker` log path.
+///
+/// A FIFO is opened with both read and write access, which never blocks on Linux
+/// even if `firecracker` has not opened the write end yet. Holding the read end
+/// before `firecracker` opens the path avoids the named-pipe open-ordering deadlock.
+/// A regular file is created if it does not exist yet and read from the beginning.
+pub(crate) fn open_log_path<P: AsRef<Path>>(log_path: P) -> Result<File> {
+    let log_path = log_path.as_ref();
+    let is_fifo = log_path
+        .metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_fifo());
+
+    let file = if is_fifo {
+        OpenOptions::new().read(true).write(true).open(log_path)?
+    } else {
+        OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(log_path)?
+    };
+    Ok(file)
+}
+
+/// Line-based stream of the metrics output of `firecracker`, one JSON object per line.
+/// The metrics path is opened like the log path, see [`open_log_path`].
+pub type MetricsStream = LogStream;
+
+/// Line-based stream of the log output of `firecracker`.
+pub struct LogStream {
+    #[cfg(feature = "_rt-std")]
+    reader: std::io::BufReader<File>,
+    #[cfg(feature = "_rt-tokio")]
+    reader: tokio::io::BufReader<tokio::fs::File>,
+    #[cfg(feature = "_rt-async-std")]
+    reader: async_std::io::BufReader<async_std::fs::File>,
+}
+
+#[cfg(feature = "_rt-std")]
+impl LogStream {
+    pub(crate) fn new(file: File) -> Self {
+        Self {
+            reader: std::io::BufReader::new(file),
+        }
+    }
+
+    /// Read the next line of log, without the trailing newline.
+    /// Returns [`None`] at the end of a regular log file.
+    /// Reading from a FIFO blocks until `firecracker` writes a new line.
+    pub fn next_line(&mut self) -> Result<Option<String>> {
+        use std::io::BufRead;
+        let mut line = String::new();
+        match self.reader.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim_end_matches('\n').to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "_rt-tokio")]
+impl LogStream {
+    pub(crate) fn new(file: File) -> Self {
+        Self {
+            reader: tokio::io::BufReader::new(tokio::fs::File::from_std(file)),
+        }
+    }
+
+    /// Read the next line of log, without the trailing newline.
+    /// Returns [`None`] at the end of a regular log file.
+    /// Reading from a FIFO waits until `firecracker` writes a new line.
+    pub async fn next_line(&mut self) -> Result<Option<String>> {
+        use tokio::io::AsyncBufReadExt;
+        let mut line = String::new();
+        match self.reader.read_line(&mut line).await? {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim_end_matches('\n').to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "_rt-async-std")]
+impl LogStream {
+    pub(crate) fn new(file: File) -> Self {
+        Self {
+            reader: async_std::io::BufReader::new(async_std::fs::File::from(file)),
+        }
+    }
+
+    /// Read the next line of log, without the trailing newline.
+    /// Returns [`None`] at the end of a regular log file.
+    /// Reading from a FIFO waits until `firecracker` writes a new line.
+    pub async fn next_line(&mut self) -> Result<Option<String>> {
+        use async_std::io::BufReadExt;
+        let mut line = String::new();
+        match self.reader.read_line(&mut line).await? {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim_end_matches('\n').to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "_rt-async")]
+impl LogStream {
+    /// Turn into a [`Stream`](futures_core::Stream) of the lines of log.
+    pub fn into_lines(self) -> LogLines {
+        LogLines {
+            stream: self,
+            line: Vec::new(),
+        }
+    }
+}
+
+/// [`Stream`](futures_core::Stream) of the lines of log, without the trailing newline.
+/// It ends at the end of a regular log file, while a FIFO waits for `firecracker`
+/// to write new lines.
+#[cfg(feature = "_rt-async")]
+pub struct LogLines {
+    stream: LogStream,
+    // bytes of the line being read, kept across polls
+    line: Vec<u8>,
+}
+
+#[cfg(feature = "_rt-async")]
+impl LogLines {
+    fn take_line(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut self.line)).into_owned()
+    }
+}
+
+#[cfg(feature = "_rt-async")]
+impl futures_core::Stream for LogLines {
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let mut reader = Pin::new(&mut this.stream.reader);
+            let available = match reader.as_mut().poll_fill_buf(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Ready(Ok(available)) => available,
+            };
+
+            if available.is_empty() {
+                if this.line.is_empty() {
+                    return Poll::Ready(None);
+                }
+                return Poll::Ready(Some(Ok(this.take_line())));
+            }
+
+            match available.iter().position(|&byte| byte == b'\n') {
+                Some(end) => {
+                    this.line.extend_from_slice(&available[..end]);
+                    reader.consume(end + 1);
+                    return Poll::Ready(Some(Ok(this.take_line())));
+                }
+                None => {
+                    let consumed = available.len();
+                    this.line.extend_from_slice(available);
+                    reader.consume(consumed);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "_rt-std")]
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write, process::Command};
+
+    use super::{open_log_path, LogStream};
+
+    #[test]
+    fn test_fifo_log_path() {
+        const LOG_PATH: &str = "/tmp/firecracker-sdk-test-log-stream.fifo";
+        let _ = fs::remove_file(LOG_PATH);
+        Command::new("mkfifo").arg(LOG_PATH).status().unwrap();
+
+        // must not block even though there's no writer yet
+        let mut stream = LogStream::new(open_log_path(LOG_PATH).unwrap());
+
+        let mut writer = fs::OpenOptions::new().write(true).open(LOG_PATH).unwrap();
+        writer.write_all(b"hello\nworld\n").unwrap();
+
+        assert_eq!(stream.next_line().unwrap().as_deref(), Some("hello"));
+        assert_eq!(stream.next_line().unwrap().as_deref(), Some("world"));
+
+        let _ = fs::remove_file(LOG_PATH);
+    }
+}
+
+#[cfg(feature = "_rt-tokio")]
+#[cfg(test)]
+mod tests {
+    use std::{fs, future::poll_fn, pin::Pin};
+
+    use futures_core::Stream;
+
+    use super::{open_log_path, LogLines, LogStream};
+    use crate::Result;
+
+    async fn next_line(lines: &mut LogLines) -> Option<Result<String>> {
+        poll_fn(|cx| Pin::new(&mut *lines).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn log_lines() {
+        const LOG_PATH: &str = "/tmp/firecracker-sdk-test-log-lines.log";
+        fs::write(LOG_PATH, "hello\nworld\nno newline").unwrap();
+
+        let mut lines = LogStream::new(open_log_path(LOG_PATH).unwrap()).into_lines();
+        assert_eq!(next_line(&mut lines).await.unwrap().unwrap(), "hello");
+        assert_eq!(next_line(&mut lines).await.unwrap().unwrap(), "world");
+        assert_eq!(next_line(&mut lines).await.unwrap().unwrap(), "no newline");
+        assert!(next_line(&mut lines).await.is_none());
+
+        let _ = fs::remove_file(LOG_PATH);
+    }
+}
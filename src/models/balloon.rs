@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
 
 /// Balloon device descriptor.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct Balloon {
     /// Target balloon size in MiB
     /// Required: true
-    #[serde(rename = "amount_mib")]
+    #[serde(rename = "amount_mib", alias = "amount_mb")]
     pub amount_mib: i64,
 
     /// Whether the balloon should deflate when then guest has memory pressure
@@ -22,3 +24,72 @@ pub struct Balloon {
     )]
     pub stats_polling_interval_s: Option<i64>,
 }
+
+impl Balloon {
+    /// Balloon of `amount_mib` MiB with statistics disabled.
+    pub fn new(amount_mib: i64, deflate_on_oom: bool) -> Self {
+        Self {
+            amount_mib,
+            deflate_on_oom,
+            ..Default::default()
+        }
+    }
+}
+
+/// [`Balloon`] serialized with the target size field named `amount_mb`,
+/// as expected by `firecracker` before [`BALLOON_AMOUNT_MIB_SINCE`].
+///
+/// [`BALLOON_AMOUNT_MIB_SINCE`]: super::firecracker_version::BALLOON_AMOUNT_MIB_SINCE
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LegacyBalloon {
+    #[serde(rename = "amount_mb")]
+    pub amount_mb: i64,
+
+    #[serde(rename = "deflate_on_oom")]
+    pub deflate_on_oom: bool,
+
+    #[serde(
+        rename = "stats_polling_interval_s",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stats_polling_interval_s: Option<i64>,
+}
+
+impl From<&Balloon> for LegacyBalloon {
+    fn from(balloon: &Balloon) -> Self {
+        Self {
+            amount_mb: balloon.amount_mib,
+            deflate_on_oom: balloon.deflate_on_oom,
+            stats_polling_interval_s: balloon.stats_polling_interval_s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_balloon_amount() {
+        let balloon = Balloon {
+            amount_mib: 256,
+            deflate_on_oom: true,
+            stats_polling_interval_s: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&balloon).unwrap(),
+            r#"{"amount_mib":256,"deflate_on_oom":true}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&LegacyBalloon::from(&balloon)).unwrap(),
+            r#"{"amount_mb":256,"deflate_on_oom":true}"#
+        );
+
+        for json in [
+            r#"{"amount_mib":256,"deflate_on_oom":true}"#,
+            r#"{"amount_mb":256,"deflate_on_oom":true}"#,
+        ] {
+            assert_eq!(serde_json::from_str::<Balloon>(json).unwrap(), balloon);
+        }
+    }
+}
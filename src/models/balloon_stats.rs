@@ -5,7 +5,9 @@ use serde::{Deserialize, Serialize};
 /// This structure represents the return value requested
 /// by `GET /balloon/statistics`, which describes detailed
 /// information of the balloon device.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct BalloonStats {
     /// Target number of pages the device aims to hold.
     /// Required: true
@@ -72,3 +74,81 @@ pub struct BalloonStats {
     #[serde(rename = "hugetlb_failures", skip_serializing_if = "Option::is_none")]
     pub hugetlb_failures: Option<u64>,
 }
+
+impl BalloonStats {
+    /// Ratio of free memory to total memory of the guest, in `[0, 1]`.
+    /// Returns [`None`] if the guest did not report its memory statistics.
+    pub fn free_ratio(&self) -> Option<f64> {
+        match (self.free_memory, self.total_memory) {
+            (Some(free), Some(total)) if total > 0 => Some(free as f64 / total as f64),
+            _ => None,
+        }
+    }
+
+    /// Human-readable one-line summary of the statistics.
+    pub fn summary(&self) -> String {
+        const MIB: u64 = 1 << 20;
+        let mut summary = format!(
+            "balloon: {}/{} MiB ({}/{} pages)",
+            self.actual_mib, self.target_mib, self.actual_pages, self.target_pages
+        );
+        if let (Some(free), Some(total)) = (self.free_memory, self.total_memory) {
+            summary.push_str(&format!(", free {}/{} MiB", free / MIB, total / MIB));
+        }
+        if let Some(ratio) = self.free_ratio() {
+            summary.push_str(&format!(" ({:.1}%)", ratio * 100.0));
+        }
+        if let Some(available) = self.available_memory {
+            summary.push_str(&format!(", available {} MiB", available / MIB));
+        }
+        if let Some(disk_caches) = self.disk_caches {
+            summary.push_str(&format!(", disk caches {} MiB", disk_caches / MIB));
+        }
+        if let (Some(swap_in), Some(swap_out)) = (self.swap_in, self.swap_out) {
+            summary.push_str(&format!(", swap in/out {swap_in}/{swap_out} B"));
+        }
+        if let (Some(major), Some(minor)) = (self.major_faults, self.minor_faults) {
+            summary.push_str(&format!(", faults major/minor {major}/{minor}"));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BalloonStats;
+
+    #[test]
+    fn deserialize_balloon_stats() {
+        // captured from `GET /balloon/statistics`
+        const PAYLOAD: &str = r#"{
+            "target_pages": 131072,
+            "actual_pages": 131072,
+            "target_mib": 512,
+            "actual_mib": 512,
+            "swap_in": 0,
+            "swap_out": 0,
+            "major_faults": 11,
+            "minor_faults": 2031,
+            "free_memory": 1863770112,
+            "total_memory": 2092134400,
+            "available_memory": 1890308096,
+            "disk_caches": 36741120,
+            "hugetlb_allocations": 0,
+            "hugetlb_failures": 0
+        }"#;
+
+        let stats: BalloonStats = serde_json::from_str(PAYLOAD).unwrap();
+        assert_eq!(stats.actual_mib, 512);
+        assert_eq!(stats.major_faults, Some(11));
+        assert_eq!(stats.total_memory, Some(2092134400));
+
+        let free_ratio = stats.free_ratio().unwrap();
+        assert!((free_ratio - 0.8908).abs() < 1e-3);
+        assert_eq!(
+            stats.summary(),
+            "balloon: 512/512 MiB (131072/131072 pages), free 1777/1995 MiB (89.1%), \
+             available 1802 MiB, disk caches 35 MiB, swap in/out 0/0 B, faults major/minor 11/2031"
+        );
+    }
+}
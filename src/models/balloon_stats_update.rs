@@ -1,9 +1,18 @@
 use serde::{Deserialize, Serialize};
 
 /// Describes the balloon device statistics.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct BalloonStatsUpdate {
     /// Interval in seconds between refreshing statistics.
     #[serde(rename = "stats_polling_interval_s")]
     pub stats_polling_interval_s: i64,
 }
+
+impl BalloonStatsUpdate {
+    pub fn new(stats_polling_interval_s: i64) -> Self {
+        Self {
+            stats_polling_interval_s,
+        }
+    }
+}
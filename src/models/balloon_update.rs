@@ -1,9 +1,59 @@
 use serde::{Deserialize, Serialize};
 
 /// Balloon device descriptor.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct BalloonUpdate {
     /// Target balloon size in MiB.
-    #[serde(rename = "amount_mib")]
+    #[serde(rename = "amount_mib", alias = "amount_mb")]
     pub amount_mib: i64,
 }
+
+impl BalloonUpdate {
+    pub fn new(amount_mib: i64) -> Self {
+        Self { amount_mib }
+    }
+}
+
+/// [`BalloonUpdate`] serialized with the target size field named `amount_mb`,
+/// as expected by `firecracker` before [`BALLOON_AMOUNT_MIB_SINCE`].
+///
+/// [`BALLOON_AMOUNT_MIB_SINCE`]: super::firecracker_version::BALLOON_AMOUNT_MIB_SINCE
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LegacyBalloonUpdate {
+    #[serde(rename = "amount_mb")]
+    pub amount_mb: i64,
+}
+
+impl From<&BalloonUpdate> for LegacyBalloonUpdate {
+    fn from(balloon_update: &BalloonUpdate) -> Self {
+        Self {
+            amount_mb: balloon_update.amount_mib,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_balloon_update_amount() {
+        let balloon_update = BalloonUpdate { amount_mib: 128 };
+        assert_eq!(
+            serde_json::to_string(&balloon_update).unwrap(),
+            r#"{"amount_mib":128}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&LegacyBalloonUpdate::from(&balloon_update)).unwrap(),
+            r#"{"amount_mb":128}"#
+        );
+
+        for json in [r#"{"amount_mib":128}"#, r#"{"amount_mb":128}"#] {
+            assert_eq!(
+                serde_json::from_str::<BalloonUpdate>(json).unwrap(),
+                balloon_update
+            );
+        }
+    }
+}
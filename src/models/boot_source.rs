@@ -2,8 +2,13 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use super::Validate;
+use crate::{Error, Result};
+
 /// Boot source descriptor.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct BootSource {
     /// Kernel boot arguments defines the command-line arguments
     /// that should be passed to the kernel.
@@ -17,3 +22,89 @@ pub struct BootSource {
     /// Required: true
     pub kernel_image_path: PathBuf,
 }
+
+impl BootSource {
+    /// Boot source booting `kernel_image_path` without boot arguments nor initrd.
+    pub fn new<P: Into<PathBuf>>(kernel_image_path: P) -> Self {
+        Self {
+            kernel_image_path: kernel_image_path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Boot source booting entirely from the initramfs `initrd_path`, for a VM without
+    /// a root drive, with boot arguments [`BootSource::INITRD_BOOT_ARGS`].
+    ///
+    /// No drive must be put with `is_root_device`: `firecracker` would append its `root=`
+    /// to the boot arguments, and the kernel would mount it instead of running the
+    /// initramfs `/init`.
+    pub fn initrd_only<P: Into<PathBuf>, Q: Into<PathBuf>>(
+        kernel_image_path: P,
+        initrd_path: Q,
+    ) -> Self {
+        Self {
+            boot_args: Some(Self::INITRD_BOOT_ARGS.into()),
+            initrd_path: Some(initrd_path.into()),
+            kernel_image_path: kernel_image_path.into(),
+        }
+    }
+
+    /// Boot arguments of [`BootSource::initrd_only`]: a serial console, and no `root=`.
+    pub const INITRD_BOOT_ARGS: &'static str = "console=ttyS0 reboot=k panic=1 pci=off";
+
+    /// Drop the `root=` argument (if any) from `boot_args`, e.g. to boot arguments made for
+    /// a root drive when booting from the initrd instead.
+    pub fn without_root_arg(mut self) -> Self {
+        if let Some(ref boot_args) = self.boot_args {
+            let boot_args = boot_args
+                .split_ascii_whitespace()
+                .filter(|arg| !arg.starts_with("root="))
+                .collect::<Vec<_>>()
+                .join(" ");
+            self.boot_args = Some(boot_args);
+        }
+        self
+    }
+}
+
+impl Validate for BootSource {
+    /// Check that `kernel_image_path` is not empty.
+    fn validate(&self) -> Result<()> {
+        if self.kernel_image_path.as_os_str().is_empty() {
+            return Err(Error::Configuration(
+                "Boot source `kernel_image_path` must not be empty".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::BootSource;
+
+    #[test]
+    fn initrd_only() {
+        let boot_source = BootSource::initrd_only("/path/to/vmlinux", "/path/to/initrd.cpio");
+        assert_eq!(
+            boot_source.initrd_path.as_deref(),
+            Some(Path::new("/path/to/initrd.cpio"))
+        );
+        assert!(!boot_source.boot_args.as_ref().unwrap().contains("root="));
+
+        let mut boot_source = BootSource::new("/path/to/vmlinux");
+        boot_source.boot_args = Some("console=ttyS0 root=/dev/vda rw rootfstype=ext4".into());
+        assert_eq!(
+            boot_source.without_root_arg().boot_args.as_deref(),
+            Some("console=ttyS0 rw rootfstype=ext4")
+        );
+        assert_eq!(
+            BootSource::new("/path/to/vmlinux")
+                .without_root_arg()
+                .boot_args,
+            None
+        );
+    }
+}
@@ -51,7 +51,8 @@ pub enum CPUTemplateString {
 /// The CPU configuration template defines a set of bit maps as modifiers
 /// of flags accessed by register to be disabled/enabled for the microvm.
 /// For advanved users.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct CPUConfig {
     /// Additional KVM capabilities can be added or existing (built-in) capabilities can
     /// be removed from the firecracker checks. To add KVM capability to the checklist specify
@@ -76,7 +77,8 @@ pub struct CPUConfig {
 /// Examples: ["171", "!172"]
 pub type KvmCapabilitiy = String;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct VcpuModifier {
     /// Index into kvm_vcpu_init::features array.
     /// As of Linux kernel 6.4.10, only value 0 is allowed.
@@ -90,7 +92,8 @@ pub struct VcpuModifier {
 }
 
 /// CPUID modifiers. Only for x86_64.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct CpuIdModifier {
     /// CPUID leaf index (or function). Must be a string containing an integer.
     /// Examples: ["0x1", "0x2"]
@@ -109,6 +112,7 @@ pub struct CpuIdModifier {
 
 /// CPUID register modifier
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub struct Modifiers {
     /// CPUID register name
     /// One of ["eax", "ebx", "ecx", "edx"]
@@ -121,6 +125,15 @@ pub struct Modifiers {
     pub bitmap: String,
 }
 
+impl Modifiers {
+    pub fn new<S: Into<String>>(register: ModifierRegisterName, bitmap: S) -> Self {
+        Self {
+            register,
+            bitmap: bitmap.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ModifierRegisterName {
     #[serde(rename = "eax")]
@@ -134,7 +147,8 @@ pub enum ModifierRegisterName {
 }
 
 /// MSR modifiers. Only for x86_64.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct MsrModifier {
     /// MSR address/identifier. Must be a string containing an integer.
     /// Example: ["0x10a"]
@@ -147,7 +161,8 @@ pub struct MsrModifier {
     pub bitmap: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct RegModifier {
     /// ARM register address/identifier. Must be a string containing an integer. See https://docs.kernel.org/virt/kvm/api.html#kvm-set-one-reg
     /// Example: ["0x603000000013c020"]
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use super::{kernel_args::KernelArgs, machine_configuration::MachineConfiguration};
+use crate::{Error, Result};
+
+/// CPU topology hints for the guest.
+///
+/// `firecracker` has no native topology configuration: it always exposes a single
+/// socket to the guest, whose cores have either one thread or, with `smt` enabled
+/// (x86_64 only), two threads. The topology is therefore expressed through the
+/// machine configuration plus kernel boot arguments, and topologies `firecracker`
+/// could not present are rejected with [`Error::FeatureNone`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub struct CpuTopology {
+    /// Number of sockets, must be 1.
+    pub sockets: usize,
+
+    /// Number of cores of each socket.
+    pub cores_per_socket: usize,
+
+    /// Number of threads of each core, either 1 or 2.
+    pub threads_per_core: usize,
+
+    /// Maximum number of CPUs the guest kernel brings up at boot (`maxcpus=`).
+    /// All CPUs are brought up if not set.
+    pub maxcpus: Option<usize>,
+}
+
+impl CpuTopology {
+    /// Single socket topology with `cores_per_socket` cores of `threads_per_core` threads.
+    pub fn new(cores_per_socket: usize, threads_per_core: usize) -> Self {
+        Self {
+            sockets: 1,
+            cores_per_socket,
+            threads_per_core,
+            maxcpus: None,
+        }
+    }
+
+    /// Apply the topology to the machine configuration and the kernel boot arguments.
+    pub fn apply(
+        &self,
+        machine_config: &mut MachineConfiguration,
+        boot_args: &mut KernelArgs,
+    ) -> Result<()> {
+        if self.sockets != 1 {
+            return Err(Error::FeatureNone(
+                "firecracker only exposes a single CPU socket to the guest".into(),
+            ));
+        }
+        if self.threads_per_core != 1 && self.threads_per_core != 2 {
+            return Err(Error::FeatureNone(
+                "firecracker only supports 1 or 2 threads per core".into(),
+            ));
+        }
+        if self.cores_per_socket == 0 {
+            return Err(Error::Configuration(
+                "CPU topology requires at least one core".into(),
+            ));
+        }
+
+        let vcpu_count = self.cores_per_socket * self.threads_per_core;
+        machine_config.vcpu_count = vcpu_count as isize;
+        machine_config.smt = Some(self.threads_per_core == 2);
+
+        if let Some(maxcpus) = self.maxcpus {
+            if maxcpus == 0 || maxcpus > vcpu_count {
+                return Err(Error::Configuration(format!(
+                    "`maxcpus` should be within 1..={vcpu_count}"
+                )));
+            }
+            boot_args
+                .0
+                .insert("maxcpus".into(), Some(maxcpus.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_cpu_topology() {
+        let mut machine_config = MachineConfiguration {
+            cpu_template: None,
+            smt: None,
+            mem_size_mib: 1024,
+            track_dirty_pages: None,
+            vcpu_count: 1,
+            huge_pages: None,
+        };
+        let mut boot_args = KernelArgs::from("console=ttyS0".to_string());
+
+        let topology = CpuTopology {
+            sockets: 1,
+            cores_per_socket: 2,
+            threads_per_core: 2,
+            maxcpus: Some(2),
+        };
+        topology.apply(&mut machine_config, &mut boot_args).unwrap();
+        assert_eq!(machine_config.vcpu_count, 4);
+        assert_eq!(machine_config.smt, Some(true));
+        assert_eq!(boot_args.0.get("maxcpus"), Some(&Some("2".into())));
+
+        let topology = CpuTopology {
+            sockets: 2,
+            ..topology
+        };
+        assert!(matches!(
+            topology.apply(&mut machine_config, &mut boot_args),
+            Err(Error::FeatureNone(_))
+        ));
+    }
+}
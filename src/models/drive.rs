@@ -2,10 +2,15 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use super::rate_limiter::RateLimiter;
+use super::{rate_limiter::RateLimiter, Validate};
+use crate::{Error, Result};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct Drive {
+    // `firecracker` sets up a single queue per virtio-block device and rejects unknown
+    // fields, so there's no queue count to request multi-queue with.
     /// drive id
     /// Required: true
     #[serde(rename = "drive_id")]
@@ -15,6 +20,10 @@ pub struct Drive {
     /// Represents the unique id of the boot partition of this device.
     /// It is optional and it will be taken into account
     /// only if the is_root_device field is true.
+    ///
+    /// To boot from a partition of a whole-disk image, set both `is_root_device`
+    /// and `partuuid`: `firecracker` then passes `root=PARTUUID=<partuuid>` to the
+    /// guest kernel instead of `root=/dev/vda`.
     #[serde(rename = "partuuid", skip_serializing_if = "Option::is_none")]
     pub partuuid: Option<String>,
 
@@ -61,6 +70,55 @@ pub struct Drive {
     pub socket: Option<PathBuf>,
 }
 
+impl Drive {
+    /// Writable, non-root drive `drive_id` backed by `path_on_host`.
+    pub fn new<S: Into<String>, P: Into<PathBuf>>(drive_id: S, path_on_host: P) -> Self {
+        Self {
+            drive_id: drive_id.into(),
+            path_on_host: path_on_host.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Validate for Drive {
+    /// Check that the drive id is not empty, that a virtio-block drive has a
+    /// `path_on_host`, that `partuuid` is only set (and not empty) on the root device,
+    /// and validate the rate limiter (if any).
+    fn validate(&self) -> Result<()> {
+        if self.drive_id.is_empty() {
+            return Err(Error::Configuration(
+                "Drive `drive_id` must not be empty".into(),
+            ));
+        }
+        if self.socket.is_none() && self.path_on_host.as_os_str().is_empty() {
+            return Err(Error::Configuration(format!(
+                "Drive {} `path_on_host` must not be empty",
+                self.drive_id
+            )));
+        }
+        match self.partuuid {
+            Some(ref partuuid) if partuuid.is_empty() => {
+                return Err(Error::Configuration(format!(
+                    "Drive {} `partuuid` must not be empty",
+                    self.drive_id
+                )));
+            }
+            Some(_) if !self.is_root_device => {
+                return Err(Error::Configuration(format!(
+                    "Drive {} `partuuid` is only used with `is_root_device`",
+                    self.drive_id
+                )));
+            }
+            _ => (),
+        }
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.validate()?;
+        }
+        Ok(())
+    }
+}
+
 /// Block device caching strategies, default to "Unsafe".
 /// Firecracker offers the possiblity of choosing the block device caching strategy.
 /// Caching strategy affects the path data written from inside the microVM takes to the host persistent storage.
@@ -105,3 +163,30 @@ pub enum IoEngine {
     #[serde(rename = "Async")]
     Async,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_drive() {
+        assert!(Drive::new("rootfs", "/foo/bar/rootfs.ext4")
+            .validate()
+            .is_ok());
+        assert!(Drive::new("", "/foo/bar/rootfs.ext4").validate().is_err());
+        assert!(Drive::new("rootfs", "").validate().is_err());
+    }
+
+    #[test]
+    fn validate_drive_partuuid() {
+        let mut drive = Drive::new("rootfs", "/foo/bar/disk.img");
+        drive.partuuid = Some("0eaa91a0-01".into());
+        assert!(drive.validate().is_err());
+
+        drive.is_root_device = true;
+        assert!(drive.validate().is_ok());
+
+        drive.partuuid = Some("".into());
+        assert!(drive.validate().is_err());
+    }
+}
@@ -1,10 +1,47 @@
 use serde::{Deserialize, Serialize};
 
-use super::rate_limiter::RateLimiter;
+use super::{rate_limiter::RateLimiter, Validate};
+use crate::Result;
 
 /// Defines an entropy device.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct EntropyDevice {
     #[serde(rename = "rate_limiter")]
     pub rate_limiter: Option<RateLimiter>,
 }
+
+impl Validate for EntropyDevice {
+    /// Validate the rate limiter (if any) of this entropy device.
+    fn validate(&self) -> Result<()> {
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TokenBucket;
+
+    #[test]
+    fn validate_rate_limiter() {
+        let bucket = |size, refill_time| TokenBucket {
+            one_time_burst: None,
+            refill_time,
+            size,
+        };
+
+        let valid = EntropyDevice {
+            rate_limiter: Some(RateLimiter::new(bucket(1024, 100), bucket(10, 100))),
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = EntropyDevice {
+            rate_limiter: Some(RateLimiter::new(bucket(1024, 0), bucket(10, 100))),
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -2,16 +2,45 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct InternalError {
+/// Error body returned by the `firecracker` API along with a 4xx / 5xx status code, e.g.
+/// `{"fault_message":"The drive ID does not exist."}`.
+///
+/// Decoded into [`crate::Error::FirecrackerApiError`], see [`crate::Error::fault`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub struct FirecrackerFault {
     /// A description of the error condition
     /// readOnly: true
     #[serde(rename = "fault_message")]
     pub fault_message: String,
 }
 
-impl Display for InternalError {
+/// Name of [`FirecrackerFault`] in the `firecracker` API specification.
+pub type InternalError = FirecrackerFault;
+
+impl FirecrackerFault {
+    pub fn new<S: Into<String>>(fault_message: S) -> Self {
+        Self {
+            fault_message: fault_message.into(),
+        }
+    }
+}
+
+impl Display for FirecrackerFault {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.fault_message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FirecrackerFault;
+
+    #[test]
+    fn decode_fault() {
+        let fault: FirecrackerFault =
+            serde_json::from_str(r#"{"fault_message":"The drive ID does not exist."}"#).unwrap();
+        assert_eq!(fault, FirecrackerFault::new("The drive ID does not exist."));
+        assert_eq!(fault.to_string(), "The drive ID does not exist.");
+    }
+}
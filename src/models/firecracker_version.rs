@@ -1,9 +1,72 @@
 use serde::{Deserialize, Serialize};
 
+/// `firecracker` version since which the balloon target size is named `amount_mib`
+/// instead of `amount_mb`.
+pub const BALLOON_AMOUNT_MIB_SINCE: (u64, u64, u64) = (0, 24, 0);
+
 /// Describes the Firecracker version.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct FirecrackerVersion {
     /// Firecracker build version.
     #[serde(rename = "firecracker_version")]
     pub firecracker_version: String,
 }
+
+impl FirecrackerVersion {
+    /// Parse the build version into `(major, minor, patch)`,
+    /// ignoring a leading `v` and any pre-release / build suffix.
+    pub fn semver(&self) -> Option<(u64, u64, u64)> {
+        let version = self.firecracker_version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let version = version.split(['-', '+']).next()?;
+        let mut numbers = version.split('.').map(|n| n.parse::<u64>().ok());
+        let version = (numbers.next()??, numbers.next()??, numbers.next()??);
+        match numbers.next() {
+            None => Some(version),
+            Some(_) => None,
+        }
+    }
+
+    /// Whether this `firecracker` expects the balloon target size to be named `amount_mb`.
+    /// Defaults to `false` (i.e. `amount_mib`) if the version cannot be parsed.
+    pub fn legacy_balloon_amount(&self) -> bool {
+        self.semver()
+            .is_some_and(|version| version < BALLOON_AMOUNT_MIB_SINCE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(firecracker_version: &str) -> FirecrackerVersion {
+        FirecrackerVersion {
+            firecracker_version: firecracker_version.into(),
+        }
+    }
+
+    #[test]
+    fn parse_firecracker_version() {
+        assert_eq!(version("1.10.1").semver(), Some((1, 10, 1)));
+        assert_eq!(version("v0.23.0-dev").semver(), Some((0, 23, 0)));
+        assert_eq!(version("unknown").semver(), None);
+
+        assert!(version("0.23.5").legacy_balloon_amount());
+        assert!(!version("0.24.0").legacy_balloon_amount());
+        assert!(!version("1.10.1").legacy_balloon_amount());
+        assert!(!version("unknown").legacy_balloon_amount());
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let json = r#"{"firecracker_version":"1.10.1","build":"abc"}"#;
+        let result = serde_json::from_str::<FirecrackerVersion>(json);
+        if cfg!(feature = "strict-deserialize") {
+            assert!(result.is_err());
+        } else {
+            assert_eq!(result.unwrap(), version("1.10.1"));
+        }
+    }
+}
@@ -1,18 +1,34 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
 use super::*;
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct FullVmConfiguration {
     #[serde(rename = "balloon", skip_serializing_if = "Option::is_none")]
     pub balloon: Option<balloon::Balloon>,
 
     /// Configurations for all block devices.
-    #[serde(rename = "drive", skip_serializing_if = "Option::is_none")]
+    #[serde(
+        rename = "drives",
+        alias = "drive",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub drives: Option<Vec<drive::Drive>>,
 
     #[serde(rename = "boot-source", skip_serializing_if = "Option::is_none")]
     pub boot_source: Option<boot_source::BootSource>,
 
+    /// Path of a JSON custom CPU template (see [`CPUConfig`]), only read from a config file
+    /// by `firecracker` itself: always `null` in the exported configuration, and not applied
+    /// through the API by `Instance::apply_config_file`.
+    #[serde(rename = "cpu-config", skip_serializing_if = "Option::is_none")]
+    pub cpu_config: Option<PathBuf>,
+
     #[serde(rename = "logger", skip_serializing_if = "Option::is_none")]
     pub logger: Option<logger::Logger>,
 
@@ -31,4 +47,157 @@ pub struct FullVmConfiguration {
 
     #[serde(rename = "vsock", skip_serializing_if = "Option::is_none")]
     pub vsock: Option<vsock::Vsock>,
+
+    #[serde(rename = "entropy", skip_serializing_if = "Option::is_none")]
+    pub entropy: Option<entropy_device::EntropyDevice>,
+}
+
+impl FullVmConfiguration {
+    /// Serialize into a JSON config file for `firecracker --config-file`, e.g. to boot
+    /// without the API a VM configured through it (see [`crate::instance::VmConfigBuilder`]).
+    ///
+    /// Unlike the API objects, the config file requires `boot-source` and `drives`
+    /// (written empty if not set).
+    pub fn to_config_json(&self) -> Result<String> {
+        if self.boot_source.is_none() {
+            return Err(Error::Configuration(
+                "Config file requires `boot-source`".into(),
+            ));
+        }
+        let mut config = serde_json::to_value(self)
+            .map_err(|e| Error::Configuration(format!("Fail to serialize config file: {e}")))?;
+        if let Some(config) = config.as_object_mut() {
+            config
+                .entry("drives")
+                .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+        }
+        serde_json::to_string_pretty(&config)
+            .map_err(|e| Error::Configuration(format!("Fail to serialize config file: {e}")))
+    }
+
+    /// The same configuration with the drives sorted by `drive_id` and the network interfaces
+    /// by `iface_id`, and no empty list, so that equal configurations compare equal whatever
+    /// order `firecracker` or the caller listed the devices in, e.g. the desired configuration
+    /// and the one exported by [`crate::instance::Instance::get_export_vm_config`].
+    pub fn normalized(&self) -> FullVmConfiguration {
+        let mut config = self.clone();
+        if let Some(ref mut drives) = config.drives {
+            drives.sort_by(|a, b| a.drive_id.cmp(&b.drive_id));
+        }
+        if let Some(ref mut network_interfaces) = config.network_interfaces {
+            network_interfaces.sort_by(|a, b| a.iface_id.cmp(&b.iface_id));
+        }
+        config.drives = config.drives.filter(|x| !x.is_empty());
+        config.network_interfaces = config.network_interfaces.filter(|x| !x.is_empty());
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_config_json() {
+        let mut config = FullVmConfiguration::default();
+        assert!(config.to_config_json().is_err());
+
+        config.boot_source = Some(BootSource::new("/path/to/vmlinux"));
+        config.machine_config = Some(MachineConfiguration::new(1, 256));
+        let json = config.to_config_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["drives"], serde_json::json!([]));
+        assert_eq!(
+            value["boot-source"]["kernel_image_path"],
+            "/path/to/vmlinux"
+        );
+        assert_eq!(value["machine-config"]["mem_size_mib"], 256);
+        assert!(value.get("network-interfaces").is_none());
+
+        let mut read_back: FullVmConfiguration = serde_json::from_str(&json).unwrap();
+        assert_eq!(read_back.drives.take(), Some(Vec::new()));
+        assert_eq!(read_back, config);
+    }
+
+    #[test]
+    fn normalized() {
+        let desired = FullVmConfiguration {
+            drives: Some(vec![
+                Drive::new("rootfs", "/rootfs"),
+                Drive::new("data", "/data"),
+            ]),
+            network_interfaces: Some(Vec::new()),
+            ..Default::default()
+        };
+        let actual = FullVmConfiguration {
+            drives: Some(vec![
+                Drive::new("data", "/data"),
+                Drive::new("rootfs", "/rootfs"),
+            ]),
+            ..Default::default()
+        };
+
+        assert_ne!(desired, actual);
+        assert_eq!(desired.normalized(), actual.normalized());
+        let drives = actual.normalized().drives.unwrap();
+        assert_eq!(drives[0].drive_id, "data");
+        assert_eq!(actual.normalized().network_interfaces, None);
+    }
+
+    #[test]
+    fn decode_exported() {
+        // `GET /vm/config` of firecracker v1.10, every section listed even if not configured
+        let body = r#"{
+            "balloon": {"amount_mib": 1, "deflate_on_oom": false, "stats_polling_interval_s": 0},
+            "drives": [{
+                "drive_id": "rootfs",
+                "partuuid": null,
+                "is_root_device": true,
+                "cache_type": "Unsafe",
+                "is_read_only": false,
+                "path_on_host": "/rootfs.ext4",
+                "rate_limiter": null,
+                "io_engine": "Sync",
+                "socket": null
+            }],
+            "boot-source": {
+                "kernel_image_path": "/vmlinux",
+                "initrd_path": null,
+                "boot_args": "console=ttyS0 reboot=k panic=1"
+            },
+            "cpu-config": null,
+            "logger": null,
+            "machine-config": {
+                "vcpu_count": 2,
+                "mem_size_mib": 256,
+                "smt": false,
+                "track_dirty_pages": false,
+                "huge_pages": "None"
+            },
+            "metrics": null,
+            "mmds-config": {
+                "version": "V2",
+                "ipv4_address": "169.254.169.250",
+                "network_interfaces": ["eth0"]
+            },
+            "network-interfaces": [{
+                "iface_id": "eth0",
+                "host_dev_name": "tap0",
+                "guest_mac": "06:00:00:00:00:01",
+                "rx_rate_limiter": null,
+                "tx_rate_limiter": null
+            }],
+            "vsock": {"guest_cid": 3, "uds_path": "/v.sock"},
+            "entropy": {"rate_limiter": null}
+        }"#;
+        let config: FullVmConfiguration = serde_json::from_str(body).unwrap();
+        assert_eq!(config.cpu_config, None);
+        assert_eq!(config.entropy, Some(EntropyDevice::default()));
+        assert_eq!(config.machine_config.unwrap().mem_size_mib, 256);
+        assert_eq!(config.drives.unwrap()[0].drive_id, "rootfs");
+        assert_eq!(
+            config.mmds_config.unwrap().ipv4_address,
+            Some("169.254.169.250".parse().unwrap())
+        );
+    }
 }
@@ -7,11 +7,18 @@ pub enum ActionType {
     #[serde(rename = "InstanceStart")]
     InstanceStart,
     #[serde(rename = "SendCtrlAltDel")]
-    SendCtrlAtlDel,
+    SendCtrlAltDel,
+}
+
+impl ActionType {
+    #[deprecated(note = "misspelled, use `ActionType::SendCtrlAltDel`")]
+    #[allow(non_upper_case_globals)]
+    pub const SendCtrlAtlDel: ActionType = ActionType::SendCtrlAltDel;
 }
 
 /// Variant wrapper containing the real action.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub struct InstanceActionInfo {
     /// Enumeration indicating what type of action is contained in the payload
     /// Required: true
@@ -19,3 +26,47 @@ pub struct InstanceActionInfo {
     #[serde(rename = "action_type")]
     pub action_type: ActionType,
 }
+
+impl InstanceActionInfo {
+    pub fn new(action_type: ActionType) -> Self {
+        Self { action_type }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_instance_action_info() {
+        // request bodies of `PUT /actions` as documented by `firecracker`
+        for (action_type, body) in [
+            (
+                ActionType::FlushMetrics,
+                r#"{"action_type":"FlushMetrics"}"#,
+            ),
+            (
+                ActionType::InstanceStart,
+                r#"{"action_type":"InstanceStart"}"#,
+            ),
+            (
+                ActionType::SendCtrlAltDel,
+                r#"{"action_type":"SendCtrlAltDel"}"#,
+            ),
+        ] {
+            let action = InstanceActionInfo::new(action_type);
+            assert_eq!(serde_json::to_string(&action).unwrap(), body);
+            assert_eq!(
+                serde_json::from_str::<InstanceActionInfo>(body).unwrap(),
+                action
+            );
+        }
+
+        #[allow(deprecated)]
+        let action_type = ActionType::SendCtrlAtlDel;
+        assert_eq!(
+            serde_json::to_string(&action_type).unwrap(),
+            r#""SendCtrlAltDel""#
+        );
+    }
+}
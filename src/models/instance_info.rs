@@ -2,6 +2,10 @@ use serde::{Deserialize, Serialize};
 
 /// The current detailed state (Not started, Running, Paused) of the Firecracker instance.
 /// This value is read-only for the control-plane.
+///
+/// A state unknown to this SDK (e.g. added by a newer `firecracker`) is decoded as
+/// [`State::Unknown`] rather than failing [`InstanceInfo`] decoding, see
+/// [`InstanceInfo::raw_state`].
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum State {
     #[serde(rename = "Not started")]
@@ -10,10 +14,14 @@ pub enum State {
     Running,
     #[serde(rename = "Paused")]
     Paused,
+    #[serde(other)]
+    Unknown,
 }
 
 /// Describes MicroVM instance information.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+#[serde(from = "RawInstanceInfo", into = "RawInstanceInfo")]
 pub struct InstanceInfo {
     /// Application name.
     /// Required: true
@@ -36,4 +44,80 @@ pub struct InstanceInfo {
     /// Required: true
     #[serde(rename = "vmm_version")]
     pub vmm_version: String,
+
+    // `state` as sent by `firecracker`
+    raw_state: String,
+}
+
+impl InstanceInfo {
+    /// The state as sent by `firecracker`, e.g. to tell which one was decoded as
+    /// [`State::Unknown`].
+    pub fn raw_state(&self) -> &str {
+        &self.raw_state
+    }
+}
+
+/// [`InstanceInfo`] as sent by `firecracker`, with the state not decoded yet.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
+struct RawInstanceInfo {
+    app_name: String,
+    id: String,
+    state: String,
+    vmm_version: String,
+}
+
+impl From<RawInstanceInfo> for InstanceInfo {
+    fn from(raw: RawInstanceInfo) -> Self {
+        // a string always deserializes into a `State`, unknown ones into `State::Unknown`
+        let state = serde_json::from_value(serde_json::Value::String(raw.state.clone()))
+            .unwrap_or(State::Unknown);
+        Self {
+            app_name: raw.app_name,
+            id: raw.id,
+            state,
+            vmm_version: raw.vmm_version,
+            raw_state: raw.state,
+        }
+    }
+}
+
+impl From<InstanceInfo> for RawInstanceInfo {
+    fn from(info: InstanceInfo) -> Self {
+        Self {
+            app_name: info.app_name,
+            id: info.id,
+            state: info.raw_state,
+            vmm_version: info.vmm_version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_state() {
+        let state = |raw: &str| serde_json::from_str::<State>(raw).unwrap();
+        assert_eq!(state(r#""Not started""#), State::NotStarted);
+        assert_eq!(state(r#""Running""#), State::Running);
+        assert_eq!(state(r#""Paused""#), State::Paused);
+        assert_eq!(state(r#""Halted""#), State::Unknown);
+
+        let info: InstanceInfo = serde_json::from_str(
+            r#"{"app_name":"Firecracker","id":"vm0","state":"Running","vmm_version":"1.10.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(info.state, State::Running);
+        assert_eq!(info.raw_state(), "Running");
+
+        // the unknown state is kept, and sent back as it was
+        let raw =
+            r#"{"app_name":"Firecracker","id":"vm0","state":"Halted","vmm_version":"1.10.1"}"#;
+        let info: InstanceInfo = serde_json::from_str(raw).unwrap();
+        assert_eq!(info.state, State::Unknown);
+        assert_eq!(info.raw_state(), "Halted");
+        assert_eq!(serde_json::to_string(&info).unwrap(), raw);
+    }
 }
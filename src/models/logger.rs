@@ -22,7 +22,9 @@ pub enum LogLevel {
 /// Describes the configuration option for the logging capability.
 /// logger can only be constructed once
 /// and cannot update after configuration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct Logger {
     /// Set the level. The possible values are case-insensitive.
     /// Enum: [Error Warning Info Debug]
@@ -46,3 +48,61 @@ pub struct Logger {
     #[serde(rename = "module", skip_serializing_if = "Option::is_none")]
     pub module: Option<String>,
 }
+
+impl Logger {
+    /// Logger writing to `log_path` with `firecracker` default settings.
+    pub fn new<P: Into<PathBuf>>(log_path: P) -> Self {
+        Self {
+            log_path: log_path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn level(mut self, level: LogLevel) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Prefix every log line with its level, as the `--show-level` flag of `firecracker`.
+    pub fn show_level(mut self, show_level: bool) -> Self {
+        self.show_level = Some(show_level);
+        self
+    }
+
+    /// Prefix every log line with its file and line, as the `--show-log-origin` flag of
+    /// `firecracker`.
+    pub fn show_log_origin(mut self, show_log_origin: bool) -> Self {
+        self.show_log_origin = Some(show_log_origin);
+        self
+    }
+
+    pub fn module<S: Into<String>>(mut self, module: S) -> Self {
+        self.module = Some(module.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_logger() {
+        let logger = Logger::new("/tmp/firecracker.log");
+        assert_eq!(
+            serde_json::to_string(&logger).unwrap(),
+            r#"{"log_path":"/tmp/firecracker.log"}"#
+        );
+
+        let logger = logger
+            .level(LogLevel::Warning)
+            .show_level(true)
+            .show_log_origin(false);
+        let json = serde_json::to_string(&logger).unwrap();
+        assert_eq!(
+            json,
+            r#"{"level":"Warning","log_path":"/tmp/firecracker.log","show_level":true,"show_log_origin":false}"#
+        );
+        assert_eq!(serde_json::from_str::<Logger>(&json).unwrap(), logger);
+    }
+}
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-use super::cpu_template::CPUTemplate;
+use super::{cpu_template::CPUTemplate, Validate};
+use crate::{Error, Result};
 
 /// # Example
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct MachineConfiguration {
     /// cpu template
     #[serde(rename = "cpu_template", skip_serializing_if = "Option::is_none")]
@@ -41,6 +44,44 @@ pub struct MachineConfiguration {
     pub huge_pages: Option<HugePageOption>,
 }
 
+impl MachineConfiguration {
+    /// Machine configuration with `vcpu_count` vCPUs and `mem_size_mib` MiB of memory,
+    /// leaving the optional fields to `firecracker` defaults.
+    pub fn new(vcpu_count: isize, mem_size_mib: isize) -> Self {
+        Self {
+            vcpu_count,
+            mem_size_mib,
+            ..Default::default()
+        }
+    }
+}
+
+impl Validate for MachineConfiguration {
+    /// Check that `vcpu_count` is within 1 to 32, and either 1 or even with `smt` enabled,
+    /// and that `mem_size_mib` is positive.
+    fn validate(&self) -> Result<()> {
+        if !(1..=32).contains(&self.vcpu_count) {
+            return Err(Error::Configuration(format!(
+                "Machine configuration `vcpu_count` {} is not within 1 to 32",
+                self.vcpu_count
+            )));
+        }
+        if self.smt == Some(true) && self.vcpu_count != 1 && self.vcpu_count % 2 != 0 {
+            return Err(Error::Configuration(format!(
+                "Machine configuration `vcpu_count` {} must be 1 or even with `smt`",
+                self.vcpu_count
+            )));
+        }
+        if self.mem_size_mib <= 0 {
+            return Err(Error::Configuration(format!(
+                "Machine configuration `mem_size_mib` {} must be positive",
+                self.mem_size_mib
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum HugePageOption {
     #[serde(rename = "None")]
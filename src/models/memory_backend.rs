@@ -2,7 +2,11 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use super::machine_configuration::HugePageOption;
+use crate::{Error, Result};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub struct MemoryBackend {
     #[serde(rename = "backend_type")]
     pub backend_type: BackendType,
@@ -15,6 +19,15 @@ pub struct MemoryBackend {
     pub backend_path: PathBuf,
 }
 
+impl MemoryBackend {
+    pub fn new<P: Into<PathBuf>>(backend_type: BackendType, backend_path: P) -> Self {
+        Self {
+            backend_type,
+            backend_path: backend_path.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum BackendType {
     #[serde(rename = "File")]
@@ -22,3 +35,101 @@ pub enum BackendType {
     #[serde(rename = "Uffd")]
     Uffd,
 }
+
+/// How the guest memory is mapped on the host.
+///
+/// - [`GuestMemoryBacking::AnonymousPrivate`]: anonymous private memory, the only backing of a
+///   VM booted from scratch, and the one that can sit on huge pages
+///   (`MachineConfiguration::huge_pages`). Restoring a snapshot this way needs a UFFD handler
+///   ([`BackendType::Uffd`]) to populate the memory, which allows lazy loading driven by the
+///   handler, at the cost of a page fault round trip to it.
+/// - [`GuestMemoryBacking::FileShared`]: guest memory mapped from the memory file of a snapshot
+///   ([`BackendType::File`]). Restoring is fast since pages are only read on first access and
+///   shared with the host page cache until the guest writes them, but the memory file must be
+///   kept for the lifetime of the VM, and snapshots of VMs on huge pages cannot be restored
+///   this way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GuestMemoryBacking {
+    #[default]
+    AnonymousPrivate,
+    FileShared,
+}
+
+impl GuestMemoryBacking {
+    /// Memory backend restoring a snapshot with this backing. `backend_path` is the memory file
+    /// of the snapshot for [`GuestMemoryBacking::FileShared`], or the socket of the UFFD
+    /// handler for [`GuestMemoryBacking::AnonymousPrivate`].
+    pub fn mem_backend<P: Into<PathBuf>>(self, backend_path: P) -> MemoryBackend {
+        let backend_type = match self {
+            GuestMemoryBacking::AnonymousPrivate => BackendType::Uffd,
+            GuestMemoryBacking::FileShared => BackendType::File,
+        };
+        MemoryBackend::new(backend_type, backend_path)
+    }
+
+    /// Check that this backing is possible with the `huge_pages` of the machine configuration
+    /// and the `mem_backend` a snapshot is loaded with ([`None`] when booting from scratch).
+    pub fn validate(
+        self,
+        huge_pages: Option<&HugePageOption>,
+        mem_backend: Option<&MemoryBackend>,
+    ) -> Result<()> {
+        if mem_backend.is_some_and(|mem_backend| mem_backend.backend_path.as_os_str().is_empty()) {
+            return Err(Error::Configuration(
+                "memory backend `backend_path` must not be empty".into(),
+            ));
+        }
+        let backend_type = mem_backend.map(|mem_backend| mem_backend.backend_type);
+        match (self, backend_type) {
+            (GuestMemoryBacking::AnonymousPrivate, None | Some(BackendType::Uffd)) => Ok(()),
+            (GuestMemoryBacking::AnonymousPrivate, Some(BackendType::File)) => {
+                Err(Error::Configuration(
+                    "a `File` memory backend maps the guest memory file-backed shared".into(),
+                ))
+            }
+            (GuestMemoryBacking::FileShared, Some(BackendType::File)) => match huge_pages {
+                Some(HugePageOption::HugePage2M) => Err(Error::Configuration(
+                    "guest memory on huge pages cannot be file-backed shared, use a `Uffd` memory backend".into(),
+                )),
+                Some(HugePageOption::None) | None => Ok(()),
+            },
+            (GuestMemoryBacking::FileShared, None | Some(BackendType::Uffd)) => {
+                Err(Error::Configuration(
+                    "file-backed shared guest memory requires a `File` memory backend".into(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackendType, GuestMemoryBacking};
+    use crate::models::HugePageOption;
+
+    #[test]
+    fn validate_guest_memory_backing() {
+        let anonymous = GuestMemoryBacking::AnonymousPrivate;
+        let file_shared = GuestMemoryBacking::FileShared;
+        let huge_pages = Some(&HugePageOption::HugePage2M);
+
+        // booting from scratch
+        assert!(anonymous.validate(huge_pages, None).is_ok());
+        assert!(file_shared.validate(None, None).is_err());
+
+        // restoring a snapshot
+        let uffd = anonymous.mem_backend("/tmp/uffd.socket");
+        assert_eq!(uffd.backend_type, BackendType::Uffd);
+        assert!(anonymous.validate(huge_pages, Some(&uffd)).is_ok());
+        assert!(file_shared.validate(None, Some(&uffd)).is_err());
+
+        let file = file_shared.mem_backend("/tmp/snapshot.mem");
+        assert_eq!(file.backend_type, BackendType::File);
+        assert!(file_shared.validate(None, Some(&file)).is_ok());
+        assert!(file_shared.validate(huge_pages, Some(&file)).is_err());
+        assert!(anonymous.validate(None, Some(&file)).is_err());
+
+        let empty = file_shared.mem_backend("");
+        assert!(file_shared.validate(None, Some(&empty)).is_err());
+    }
+}
@@ -3,10 +3,20 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
 /// Describes the configuration option for the metrics capability.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct Metrics {
     /// Path to the named pipe or file where the JSON-formatted metrics are flushed.
     /// Required: true
     #[serde(rename = "metrics_path")]
     pub metrics_path: PathBuf,
 }
+
+impl Metrics {
+    pub fn new<P: Into<PathBuf>>(metrics_path: P) -> Self {
+        Self {
+            metrics_path: metrics_path.into(),
+        }
+    }
+}
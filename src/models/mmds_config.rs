@@ -2,8 +2,15 @@ use std::net::Ipv4Addr;
 
 use serde::{Deserialize, Serialize};
 
+use super::Validate;
+use crate::{Error, Result};
+
+pub const DEFAULT_MMDS_IPV4_ADDRESS: Ipv4Addr = Ipv4Addr::new(169, 254, 169, 254);
+
 /// Defines the MMDS configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct MmdsConfig {
     /// MMDS version to be used. V1 is deprecated, V2 is recommended.
     #[serde(rename = "version", skip_serializing_if = "Option::is_none")]
@@ -25,13 +32,76 @@ pub struct MmdsConfig {
     pub network_interfaces: Vec<String>,
 }
 
+impl MmdsConfig {
+    /// MMDS configuration using the recommended version V2 and the
+    /// default address `169.254.169.254`.
+    pub fn new(network_interfaces: Vec<String>) -> Self {
+        Self {
+            version: Some(MmdsConfigVersion::V2),
+            ipv4_address: Some(DEFAULT_MMDS_IPV4_ADDRESS),
+            network_interfaces,
+        }
+    }
+}
+
+impl Validate for MmdsConfig {
+    /// Check that `ipv4_address` (if provided) is within the range accepted by
+    /// `firecracker`, i.e. 169.254.[1-254].[0-255].
+    fn validate(&self) -> Result<()> {
+        if let Some(ipv4_address) = self.ipv4_address {
+            let [a, b, c, _] = ipv4_address.octets();
+            if a != 169 || b != 254 || c == 0 || c == 255 {
+                return Err(Error::Configuration(format!(
+                    "MMDS `ipv4_address` {ipv4_address} is not a valid link-local address"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// MMDS version, also exported as [`MmdsVersion`](crate::models::MmdsVersion).
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub enum MmdsConfigVersion {
+    /// Unauthenticated `GET` requests, default of `firecracker` when `version` is omitted.
     #[default]
     #[serde(rename = "V1")]
     V1,
+    /// Session-oriented: the guest first obtains a token with `PUT /latest/api/token`
+    /// and passes it in the `X-metadata-token` header of its `GET` requests.
     #[serde(rename = "V2")]
     V2,
 }
 
 pub type MmdsContentsObject = String;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_mmds_config_version() {
+        let mut config = MmdsConfig::new(vec!["eth0".into()]);
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"version":"V2","ipv4_address":"169.254.169.254","network_interfaces":["eth0"]}"#
+        );
+
+        config.version = Some(MmdsConfigVersion::V1);
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains(r#""version":"V1""#));
+        assert_eq!(serde_json::from_str::<MmdsConfig>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn validate_mmds_ipv4_address() {
+        let mut config = MmdsConfig::new(vec![]);
+        assert!(config.validate().is_ok());
+
+        config.ipv4_address = Some(Ipv4Addr::new(169, 254, 0, 1));
+        assert!(config.validate().is_err());
+
+        config.ipv4_address = Some(Ipv4Addr::new(10, 0, 0, 1));
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,9 +1,20 @@
+//! Typed models of the `firecracker` API.
+//!
+//! `firecracker` keeps adding fields to its API objects, so every model struct is
+//! `#[non_exhaustive]`: construct it with `new` (which takes the required fields) or
+//! `Default::default()`, then assign the optional fields. New fields can then be added
+//! without breaking callers.
+//!
+//! Every model type (and enum) is re-exported here, so `use firecracker_rs_sdk::models::*`
+//! brings in everything needed regardless of which submodule defines it.
+
 pub mod balloon;
 pub mod balloon_stats;
 pub mod balloon_stats_update;
 pub mod balloon_update;
 pub mod boot_source;
 pub mod cpu_template;
+pub mod cpu_topology;
 pub mod drive;
 pub mod entropy_device;
 pub mod error;
@@ -18,43 +29,53 @@ pub mod memory_backend;
 pub mod metrics;
 pub mod mmds_config;
 pub mod network_interface;
+pub mod network_override;
 pub mod partial_drive;
 pub mod partial_network_interface;
 pub mod rate_limiter;
 pub mod snapshot_create_params;
 pub mod snapshot_load_params;
 pub mod token_bucket;
+pub mod validate;
 pub mod vm;
 pub mod vsock;
 
-pub use balloon::Balloon;
+pub use balloon::{Balloon, LegacyBalloon};
 pub use balloon_stats::BalloonStats;
 pub use balloon_stats_update::BalloonStatsUpdate;
-pub use balloon_update::BalloonUpdate;
+pub use balloon_update::{BalloonUpdate, LegacyBalloonUpdate};
 pub use boot_source::BootSource;
-pub use cpu_template::{CPUConfig, CPUTemplate, CPUTemplateString, CpuIdModifier};
+pub use cpu_template::{
+    CPUConfig, CPUTemplate, CPUTemplateString, CpuIdModifier, KvmCapabilitiy, ModifierRegisterName,
+    Modifiers, MsrModifier, RegModifier, VcpuModifier,
+};
+pub use cpu_topology::CpuTopology;
 pub use drive::{CacheType, Drive, IoEngine};
 pub use entropy_device::EntropyDevice;
-pub use error::InternalError;
-pub use firecracker_version::FirecrackerVersion;
+pub use error::{FirecrackerFault, InternalError};
+pub use firecracker_version::{FirecrackerVersion, BALLOON_AMOUNT_MIB_SINCE};
 pub use full_vm_configuration::FullVmConfiguration;
 pub use instance_action_info::{ActionType, InstanceActionInfo};
 pub use instance_info::{InstanceInfo, State as InstanceState};
 pub use kernel_args::KernelArgs;
 pub use logger::{LogLevel, Logger};
 pub use machine_configuration::{HugePageOption, MachineConfiguration};
-pub use memory_backend::{BackendType, MemoryBackend};
+pub use memory_backend::{BackendType, GuestMemoryBacking, MemoryBackend};
 pub use metrics::Metrics;
-pub use mmds_config::{MmdsConfig, MmdsContentsObject, MmdsConfigVersion};
-pub use network_interface::NetworkInterface;
+pub use mmds_config::{
+    MmdsConfig, MmdsConfigVersion, MmdsConfigVersion as MmdsVersion, MmdsContentsObject,
+    DEFAULT_MMDS_IPV4_ADDRESS,
+};
+pub use network_interface::{NetworkInterface, NetworkInterfaceBuilder};
+pub use network_override::NetworkOverride;
 pub use partial_drive::PartialDrive;
 pub use partial_network_interface::PartialNetworkInterface;
 pub use rate_limiter::{RateLimiter, RateLimiterSet};
 pub use snapshot_create_params::{SnapshotCreateParams, SnapshotType};
 pub use snapshot_load_params::SnapshotLoadParams;
 pub use token_bucket::TokenBucket;
+pub use validate::Validate;
 pub use vm::{State as VmState, Vm, VM_STATE_PAUSED, VM_STATE_RESUMED};
 pub use vsock::Vsock;
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct Empty;
+pub use crate::events::Empty;
@@ -2,18 +2,18 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use super::rate_limiter;
+use super::{rate_limiter, token_bucket::TokenBucket, Validate};
+use crate::{Error, Result};
 
 /// Defines a network interface.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct NetworkInterface {
-    /// If this field is set, the device model will reply to
-    /// HTTP GET requests sent to the MMDS address via this interface.
-    /// In this case, both ARP requests for 169.254.169.254 and TCP
-    /// segments heading to the same address are intercepted by the
-    /// device model, and do not reach the associated TAP device.
-    // pub allow_mmds_requests: Option<bool>,
-
+    // `allow_mmds_requests` was removed by `firecracker`, MMDS is enabled for
+    // network interfaces via `MmdsConfig::network_interfaces` instead.
+    // There's no queue count either: `firecracker` gives each interface a single RX / TX
+    // queue pair and rejects unknown fields, so multi-queue virtio-net can't be requested.
     /// guest mac
     #[serde(rename = "guest_mac", skip_serializing_if = "Option::is_none")]
     pub guest_mac: Option<String>,
@@ -36,3 +36,240 @@ pub struct NetworkInterface {
     #[serde(rename = "tx_rate_limiter", skip_serializing_if = "Option::is_none")]
     pub tx_rate_limiter: Option<rate_limiter::RateLimiter>,
 }
+
+impl NetworkInterface {
+    /// Network interface `iface_id` backed by the host TAP device `host_dev_name`,
+    /// without guest MAC address nor rate limiters.
+    pub fn new<S: Into<String>, P: Into<PathBuf>>(iface_id: S, host_dev_name: P) -> Result<Self> {
+        let network_interface = Self {
+            guest_mac: None,
+            host_dev_name: host_dev_name.into(),
+            iface_id: iface_id.into(),
+            rx_rate_limiter: None,
+            tx_rate_limiter: None,
+        };
+        network_interface.validate()?;
+        Ok(network_interface)
+    }
+
+    /// Builder of a network interface, see [`NetworkInterfaceBuilder`].
+    pub fn builder() -> NetworkInterfaceBuilder {
+        NetworkInterfaceBuilder::default()
+    }
+
+    pub fn guest_mac<S: Into<String>>(mut self, guest_mac: S) -> Self {
+        self.guest_mac = Some(guest_mac.into());
+        self
+    }
+
+    pub fn rx_rate_limiter(mut self, rx_rate_limiter: rate_limiter::RateLimiter) -> Self {
+        self.rx_rate_limiter = Some(rx_rate_limiter);
+        self
+    }
+
+    pub fn tx_rate_limiter(mut self, tx_rate_limiter: rate_limiter::RateLimiter) -> Self {
+        self.tx_rate_limiter = Some(tx_rate_limiter);
+        self
+    }
+}
+
+/// Builder of a [`NetworkInterface`], building the nested rate limiters from plain limits.
+///
+/// ```
+/// # use firecracker_rs_sdk::models::NetworkInterface;
+/// let network_interface = NetworkInterface::builder()
+///     .iface_id("eth0")
+///     .host_dev_name("tap0")
+///     .guest_mac("06:00:AC:10:00:02")
+///     .with_bandwidth(10 * 1024 * 1024, 1000)
+///     .build()
+///     .unwrap();
+/// assert!(network_interface.rx_rate_limiter.is_some());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NetworkInterfaceBuilder {
+    iface_id: Option<String>,
+    host_dev_name: Option<PathBuf>,
+    guest_mac: Option<String>,
+    rx_rate_limiter: Option<rate_limiter::RateLimiter>,
+    tx_rate_limiter: Option<rate_limiter::RateLimiter>,
+}
+
+impl NetworkInterfaceBuilder {
+    pub fn iface_id<S: Into<String>>(mut self, iface_id: S) -> Self {
+        self.iface_id = Some(iface_id.into());
+        self
+    }
+
+    pub fn host_dev_name<P: Into<PathBuf>>(mut self, host_dev_name: P) -> Self {
+        self.host_dev_name = Some(host_dev_name.into());
+        self
+    }
+
+    pub fn guest_mac<S: Into<String>>(mut self, guest_mac: S) -> Self {
+        self.guest_mac = Some(guest_mac.into());
+        self
+    }
+
+    pub fn rx_rate_limiter(mut self, rx_rate_limiter: rate_limiter::RateLimiter) -> Self {
+        self.rx_rate_limiter = Some(rx_rate_limiter);
+        self
+    }
+
+    pub fn tx_rate_limiter(mut self, tx_rate_limiter: rate_limiter::RateLimiter) -> Self {
+        self.tx_rate_limiter = Some(tx_rate_limiter);
+        self
+    }
+
+    /// Limit both directions to `size` bytes refilled every `refill_time_ms` milliseconds.
+    pub fn with_bandwidth(self, size: u64, refill_time_ms: u64) -> Self {
+        self.rx_bandwidth(size, refill_time_ms)
+            .tx_bandwidth(size, refill_time_ms)
+    }
+
+    /// Limit received bytes to `size` refilled every `refill_time_ms` milliseconds,
+    /// keeping the ops limit of the rx rate limiter (if any).
+    pub fn rx_bandwidth(mut self, size: u64, refill_time_ms: u64) -> Self {
+        self.rx_rate_limiter
+            .get_or_insert_with(Default::default)
+            .bandwidth = Some(TokenBucket::new(size, refill_time_ms));
+        self
+    }
+
+    /// Limit transmitted bytes to `size` refilled every `refill_time_ms` milliseconds,
+    /// keeping the ops limit of the tx rate limiter (if any).
+    pub fn tx_bandwidth(mut self, size: u64, refill_time_ms: u64) -> Self {
+        self.tx_rate_limiter
+            .get_or_insert_with(Default::default)
+            .bandwidth = Some(TokenBucket::new(size, refill_time_ms));
+        self
+    }
+
+    /// Limit both directions to `size` operations refilled every `refill_time_ms` milliseconds.
+    pub fn with_ops(mut self, size: u64, refill_time_ms: u64) -> Self {
+        for rate_limiter in [&mut self.rx_rate_limiter, &mut self.tx_rate_limiter] {
+            rate_limiter.get_or_insert_with(Default::default).ops =
+                Some(TokenBucket::new(size, refill_time_ms));
+        }
+        self
+    }
+
+    /// Build the network interface, failing if `iface_id` or `host_dev_name` is not set
+    /// or the result doesn't pass [`Validate`].
+    pub fn build(self) -> Result<NetworkInterface> {
+        let Some(iface_id) = self.iface_id else {
+            return Err(Error::Configuration(
+                "Network interface `iface_id` must be set".into(),
+            ));
+        };
+        let Some(host_dev_name) = self.host_dev_name else {
+            return Err(Error::Configuration(format!(
+                "Network interface {iface_id} `host_dev_name` must be set"
+            )));
+        };
+        let network_interface = NetworkInterface {
+            guest_mac: self.guest_mac,
+            host_dev_name,
+            iface_id,
+            rx_rate_limiter: self.rx_rate_limiter,
+            tx_rate_limiter: self.tx_rate_limiter,
+        };
+        network_interface.validate()?;
+        Ok(network_interface)
+    }
+}
+
+impl Validate for NetworkInterface {
+    /// Check that the interface id and host device name are not empty,
+    /// and validate the rate limiters (if any).
+    fn validate(&self) -> Result<()> {
+        if self.iface_id.is_empty() {
+            return Err(Error::Configuration(
+                "Network interface `iface_id` must not be empty".into(),
+            ));
+        }
+        if self.host_dev_name.as_os_str().is_empty() {
+            return Err(Error::Configuration(format!(
+                "Network interface {} `host_dev_name` must not be empty",
+                self.iface_id
+            )));
+        }
+        if let Some(ref rx_rate_limiter) = self.rx_rate_limiter {
+            rx_rate_limiter.validate()?;
+        }
+        if let Some(ref tx_rate_limiter) = self.tx_rate_limiter {
+            tx_rate_limiter.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_network_interface() {
+        let network_interface = NetworkInterface::new("eth0", "tap0").unwrap();
+        // `firecracker` may reject a `null` MAC address, so the key must be omitted
+        assert_eq!(
+            serde_json::to_string(&network_interface).unwrap(),
+            r#"{"host_dev_name":"tap0","iface_id":"eth0"}"#
+        );
+
+        let network_interface = network_interface.guest_mac("06:00:AC:10:00:02");
+        let json = serde_json::to_string(&network_interface).unwrap();
+        assert!(json.contains(r#""guest_mac":"06:00:AC:10:00:02""#));
+        assert_eq!(
+            serde_json::from_str::<NetworkInterface>(&json).unwrap(),
+            network_interface
+        );
+    }
+
+    #[test]
+    fn build_network_interface() {
+        assert!(NetworkInterface::builder()
+            .host_dev_name("tap0")
+            .build()
+            .is_err());
+        assert!(NetworkInterface::builder()
+            .iface_id("eth0")
+            .build()
+            .is_err());
+        assert_eq!(
+            NetworkInterface::builder()
+                .iface_id("eth0")
+                .host_dev_name("tap0")
+                .build()
+                .unwrap(),
+            NetworkInterface::new("eth0", "tap0").unwrap()
+        );
+
+        let network_interface = NetworkInterface::builder()
+            .iface_id("eth0")
+            .host_dev_name("tap0")
+            .with_ops(100, 1000)
+            .rx_bandwidth(1024, 100)
+            .build()
+            .unwrap();
+        let rx_rate_limiter = network_interface.rx_rate_limiter.unwrap();
+        assert_eq!(rx_rate_limiter.bandwidth, Some(TokenBucket::new(1024, 100)));
+        assert_eq!(rx_rate_limiter.ops, Some(TokenBucket::new(100, 1000)));
+        let tx_rate_limiter = network_interface.tx_rate_limiter.unwrap();
+        assert_eq!(tx_rate_limiter.bandwidth, None);
+
+        // zero-sized bucket rejected by validation
+        assert!(NetworkInterface::builder()
+            .iface_id("eth0")
+            .host_dev_name("tap0")
+            .with_bandwidth(0, 100)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn validate_network_interface() {
+        assert!(NetworkInterface::new("", "tap0").is_err());
+        assert!(NetworkInterface::new("eth0", "").is_err());
+    }
+}
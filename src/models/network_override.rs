@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Allows for changing the backing TAP device of a network interface
+/// during snapshot restore.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub struct NetworkOverride {
+    /// The name of the interface to modify.
+    /// Required: true
+    #[serde(rename = "iface_id")]
+    pub iface_id: String,
+
+    /// The new host device of the interface.
+    /// Required: true
+    #[serde(rename = "host_dev_name")]
+    pub host_dev_name: String,
+}
+
+impl NetworkOverride {
+    pub fn new<S: Into<String>, T: Into<String>>(iface_id: S, host_dev_name: T) -> Self {
+        Self {
+            iface_id: iface_id.into(),
+            host_dev_name: host_dev_name.into(),
+        }
+    }
+}
@@ -3,7 +3,12 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
 use super::rate_limiter;
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+
+/// Update of a drive, the only one `firecracker` applies after boot: the path, e.g. to
+/// swap the backing file (the guest is notified of the new size), and the rate limiter.
+/// Other fields such as `is_read_only` are fixed once the VM started.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct PartialDrive {
     /// drive id
     /// Required: true
@@ -20,3 +25,13 @@ pub struct PartialDrive {
     #[serde(rename = "rate_limiter", skip_serializing_if = "Option::is_none")]
     pub rate_limiter: Option<rate_limiter::RateLimiter>,
 }
+
+impl PartialDrive {
+    /// Update of drive `drive_id` which changes nothing until fields are set.
+    pub fn new<S: Into<String>>(drive_id: S) -> Self {
+        Self {
+            drive_id: drive_id.into(),
+            ..Default::default()
+        }
+    }
+}
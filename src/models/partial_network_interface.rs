@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use super::rate_limiter;
 /// PartialNetworkInterface Defines a partial network interface structure,
 /// used to update the rate limiters for that interface, after microvm start.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct PartialNetworkInterface {
     /// iface id
     /// Required: true
@@ -18,3 +19,13 @@ pub struct PartialNetworkInterface {
     #[serde(rename = "tx_rate_limiter", skip_serializing_if = "Option::is_none")]
     pub tx_rate_limiter: Option<rate_limiter::RateLimiter>,
 }
+
+impl PartialNetworkInterface {
+    /// Update of network interface `iface_id` which changes nothing until fields are set.
+    pub fn new<S: Into<String>>(iface_id: S) -> Self {
+        Self {
+            iface_id: iface_id.into(),
+            ..Default::default()
+        }
+    }
+}
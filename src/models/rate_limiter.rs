@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 
-use super::token_bucket;
+use super::{token_bucket, Validate};
+use crate::Result;
 /// RateLimiter Defines an IO rate limiter with independent bytes/s and ops/s limits.
 /// Limits are defined by configuring each of the _bandwidth_ and _ops_ token buckets.
 /// This field is optional for virtio-block config and should be omitted for vhost-user-block configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct RateLimiter {
     /// Token bucket with bytes as tokens
     #[serde(rename = "banwidth")]
@@ -23,8 +26,22 @@ impl RateLimiter {
     }
 }
 
+impl Validate for RateLimiter {
+    /// Validate both token buckets of this rate limiter.
+    fn validate(&self) -> Result<()> {
+        if let Some(ref bandwidth) = self.bandwidth {
+            bandwidth.validate()?;
+        }
+        if let Some(ref ops) = self.ops {
+            ops.validate()?;
+        }
+        Ok(())
+    }
+}
+
 /// RateLimiterSet represents a pair of RateLimiters (inbound and outbound)
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct RateLimiterSet {
     /// InRateLimiter limits the incoming bytes.
     #[serde(rename = "in_rate_limiter", skip_serializing_if = "Option::is_none")]
@@ -2,7 +2,8 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct SnapshotCreateParams {
     /// Path to the file that will contain the guest memory.
     /// Required: true
@@ -25,7 +26,21 @@ pub struct SnapshotCreateParams {
     pub version: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default)]
+impl SnapshotCreateParams {
+    /// Full snapshot written to `snapshot_path` and `mem_file_path`.
+    pub fn new<P: Into<PathBuf>, Q: Into<PathBuf>>(snapshot_path: P, mem_file_path: Q) -> Self {
+        Self {
+            snapshot_path: snapshot_path.into(),
+            mem_file_path: mem_file_path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Type of a snapshot. A diff snapshot only contains the guest memory pages written
+/// since the previous snapshot, so `firecracker` must track dirty pages
+/// (`track_dirty_pages` of [`crate::models::MachineConfiguration`]).
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
 pub enum SnapshotType {
     #[default]
     #[serde(rename = "Full")]
@@ -2,11 +2,15 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use super::memory_backend;
+use super::{
+    memory_backend::{self, GuestMemoryBacking},
+    network_override::NetworkOverride,
+};
 
 /// Defines the configuration used for handling snapshot resume. Exactly one of
 /// the two `mem_*` fields must be present in the body of the request.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub struct SnapshotLoadParams {
     /// Enable support for incremental (diff) snapshots
     /// by tracking dirty guest pages.
@@ -34,8 +38,79 @@ pub struct SnapshotLoadParams {
     #[serde(rename = "resume_vm", skip_serializing_if = "Option::is_none")]
     pub resume_vm: Option<bool>,
 
+    /// Network host device names to override, e.g. when restoring
+    /// on another host than the one the snapshot was taken on.
+    #[serde(rename = "network_overrides", skip_serializing_if = "Option::is_none")]
+    pub network_overrides: Option<Vec<NetworkOverride>>,
+
     /// Path to the file that contains the microVM state to be loaded.
     /// Required: true
     #[serde(rename = "snapshot_path")]
     pub snapshot_path: PathBuf,
 }
+
+impl SnapshotLoadParams {
+    /// Load the snapshot at `snapshot_path`. The guest memory source must be set
+    /// with either `mem_file_path` or `mem_backend`.
+    pub fn new<P: Into<PathBuf>>(snapshot_path: P) -> Self {
+        Self {
+            snapshot_path: snapshot_path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Restore the guest memory with `backing` from `backend_path` (replacing `mem_file_path`),
+    /// see [`GuestMemoryBacking`] for the implications and
+    /// [`GuestMemoryBacking::validate`] to check it against the huge pages configuration.
+    pub fn memory_backing<P: Into<PathBuf>>(
+        mut self,
+        backing: GuestMemoryBacking,
+        backend_path: P,
+    ) -> Self {
+        self.mem_file_path = None;
+        self.mem_backend = Some(backing.mem_backend(backend_path));
+        self
+    }
+
+    /// Back the network interface `iface_id` of the restored VM with the TAP device
+    /// `host_dev_name`, replacing the override of `iface_id` (if any).
+    pub fn network_override<S: Into<String>, T: Into<String>>(
+        mut self,
+        iface_id: S,
+        host_dev_name: T,
+    ) -> Self {
+        let network_override = NetworkOverride::new(iface_id, host_dev_name);
+        let network_overrides = self.network_overrides.get_or_insert_with(Vec::new);
+        match network_overrides
+            .iter_mut()
+            .find(|x| x.iface_id == network_override.iface_id)
+        {
+            Some(x) => *x = network_override,
+            None => network_overrides.push(network_override),
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_network_overrides() {
+        let params = SnapshotLoadParams::new("/foo/snapshot");
+        assert_eq!(
+            serde_json::to_string(&params).unwrap(),
+            r#"{"snapshot_path":"/foo/snapshot"}"#
+        );
+
+        let params = params
+            .network_override("eth0", "tap0")
+            .network_override("eth1", "tap1")
+            .network_override("eth0", "tap2");
+        assert_eq!(
+            serde_json::to_string(&params.network_overrides).unwrap(),
+            r#"[{"iface_id":"eth0","host_dev_name":"tap2"},{"iface_id":"eth1","host_dev_name":"tap1"}]"#
+        );
+    }
+}
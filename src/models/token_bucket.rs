@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+use super::Validate;
+use crate::{Error, Result};
+
 /// TokenBucket Defines a token bucket with a maximum capacity (size),
 /// an initial burst size (one_time_burst) and an interval for refilling purposes (refill_time).
 /// The refill-rate is derived from size and refill_time,
@@ -8,7 +11,9 @@ use serde::{Deserialize, Serialize};
 /// Consumption from the token bucket is unbounded in speed which allows for bursts bound in size
 /// by the amount of tokens available. Once the token bucket is empty, consumption speed is bound
 /// by the refill_rate.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct TokenBucket {
     /// The initial size of a token bucket.
     /// Minimum: 0
@@ -24,3 +29,32 @@ pub struct TokenBucket {
     /// Minimum: 0
     pub size: u64,
 }
+
+impl TokenBucket {
+    /// Token bucket of `size` tokens refilled every `refill_time` milliseconds, without burst.
+    pub fn new(size: u64, refill_time: u64) -> Self {
+        Self {
+            one_time_burst: None,
+            refill_time,
+            size,
+        }
+    }
+}
+
+impl Validate for TokenBucket {
+    /// `firecracker` silently disables a bucket whose `size` or `refill_time` is zero,
+    /// reject such a bucket instead of leaving the device unexpectedly unlimited.
+    fn validate(&self) -> Result<()> {
+        if self.size == 0 {
+            return Err(Error::Configuration(
+                "token bucket `size` must be non-zero".into(),
+            ));
+        }
+        if self.refill_time == 0 {
+            return Err(Error::Configuration(
+                "token bucket `refill_time` must be non-zero".into(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::Result;
+
+/// Client-side checks of a model before it is sent to `firecracker`, catching the mistakes
+/// `firecracker` would reject (or, worse, silently accept) with an [`crate::Error::Configuration`]
+/// naming the offending field.
+///
+/// The `put_*` methods of [`crate::instance::Instance`] validate the models they send,
+/// unless [`crate::instance::Instance::skip_validation`] is set. Models can also be validated
+/// up front, e.g. while loading a configuration:
+///
+/// ```
+/// use firecracker_rs_sdk::models::{MachineConfiguration, Validate};
+///
+/// assert!(MachineConfiguration::new(0, 256).validate().is_err());
+/// ```
+pub trait Validate {
+    fn validate(&self) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Validate;
+    use crate::models::{BootSource, MachineConfiguration, Vsock};
+
+    fn validate_all(models: &[&dyn Validate]) -> crate::Result<()> {
+        models.iter().try_for_each(|model| model.validate())
+    }
+
+    #[test]
+    fn validate_models() {
+        let machine_configuration = MachineConfiguration::new(2, 256);
+        let vsock = Vsock::new(3, "/tmp/v.sock");
+        let boot_source = BootSource::new("/foo/vmlinux");
+        assert!(validate_all(&[&machine_configuration, &vsock, &boot_source]).is_ok());
+
+        assert!(MachineConfiguration::new(0, 256).validate().is_err());
+        assert!(MachineConfiguration::new(33, 256).validate().is_err());
+        assert!(MachineConfiguration::new(1, 0).validate().is_err());
+        let mut smt = MachineConfiguration::new(3, 256);
+        assert!(smt.validate().is_ok());
+        smt.smt = Some(true);
+        assert!(smt.validate().is_err());
+
+        assert!(Vsock::new(2, "/tmp/v.sock").validate().is_err());
+        assert!(Vsock::new(3, "").validate().is_err());
+        assert!(BootSource::new("").validate().is_err());
+    }
+}
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 // VM Defines the microVM running state.
 // It is especially useful in the snapshotting context.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub struct Vm {
     /// state
     /// Required: true
@@ -10,6 +11,18 @@ pub struct Vm {
     pub state: State,
 }
 
+impl Vm {
+    pub const fn new(state: State) -> Self {
+        Self { state }
+    }
+}
+
+/// States accepted by `PATCH /vm`.
+///
+/// The enum is deliberately closed so that states `firecracker` does not
+/// understand are rejected at compile time. Currently the endpoint only
+/// accepts `Paused` and `Resumed`; a new state should only be added here
+/// together with the `firecracker` version that introduced it.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum State {
     #[serde(rename = "Paused")]
@@ -24,3 +37,20 @@ pub const VM_STATE_PAUSED: Vm = Vm {
 pub const VM_STATE_RESUMED: Vm = Vm {
     state: State::Resumed,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_vm_state() {
+        assert_eq!(
+            serde_json::to_string(&VM_STATE_PAUSED).unwrap(),
+            r#"{"state":"Paused"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&VM_STATE_RESUMED).unwrap(),
+            r#"{"state":"Resumed"}"#
+        );
+    }
+}
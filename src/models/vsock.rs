@@ -2,6 +2,9 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use super::Validate;
+use crate::{Error, Result};
+
 /// Defines a vsock device, backed by a set of Unix Domain Sockets, on the host side.
 /// For host-initiated connections, Firecracker will be listening on the Unix socket
 /// identified by the path `uds_path`. Firecracker will create this socket, bind and
@@ -11,7 +14,9 @@ use serde::{Deserialize, Serialize};
 /// For guest-initiated connections, Firecracker will expect host software to be
 /// bound and listening on Unix sockets at `uds_path_<PORT>`.
 /// E.g. "/path/to/host_vsock.sock_52" for port number 52.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+#[cfg_attr(feature = "strict-deserialize", serde(deny_unknown_fields))]
 pub struct Vsock {
     /// Guest Vsock CID
     /// Required: true
@@ -35,3 +40,33 @@ pub struct Vsock {
     #[serde(rename = "vsock_id", skip_serializing_if = "Option::is_none")]
     pub vsock_id: Option<String>,
 }
+
+impl Vsock {
+    /// Vsock device for guest CID `guest_cid` listening on `uds_path`.
+    pub fn new<P: Into<PathBuf>>(guest_cid: u32, uds_path: P) -> Self {
+        Self {
+            guest_cid,
+            uds_path: uds_path.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Validate for Vsock {
+    /// Check that `guest_cid` is at least 3, lower CIDs being reserved, and that
+    /// `uds_path` is not empty.
+    fn validate(&self) -> Result<()> {
+        if self.guest_cid < 3 {
+            return Err(Error::Configuration(format!(
+                "Vsock `guest_cid` {} is reserved, it must be at least 3",
+                self.guest_cid
+            )));
+        }
+        if self.uds_path.as_os_str().is_empty() {
+            return Err(Error::Configuration(
+                "Vsock `uds_path` must not be empty".into(),
+            ));
+        }
+        Ok(())
+    }
+}
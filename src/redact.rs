@@ -0,0 +1,62 @@
+//! Redaction of host details in the `Debug` output of the options,
+//! so that they can be logged without leaking the filesystem layout.
+
+use std::{fmt, path::Path};
+
+/// Placeholder printed instead of a hidden value.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// `Debug` wrapper printing an absolute path as `.../<basename>`.
+/// Relative paths do not reveal the host layout and are printed as is.
+pub(crate) struct RedactedPath<'a>(pub(crate) &'a Path);
+
+impl fmt::Debug for RedactedPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.0.is_absolute(), self.0.file_name()) {
+            (true, Some(file_name)) => write!(f, "\".../{}\"", file_name.to_string_lossy()),
+            (true, None) => write!(f, "\"/...\""),
+            (false, _) => fmt::Debug::fmt(self.0, f),
+        }
+    }
+}
+
+/// Redact an optional path, see [`RedactedPath`].
+pub(crate) fn redact_path<P: AsRef<Path>>(path: Option<&P>) -> Option<RedactedPath<'_>> {
+    path.map(|path| RedactedPath(path.as_ref()))
+}
+
+/// Hide an optional value, only showing whether it is set.
+pub(crate) fn redact<T>(value: Option<&T>) -> Option<&'static str> {
+    value.map(|_| REDACTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{firecracker::FirecrackerOption, jailer::JailerOption};
+
+    #[test]
+    fn redacted_debug() {
+        let firecracker_option = FirecrackerOption::new("/opt/firecracker/bin/firecracker")
+            .api_sock("/run/firecracker.socket")
+            .clone();
+        let debug = format!("{firecracker_option:?}");
+        assert!(debug.contains(r#"firecracker_bin: ".../firecracker""#));
+        assert!(debug.contains(r#"api_sock: Some(".../firecracker.socket")"#));
+        assert!(!debug.contains("/opt"));
+
+        let jailer_option = JailerOption::new(
+            "/opt/firecracker/bin/jailer",
+            "/opt/firecracker/bin/firecracker",
+            "test-instance",
+            100,
+            123,
+        )
+        .firecracker_option(Some(&firecracker_option))
+        .clone();
+        let debug = format!("{jailer_option:?}");
+        assert!(debug.contains(r#"jailer_bin: ".../jailer""#));
+        assert!(debug.contains(r#"uid: Some("<redacted>")"#));
+        assert!(!debug.contains("/opt"));
+        assert!(!debug.contains("123"));
+    }
+}
@@ -0,0 +1,352 @@
+//! Declarative description of a whole VM in one TOML or YAML file: the options to spawn
+//! `firecracker` (optionally through `jailer`) and its pre-boot configuration.
+//!
+//! The configuration takes the keys of a `firecracker` config file
+//! (see [`FullVmConfiguration`]), next to the `firecracker` and `jailer` sections:
+//!
+//! ```toml
+//! [firecracker]
+//! bin = "/usr/bin/firecracker"
+//! api_sock = "/tmp/vm0.socket"
+//!
+//! # spawn through `jailer`, paths of the configuration are linked into the jail
+//! # [jailer]
+//! # bin = "/usr/bin/jailer"
+//! # id = "vm0"
+//! # uid = 1000
+//! # gid = 1000
+//!
+//! [machine-config]
+//! vcpu_count = 1
+//! mem_size_mib = 256
+//!
+//! [boot-source]
+//! kernel_image_path = "/path/to/vmlinux"
+//! boot_args = "console=ttyS0 reboot=k panic=1"
+//!
+//! [[drives]]
+//! drive_id = "rootfs"
+//! path_on_host = "/path/to/rootfs.ext4"
+//! is_root_device = true
+//! is_read_only = false
+//! ```
+//!
+//! [`VmSpec::launch`] spawns the VMM, applies the configuration and boots the guest.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    firecracker::FirecrackerOption, instance::VmConfigBuilder, jailer::JailerOption,
+    models::FullVmConfiguration, Error, Result,
+};
+
+#[cfg(feature = "_rt-async")]
+mod rt_async;
+#[cfg(feature = "_rt-std")]
+mod rt_std;
+
+/// Time given to the `firecracker` API to come up in [`VmSpec::launch`].
+#[cfg(any(feature = "_rt-std", feature = "_rt-async"))]
+const LAUNCH_READY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A whole VM, as read from a spec file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VmSpec {
+    #[serde(default)]
+    pub firecracker: FirecrackerSpec,
+
+    /// Spawn `firecracker` through `jailer` if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jailer: Option<JailerSpec>,
+
+    /// Pre-boot configuration, applied through the API after spawning the VMM.
+    #[serde(flatten)]
+    pub config: FullVmConfiguration,
+}
+
+/// Options of `firecracker`, a subset of [`FirecrackerOption`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FirecrackerSpec {
+    /// Path to the `firecracker` binary, located with [`crate::firecracker::find_binary`]
+    /// if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<PathBuf>,
+
+    /// Path to the API socket. Without `jailer`, defaults to a unique path under the
+    /// temporary directory (see [`FirecrackerOption::api_sock_in_tempdir`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_sock: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_path: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+
+    #[serde(default)]
+    pub no_seccomp: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<PathBuf>,
+
+    /// Grace period in milliseconds before `firecracker` is killed when dropped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutdown_grace_ms: Option<u64>,
+}
+
+/// Options of `jailer`, a subset of [`JailerOption`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JailerSpec {
+    /// Path to the `jailer` binary, located with [`crate::jailer::find_binary`] if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<PathBuf>,
+
+    pub id: String,
+
+    pub uid: usize,
+
+    pub gid: usize,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chroot_base_dir: Option<PathBuf>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netns: Option<PathBuf>,
+
+    #[serde(default)]
+    pub daemonize: bool,
+
+    #[serde(default)]
+    pub new_pid_ns: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup_version: Option<usize>,
+
+    /// `--cgroup <file>=<value>` arguments, e.g. `"cpuset.cpus" = "0"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cgroups: BTreeMap<String, String>,
+
+    /// `--resource-limit <resource>=<value>` arguments, e.g. `"no-file" = 1024`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource_limits: BTreeMap<String, usize>,
+}
+
+impl VmSpec {
+    /// Parse a spec in TOML.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| Error::Configuration(format!("Invalid spec: {e}")))
+    }
+
+    /// Parse a spec in YAML.
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        serde_yaml::from_str(content)
+            .map_err(|e| Error::Configuration(format!("Invalid spec: {e}")))
+    }
+
+    /// Read the spec file at `path`, in TOML (`.toml`) or YAML (`.yaml` / `.yml`)
+    /// according to its extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|source| Error::ConfigurationIO {
+            context: format!("Fail to read spec file {}", path.display()),
+            source,
+        })?;
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("toml") => Self::from_toml(&content),
+            Some("yaml" | "yml") => Self::from_yaml(&content),
+            _ => Err(Error::Configuration(format!(
+                "Unknown format of spec file {}, expected `.toml`, `.yaml` or `.yml`",
+                path.display()
+            ))),
+        }
+    }
+
+    /// The options to spawn `firecracker` with.
+    pub fn firecracker_option(&self) -> FirecrackerOption {
+        let spec = &self.firecracker;
+        let mut option = FirecrackerOption::new(spec.bin.as_deref().unwrap_or(Path::new("")));
+        match spec.api_sock {
+            Some(ref api_sock) => option.api_sock(api_sock),
+            // the default inside the jail is already unique to the jailed VM
+            None if self.jailer.is_none() => option.api_sock_in_tempdir(),
+            None => &mut option,
+        };
+        if let Some(ref id) = spec.id {
+            option.id(id);
+        }
+        option.log_path(spec.log_path.as_ref());
+        if let Some(ref level) = spec.level {
+            option.level(level);
+        }
+        if spec.no_seccomp {
+            option.no_seccomp();
+        }
+        if let Some(ref stdout) = spec.stdout {
+            option.stdout(stdout);
+        }
+        if let Some(ref stderr) = spec.stderr {
+            option.stderr(stderr);
+        }
+        if let Some(shutdown_grace_ms) = spec.shutdown_grace_ms {
+            option.shutdown_grace(Duration::from_millis(shutdown_grace_ms));
+        }
+        option
+    }
+
+    /// The options to spawn `firecracker` through `jailer` with, if the spec has a `jailer`
+    /// section.
+    pub fn jailer_option<'f>(
+        &self,
+        firecracker_option: &'f FirecrackerOption,
+    ) -> Option<JailerOption<'f>> {
+        let spec = self.jailer.as_ref()?;
+        let mut option = JailerOption::new(
+            spec.bin.as_deref().unwrap_or(Path::new("")),
+            self.firecracker.bin.as_deref().unwrap_or(Path::new("")),
+            &spec.id,
+            spec.gid,
+            spec.uid,
+        );
+        option
+            .chroot_base_dir(spec.chroot_base_dir.as_ref())
+            .netns(spec.netns.as_ref())
+            .new_pid_ns(spec.new_pid_ns.then_some(true))
+            .cgroup_version(spec.cgroup_version)
+            .cgroup(spec.cgroups.clone().into_iter().collect())
+            .resource_limit(spec.resource_limits.clone().into_iter().collect())
+            .firecracker_option(Some(firecracker_option));
+        if spec.daemonize {
+            option.daemonize();
+        }
+        Some(option)
+    }
+
+    /// The pre-boot configuration, as sent by [`crate::instance::Instance::apply_config`].
+    pub fn vm_config(&self) -> VmConfigBuilder {
+        self.config.clone().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::VmSpec;
+
+    const TOML_SPEC: &str = r#"
+[firecracker]
+bin = "/usr/bin/firecracker"
+api_sock = "/tmp/vm0.socket"
+no_seccomp = true
+
+[jailer]
+id = "vm0"
+uid = 1000
+gid = 1000
+
+[jailer.cgroups]
+"cpuset.cpus" = "0"
+
+[machine-config]
+vcpu_count = 2
+mem_size_mib = 256
+
+[boot-source]
+kernel_image_path = "/path/to/vmlinux"
+
+[[drives]]
+drive_id = "rootfs"
+path_on_host = "/path/to/rootfs.ext4"
+is_root_device = true
+is_read_only = false
+"#;
+
+    const YAML_SPEC: &str = r#"
+firecracker:
+  bin: /usr/bin/firecracker
+  api_sock: /tmp/vm0.socket
+  no_seccomp: true
+jailer:
+  id: vm0
+  uid: 1000
+  gid: 1000
+  cgroups:
+    cpuset.cpus: "0"
+machine-config:
+  vcpu_count: 2
+  mem_size_mib: 256
+boot-source:
+  kernel_image_path: /path/to/vmlinux
+drives:
+  - drive_id: rootfs
+    path_on_host: /path/to/rootfs.ext4
+    is_root_device: true
+    is_read_only: false
+"#;
+
+    #[test]
+    fn parse_spec() {
+        for spec in [
+            VmSpec::from_toml(TOML_SPEC).unwrap(),
+            VmSpec::from_yaml(YAML_SPEC).unwrap(),
+        ] {
+            assert_eq!(
+                spec.firecracker.api_sock.as_deref(),
+                Some(Path::new("/tmp/vm0.socket"))
+            );
+            assert!(spec.firecracker.no_seccomp);
+            let jailer = spec.jailer.as_ref().unwrap();
+            assert_eq!(
+                (jailer.id.as_str(), jailer.uid, jailer.gid),
+                ("vm0", 1000, 1000)
+            );
+            assert_eq!(jailer.cgroups["cpuset.cpus"], "0");
+            let machine_config = spec.config.machine_config.as_ref().unwrap();
+            assert_eq!(
+                (machine_config.vcpu_count, machine_config.mem_size_mib),
+                (2, 256)
+            );
+            let drives = spec.config.drives.as_ref().unwrap();
+            assert_eq!(drives.len(), 1);
+            assert!(drives[0].is_root_device);
+
+            let firecracker_option = spec.firecracker_option();
+            assert!(spec.jailer_option(&firecracker_option).is_some());
+        }
+
+        assert!(VmSpec::from_toml("[machine-config]\nvcpu_count = \"two\"").is_err());
+    }
+
+    #[test]
+    fn spec_from_file() {
+        let dir = std::env::temp_dir();
+        let toml_path = dir.join("firecracker-sdk-test-spec.toml");
+        let yml_path = dir.join("firecracker-sdk-test-spec.yml");
+        let json_path = dir.join("firecracker-sdk-test-spec.json");
+        fs::write(&toml_path, TOML_SPEC).unwrap();
+        fs::write(&yml_path, YAML_SPEC).unwrap();
+        fs::write(&json_path, "{}").unwrap();
+
+        assert!(VmSpec::from_file(&toml_path).is_ok());
+        assert!(VmSpec::from_file(&yml_path).is_ok());
+        assert!(VmSpec::from_file(&json_path).is_err());
+        assert!(VmSpec::from_file(dir.join("firecracker-sdk-test-spec-missing.toml")).is_err());
+
+        for path in [toml_path, yml_path, json_path] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
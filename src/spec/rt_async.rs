@@ -0,0 +1,22 @@
+use crate::{instance::Instance, Result};
+
+use super::{VmSpec, LAUNCH_READY_TIMEOUT};
+
+impl VmSpec {
+    /// Spawn the VMM (through `jailer` if configured), apply the pre-boot configuration
+    /// and boot the guest.
+    ///
+    /// The VMM is torn down if any step fails, as the dropped [`Instance`] cleans up.
+    pub async fn launch(&self) -> Result<Instance> {
+        let mut firecracker_option = self.firecracker_option();
+        let mut instance = match self.jailer_option(&firecracker_option) {
+            Some(mut jailer_option) => jailer_option.build()?,
+            None => firecracker_option.build()?,
+        };
+        instance.start_vmm().await?;
+        instance.wait_until_ready(LAUNCH_READY_TIMEOUT).await?;
+        instance.apply_config(&self.vm_config()).await?;
+        instance.start().await?;
+        Ok(instance)
+    }
+}
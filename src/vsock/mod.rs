@@ -0,0 +1,119 @@
+//! Connections to the guest through the vsock device, which `firecracker` proxies over
+//! Unix domain sockets on the host (see [`crate::models::Vsock`]).
+//!
+//! - Host-initiated: [`VsockStream::connect`] connects to `uds_path` and asks `firecracker`
+//!   to forward the connection to a guest port (`CONNECT <port>\n`, acknowledged with
+//!   `OK <host port>\n`).
+//! - Guest-initiated: [`VsockListener::bind`] listens on `<uds_path>_<port>`, where
+//!   `firecracker` forwards the connections of the guest to host port `port`.
+//!
+//! When spawned with `jailer`, `uds_path` is the path inside the jailer workspace directory.
+//!
+//! [`VsockStream`] implements the IO traits of the runtime: [`std::io::Read`] / [`std::io::Write`],
+//! `tokio::io::AsyncRead` / `AsyncWrite`, or `async_std::io::Read` / `Write`.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
+
+#[cfg(feature = "_rt-async-std")]
+mod rt_async_std;
+#[cfg(feature = "_rt-std")]
+mod rt_std;
+#[cfg(feature = "_rt-tokio")]
+mod rt_tokio;
+
+/// Longest acknowledgement line accepted from `firecracker`, `OK <u32>\n` being 14 bytes.
+const MAX_ACK_LEN: usize = 32;
+
+/// Connection to a guest vsock port.
+pub struct VsockStream {
+    #[cfg(feature = "_rt-std")]
+    stream: std::os::unix::net::UnixStream,
+    #[cfg(feature = "_rt-tokio")]
+    stream: tokio::net::UnixStream,
+    #[cfg(feature = "_rt-async-std")]
+    stream: async_std::os::unix::net::UnixStream,
+
+    // host port assigned by `firecracker` to a host-initiated connection
+    host_port: Option<u32>,
+}
+
+impl VsockStream {
+    /// Returns the host port `firecracker` assigned to this connection if it is host-initiated,
+    /// or [`None`] if it was accepted from the guest.
+    pub fn host_port(&self) -> Option<u32> {
+        self.host_port
+    }
+}
+
+/// Listener of the connections initiated by the guest to a host port.
+/// The socket file is removed when the listener is dropped.
+pub struct VsockListener {
+    #[cfg(feature = "_rt-std")]
+    listener: std::os::unix::net::UnixListener,
+    #[cfg(feature = "_rt-tokio")]
+    listener: tokio::net::UnixListener,
+    #[cfg(feature = "_rt-async-std")]
+    listener: async_std::os::unix::net::UnixListener,
+
+    path: PathBuf,
+}
+
+impl VsockListener {
+    /// Returns the path of the Unix domain socket listened on, i.e. `<uds_path>_<port>`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for VsockListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Path of the Unix domain socket where `firecracker` forwards the connections of the guest
+/// to host port `port`.
+pub fn listener_path<P: AsRef<Path>>(uds_path: P, port: u32) -> PathBuf {
+    let mut path = OsString::from(uds_path.as_ref());
+    path.push(format!("_{port}"));
+    path.into()
+}
+
+/// Request forwarding a host-initiated connection to guest port `port`.
+fn connect_request(port: u32) -> String {
+    format!("CONNECT {port}\n")
+}
+
+/// Parse the `OK <host port>\n` acknowledgement of a `CONNECT` request.
+fn parse_ack(line: &[u8]) -> Result<u32> {
+    std::str::from_utf8(line)
+        .ok()
+        .and_then(|line| line.strip_suffix('\n')?.strip_prefix("OK ")?.parse().ok())
+        .ok_or_else(|| {
+            Error::Agent(format!(
+                "vsock connection refused: {}",
+                String::from_utf8_lossy(line).trim_end()
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{listener_path, parse_ack};
+
+    #[test]
+    fn vsock_handshake() {
+        assert_eq!(
+            listener_path("/tmp/v.sock", 52),
+            std::path::Path::new("/tmp/v.sock_52")
+        );
+        assert_eq!(parse_ack(b"OK 1073741824\n").unwrap(), 1073741824);
+        assert!(parse_ack(b"OK 1073741824").is_err());
+        assert!(parse_ack(b"\n").is_err());
+    }
+}
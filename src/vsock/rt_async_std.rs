@@ -0,0 +1,152 @@
+use std::{
+    io,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_std::{
+    io::{Read, ReadExt, Write, WriteExt},
+    os::unix::net::{UnixListener, UnixStream},
+};
+
+use crate::Result;
+
+use super::{connect_request, listener_path, parse_ack, VsockListener, VsockStream, MAX_ACK_LEN};
+
+impl VsockStream {
+    /// Connect to guest port `port` through the vsock device listening on `uds_path`.
+    pub async fn connect<P: AsRef<Path>>(uds_path: P, port: u32) -> Result<Self> {
+        let mut stream = UnixStream::connect(uds_path.as_ref()).await?;
+        stream.write_all(connect_request(port).as_bytes()).await?;
+
+        // read byte by byte so that nothing sent by the guest is consumed
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while line.last() != Some(&b'\n') && line.len() < MAX_ACK_LEN {
+            if stream.read(&mut byte).await? == 0 {
+                break;
+            }
+            line.push(byte[0]);
+        }
+        let host_port = parse_ack(&line)?;
+
+        Ok(Self {
+            stream,
+            host_port: Some(host_port),
+        })
+    }
+
+    /// Returns the underlying Unix domain socket.
+    pub fn into_inner(self) -> UnixStream {
+        self.stream
+    }
+}
+
+impl Read for VsockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl Write for VsockStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+impl VsockListener {
+    /// Listen for the connections of the guest to host port `port` of the vsock device
+    /// at `uds_path`.
+    pub async fn bind<P: AsRef<Path>>(uds_path: P, port: u32) -> Result<Self> {
+        let path = listener_path(uds_path, port);
+        let listener = UnixListener::bind(&path).await?;
+        Ok(Self { listener, path })
+    }
+
+    /// Accept a connection from the guest.
+    pub async fn accept(&self) -> Result<VsockStream> {
+        let (stream, _) = self.listener.accept().await?;
+        Ok(VsockStream {
+            stream,
+            host_port: None,
+        })
+    }
+}
+
+#[cfg(feature = "_rt-async-std")]
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use async_std::{
+        io::{prelude::BufReadExt, BufReader, ReadExt, WriteExt},
+        os::unix::net::{UnixListener, UnixStream},
+    };
+
+    use crate::vsock::{VsockListener, VsockStream};
+
+    #[async_std::test]
+    async fn vsock_connect() {
+        const UDS_PATH: &str = "/tmp/firecracker-sdk-test-vsock-async-std-connect.socket";
+        let _ = fs::remove_file(UDS_PATH);
+        let listener = UnixListener::bind(UDS_PATH).await.unwrap();
+
+        // `firecracker` acknowledging the forwarding, then echoing as the guest
+        let server_handle = async_std::task::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            reader.read_line(&mut request).await.unwrap();
+            assert_eq!(request, "CONNECT 52\n");
+            (&stream).write_all(b"OK 1073741824\n").await.unwrap();
+            let (mut rd, mut wr) = (&stream, &stream);
+            async_std::io::copy(&mut rd, &mut wr).await.unwrap();
+        });
+
+        let mut stream = VsockStream::connect(UDS_PATH, 52).await.unwrap();
+        assert_eq!(stream.host_port(), Some(1073741824));
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        drop(stream);
+        server_handle.await;
+        let _ = fs::remove_file(UDS_PATH);
+    }
+
+    #[async_std::test]
+    async fn vsock_listen() {
+        const UDS_PATH: &str = "/tmp/firecracker-sdk-test-vsock-async-std-listen.socket";
+        let listener = VsockListener::bind(UDS_PATH, 52).await.unwrap();
+        let path = listener.path().to_path_buf();
+
+        let mut guest = UnixStream::connect(&path).await.unwrap();
+        let mut stream = listener.accept().await.unwrap();
+        assert_eq!(stream.host_port(), None);
+        guest.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    io::{Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+};
+
+use crate::Result;
+
+use super::{connect_request, listener_path, parse_ack, VsockListener, VsockStream, MAX_ACK_LEN};
+
+impl VsockStream {
+    /// Connect to guest port `port` through the vsock device listening on `uds_path`.
+    pub fn connect<P: AsRef<Path>>(uds_path: P, port: u32) -> Result<Self> {
+        let mut stream = UnixStream::connect(uds_path)?;
+        stream.write_all(connect_request(port).as_bytes())?;
+
+        // read byte by byte so that nothing sent by the guest is consumed
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while line.last() != Some(&b'\n') && line.len() < MAX_ACK_LEN {
+            if stream.read(&mut byte)? == 0 {
+                break;
+            }
+            line.push(byte[0]);
+        }
+        let host_port = parse_ack(&line)?;
+
+        Ok(Self {
+            stream,
+            host_port: Some(host_port),
+        })
+    }
+
+    /// Returns the underlying Unix domain socket.
+    pub fn into_inner(self) -> UnixStream {
+        self.stream
+    }
+}
+
+impl Read for VsockStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for VsockStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl VsockListener {
+    /// Listen for the connections of the guest to host port `port` of the vsock device
+    /// at `uds_path`.
+    pub fn bind<P: AsRef<Path>>(uds_path: P, port: u32) -> Result<Self> {
+        let path = listener_path(uds_path, port);
+        let listener = UnixListener::bind(&path)?;
+        Ok(Self { listener, path })
+    }
+
+    /// Accept a connection from the guest.
+    pub fn accept(&self) -> Result<VsockStream> {
+        let (stream, _) = self.listener.accept()?;
+        Ok(VsockStream {
+            stream,
+            host_port: None,
+        })
+    }
+}
+
+#[cfg(feature = "_rt-std")]
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{BufRead, BufReader, Read, Write},
+        os::unix::net::{UnixListener, UnixStream},
+    };
+
+    use crate::vsock::{VsockListener, VsockStream};
+
+    #[test]
+    fn vsock_connect() {
+        const UDS_PATH: &str = "/tmp/firecracker-sdk-test-vsock-std-connect.socket";
+        let _ = fs::remove_file(UDS_PATH);
+        let listener = UnixListener::bind(UDS_PATH).unwrap();
+
+        // `firecracker` acknowledging the forwarding, then the guest greeting right away
+        let server_handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            assert_eq!(request, "CONNECT 52\n");
+            let mut stream = reader.into_inner();
+            stream.write_all(b"OK 1073741824\nhello").unwrap();
+        });
+
+        let mut stream = VsockStream::connect(UDS_PATH, 52).unwrap();
+        assert_eq!(stream.host_port(), Some(1073741824));
+        let mut greeting = String::new();
+        stream.read_to_string(&mut greeting).unwrap();
+        assert_eq!(greeting, "hello");
+
+        server_handle.join().unwrap();
+        let _ = fs::remove_file(UDS_PATH);
+    }
+
+    #[test]
+    fn vsock_connect_refused() {
+        const UDS_PATH: &str = "/tmp/firecracker-sdk-test-vsock-std-refused.socket";
+        let _ = fs::remove_file(UDS_PATH);
+        let listener = UnixListener::bind(UDS_PATH).unwrap();
+
+        // `firecracker` closes the connection if nothing listens on the guest port
+        let server_handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 16]);
+        });
+
+        assert!(VsockStream::connect(UDS_PATH, 52).is_err());
+
+        server_handle.join().unwrap();
+        let _ = fs::remove_file(UDS_PATH);
+    }
+
+    #[test]
+    fn vsock_listen() {
+        const UDS_PATH: &str = "/tmp/firecracker-sdk-test-vsock-std-listen.socket";
+        let listener = VsockListener::bind(UDS_PATH, 52).unwrap();
+        let path = listener.path().to_path_buf();
+
+        let mut guest = UnixStream::connect(&path).unwrap();
+        let mut stream = listener.accept().unwrap();
+        assert_eq!(stream.host_port(), None);
+        guest.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
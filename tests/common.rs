@@ -1,4 +1,4 @@
-use std::{env, sync::LazyLock};
+use std::{env, path::PathBuf, process, sync::LazyLock};
 
 #[allow(unused)]
 pub(crate) const FIRECRACKER: LazyLock<String> = LazyLock::new(|| {
@@ -24,6 +24,28 @@ pub(crate) const ROOTFS: LazyLock<String> = LazyLock::new(|| {
     env::var("ROOTFS").unwrap()
 });
 
+#[allow(unused)]
+pub(crate) const INITRD: LazyLock<String> = LazyLock::new(|| {
+    dotenvy::dotenv().ok();
+    env::var("INITRD").unwrap()
+});
+
+/// API socket of the test `name`, unique to this test run so that tests running in parallel
+/// (or other runs of the suite) never bind the same socket.
+#[allow(unused)]
+pub(crate) fn api_sock(name: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+        "firecracker-sdk-integration-test-{name}-{}.socket",
+        process::id()
+    ))
+}
+
+/// [`api_sock`] inside the jail, where `/run` holds the API socket by default.
+#[allow(unused)]
+pub(crate) fn jailed_api_sock(name: &str) -> PathBuf {
+    PathBuf::from(format!("/run/{name}-{}.socket", process::id()))
+}
+
 #[allow(unused)]
 fn load_envs() {
     dotenvy::dotenv().ok();
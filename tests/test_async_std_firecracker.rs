@@ -1,4 +1,4 @@
-#![cfg(feature = "_rt-async-std")]
+#![cfg(all(feature = "_rt-async-std", feature = "models"))]
 
 use std::fs;
 
@@ -8,21 +8,20 @@ mod common;
 
 #[async_std::test]
 async fn spawn_plain() -> Result<()> {
-    const API_SOCK: &'static str =
-        "/tmp/firecracker-sdk-integration-test-async-std-firecracker-spawn-plain.socket";
+    let api_sock = common::api_sock("async-std-firecracker-spawn-plain");
     let firecracker_bin = &*common::FIRECRACKER;
 
     let mut instance = FirecrackerOption::new(firecracker_bin)
-        .api_sock(API_SOCK)
+        .api_sock(&api_sock)
         .build()?;
 
-    let _ = fs::remove_file(API_SOCK);
+    let _ = fs::remove_file(&api_sock);
     instance.start_vmm().await?;
 
     let version = instance.get_firecracker_version().await?;
 
     println!("{:?}", version);
-    fs::remove_file(API_SOCK)?;
+    fs::remove_file(&api_sock)?;
 
     Ok(())
 }
@@ -31,33 +30,25 @@ async fn spawn_plain() -> Result<()> {
 async fn spawn_and_config() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str =
-        "/tmp/firecracker-sdk-integration-test-async-std-firecracker-spawn-and-config.socket";
+    let api_sock = common::api_sock("async-std-firecracker-spawn-and-config");
     let firecracker_bin = &*common::FIRECRACKER;
 
     let mut instance = FirecrackerOption::new(firecracker_bin)
-        .api_sock(API_SOCK)
+        .api_sock(&api_sock)
         .build()?;
 
-    let _ = fs::remove_file(API_SOCK);
+    let _ = fs::remove_file(&api_sock);
     instance.start_vmm().await?;
 
     // put some configuration to it
-    instance
-        .put_machine_configuration(&MachineConfiguration {
-            cpu_template: None,
-            smt: Some(true),
-            mem_size_mib: 1024,
-            track_dirty_pages: None,
-            vcpu_count: 1,
-            huge_pages: None,
-        })
-        .await?;
+    let mut machine_config = MachineConfiguration::new(1, 1024);
+    machine_config.smt = Some(true);
+    instance.put_machine_configuration(&machine_config).await?;
 
     let version = instance.get_firecracker_version().await?;
 
     println!("{:?}", version);
-    fs::remove_file(API_SOCK)?;
+    fs::remove_file(&api_sock)?;
 
     Ok(())
 }
@@ -66,14 +57,13 @@ async fn spawn_and_config() -> Result<()> {
 async fn basic_launch() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str =
-        "/tmp/firecracker-sdk-integration-test-async-std-firecracker-basic-launch.socket";
+    let api_sock = common::api_sock("async-std-firecracker-basic-launch");
     let firecracker_bin = &*common::FIRECRACKER;
     let kernel = &*common::KERNEL;
     let rootfs = &*common::ROOTFS;
 
     let mut instance = FirecrackerOption::new(firecracker_bin)
-        .api_sock(API_SOCK)
+        .api_sock(&api_sock)
         .stdin("/dev/null")
         .stdout("/dev/null")
         .stderr("/dev/null")
@@ -83,37 +73,16 @@ async fn basic_launch() -> Result<()> {
 
     // put some configuration to it
     instance
-        .put_machine_configuration(&MachineConfiguration {
-            cpu_template: None,
-            smt: None,
-            mem_size_mib: 1024,
-            track_dirty_pages: None,
-            vcpu_count: 1,
-            huge_pages: None,
-        })
+        .put_machine_configuration(&MachineConfiguration::new(1, 1024))
         .await?;
 
-    instance
-        .put_guest_boot_source(&BootSource {
-            boot_args: Some("console=ttyS0 reboot=k panic=1 pci=off".into()),
-            initrd_path: None,
-            kernel_image_path: kernel.into(),
-        })
-        .await?;
+    let mut boot_source = BootSource::new(kernel);
+    boot_source.boot_args = Some("console=ttyS0 reboot=k panic=1 pci=off".into());
+    instance.put_guest_boot_source(&boot_source).await?;
 
-    instance
-        .put_guest_drive_by_id(&Drive {
-            drive_id: "rootfs".into(),
-            partuuid: None,
-            is_root_device: true,
-            cache_type: None,
-            is_read_only: false,
-            path_on_host: rootfs.into(),
-            rate_limiter: None,
-            io_engine: None,
-            socket: None,
-        })
-        .await?;
+    let mut root_drive = Drive::new("rootfs", rootfs);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive).await?;
 
     let version = instance.get_firecracker_version().await?;
     println!("{:?}", version);
@@ -129,7 +98,7 @@ async fn basic_launch() -> Result<()> {
 
     instance.stop().await?;
 
-    fs::remove_file(API_SOCK)?;
+    fs::remove_file(&api_sock)?;
 
     Ok(())
 }
@@ -1,4 +1,4 @@
-#![cfg(feature = "_rt-async-std")]
+#![cfg(all(feature = "_rt-async-std", feature = "models"))]
 
 use std::fs;
 
@@ -8,7 +8,7 @@ mod common;
 
 #[async_std::test]
 async fn spawn_plain() -> Result<()> {
-    const API_SOCK: &'static str = "/run/firecracker.socket";
+    let api_sock = common::jailed_api_sock("async-std-jailer-spawn-plain");
     let firecracker_bin = &*common::FIRECRACKER;
     let jailer_bin = &*common::JAILER;
 
@@ -21,7 +21,7 @@ async fn spawn_plain() -> Result<()> {
     )
     .remove_jailer_workspace_dir()
     .firecracker_option(Some(
-        FirecrackerOption::new(firecracker_bin).api_sock(API_SOCK),
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
     ))
     .build()?;
 
@@ -40,7 +40,7 @@ async fn spawn_plain() -> Result<()> {
 async fn spawn_and_config() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str = "/run/firecracker.socket";
+    let api_sock = common::jailed_api_sock("async-std-jailer-spawn-and-config");
     let firecracker_bin = &*common::FIRECRACKER;
     let jailer_bin = &*common::JAILER;
 
@@ -53,7 +53,7 @@ async fn spawn_and_config() -> Result<()> {
     )
     .remove_jailer_workspace_dir()
     .firecracker_option(Some(
-        FirecrackerOption::new(firecracker_bin).api_sock(API_SOCK),
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
     ))
     .build()?;
 
@@ -63,14 +63,7 @@ async fn spawn_and_config() -> Result<()> {
 
     // put some configuration to it
     instance
-        .put_machine_configuration(&MachineConfiguration {
-            cpu_template: None,
-            smt: None,
-            mem_size_mib: 1024,
-            track_dirty_pages: None,
-            vcpu_count: 1,
-            huge_pages: None,
-        })
+        .put_machine_configuration(&MachineConfiguration::new(1, 1024))
         .await?;
 
     let version = instance.get_firecracker_version().await?;
@@ -84,7 +77,7 @@ async fn spawn_and_config() -> Result<()> {
 async fn basic_launch() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str = "/run/firecracker.socket";
+    let api_sock = common::jailed_api_sock("async-std-jailer-basic-launch");
     let firecracker_bin = &*common::FIRECRACKER;
     let jailer_bin = &*common::JAILER;
     let kernel = &*common::KERNEL;
@@ -103,7 +96,7 @@ async fn basic_launch() -> Result<()> {
     .stderr("/dev/null")
     .remove_jailer_workspace_dir()
     .firecracker_option(Some(
-        FirecrackerOption::new(firecracker_bin).api_sock(API_SOCK),
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
     ))
     .build()?;
 
@@ -113,37 +106,16 @@ async fn basic_launch() -> Result<()> {
 
     // put some configuration to it
     instance
-        .put_machine_configuration(&MachineConfiguration {
-            cpu_template: None,
-            smt: None,
-            mem_size_mib: 1024,
-            track_dirty_pages: None,
-            vcpu_count: 1,
-            huge_pages: None,
-        })
+        .put_machine_configuration(&MachineConfiguration::new(1, 1024))
         .await?;
 
-    instance
-        .put_guest_boot_source(&BootSource {
-            boot_args: Some("console=ttyS0 reboot=k panic=1 pci=off".into()),
-            initrd_path: None,
-            kernel_image_path: kernel.into(),
-        })
-        .await?;
+    let mut boot_source = BootSource::new(kernel);
+    boot_source.boot_args = Some("console=ttyS0 reboot=k panic=1 pci=off".into());
+    instance.put_guest_boot_source(&boot_source).await?;
 
-    instance
-        .put_guest_drive_by_id(&Drive {
-            drive_id: "rootfs".into(),
-            partuuid: None,
-            is_root_device: true,
-            cache_type: None,
-            is_read_only: false,
-            path_on_host: rootfs.into(),
-            rate_limiter: None,
-            io_engine: None,
-            socket: None,
-        })
-        .await?;
+    let mut root_drive = Drive::new("rootfs", rootfs);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive).await?;
 
     let version = instance.get_firecracker_version().await?;
     println!("{:?}", version);
@@ -1,4 +1,4 @@
-#![cfg(feature = "_rt-std")]
+#![cfg(all(feature = "_rt-std", feature = "models"))]
 
 use std::fs;
 
@@ -8,21 +8,20 @@ mod common;
 
 #[test]
 fn spawn_plain() -> Result<()> {
-    const API_SOCK: &'static str =
-        "/tmp/firecracker-sdk-integration-test-std-firecracker-spawn-plain.socket";
+    let api_sock = common::api_sock("std-firecracker-spawn-plain");
     let firecracker_bin = &*common::FIRECRACKER;
 
     let mut instance = FirecrackerOption::new(firecracker_bin)
-        .api_sock(API_SOCK)
+        .api_sock(&api_sock)
         .build()?;
 
-    let _ = fs::remove_file(API_SOCK);
+    let _ = fs::remove_file(&api_sock);
     instance.start_vmm()?;
 
     let version = instance.get_firecracker_version()?;
 
     println!("{:?}", version);
-    fs::remove_file(API_SOCK)?;
+    fs::remove_file(&api_sock)?;
 
     Ok(())
 }
@@ -31,31 +30,25 @@ fn spawn_plain() -> Result<()> {
 fn spawn_and_config() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str =
-        "/tmp/firecracker-sdk-integration-test-std-firecracker-spawn-and-config.socket";
+    let api_sock = common::api_sock("std-firecracker-spawn-and-config");
     let firecracker_bin = &*common::FIRECRACKER;
 
     let mut instance = FirecrackerOption::new(firecracker_bin)
-        .api_sock(API_SOCK)
+        .api_sock(&api_sock)
         .build()?;
 
-    let _ = fs::remove_file(API_SOCK);
+    let _ = fs::remove_file(&api_sock);
     instance.start_vmm()?;
 
     // put some configuration to it
-    instance.put_machine_configuration(&MachineConfiguration {
-        cpu_template: None,
-        smt: Some(true),
-        mem_size_mib: 1024,
-        track_dirty_pages: None,
-        vcpu_count: 1,
-        huge_pages: None,
-    })?;
+    let mut machine_config = MachineConfiguration::new(1, 1024);
+    machine_config.smt = Some(true);
+    instance.put_machine_configuration(&machine_config)?;
 
     let version = instance.get_firecracker_version()?;
 
     println!("{:?}", version);
-    fs::remove_file(API_SOCK)?;
+    fs::remove_file(&api_sock)?;
 
     Ok(())
 }
@@ -64,14 +57,13 @@ fn spawn_and_config() -> Result<()> {
 fn basic_launch() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str =
-        "/tmp/firecracker-sdk-integration-test-std-firecracker-basic-launch.socket";
+    let api_sock = common::api_sock("std-firecracker-basic-launch");
     let firecracker_bin = &*common::FIRECRACKER;
     let kernel = &*common::KERNEL;
     let rootfs = &*common::ROOTFS;
 
     let mut instance = FirecrackerOption::new(firecracker_bin)
-        .api_sock(API_SOCK)
+        .api_sock(&api_sock)
         .stdin("/dev/null")
         .stdout("/dev/null")
         .stderr("/dev/null")
@@ -80,32 +72,15 @@ fn basic_launch() -> Result<()> {
     instance.start_vmm()?;
 
     // put some configuration to it
-    instance.put_machine_configuration(&MachineConfiguration {
-        cpu_template: None,
-        smt: None,
-        mem_size_mib: 1024,
-        track_dirty_pages: None,
-        vcpu_count: 1,
-        huge_pages: None,
-    })?;
-
-    instance.put_guest_boot_source(&BootSource {
-        boot_args: Some("console=ttyS0 reboot=k panic=1 pci=off".into()),
-        initrd_path: None,
-        kernel_image_path: kernel.into(),
-    })?;
-
-    instance.put_guest_drive_by_id(&Drive {
-        drive_id: "rootfs".into(),
-        partuuid: None,
-        is_root_device: true,
-        cache_type: None,
-        is_read_only: false,
-        path_on_host: rootfs.into(),
-        rate_limiter: None,
-        io_engine: None,
-        socket: None,
-    })?;
+    instance.put_machine_configuration(&MachineConfiguration::new(1, 1024))?;
+
+    let mut boot_source = BootSource::new(kernel);
+    boot_source.boot_args = Some("console=ttyS0 reboot=k panic=1 pci=off".into());
+    instance.put_guest_boot_source(&boot_source)?;
+
+    let mut root_drive = Drive::new("rootfs", rootfs);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive)?;
 
     let version = instance.get_firecracker_version()?;
     println!("{:?}", version);
@@ -122,7 +97,119 @@ fn basic_launch() -> Result<()> {
     instance.stop()?;
 
     // fs::remove_dir_all(instance.jailer_workspace_dir().unwrap())?;
-    fs::remove_file(API_SOCK)?;
+    fs::remove_file(&api_sock)?;
+
+    Ok(())
+}
+
+/// Boot from an initramfs only, without any drive.
+#[test]
+fn initrd_launch() -> Result<()> {
+    use firecracker_rs_sdk::models::*;
+
+    let api_sock = common::api_sock("std-firecracker-initrd-launch");
+    let firecracker_bin = &*common::FIRECRACKER;
+    let kernel = &*common::KERNEL;
+    let initrd = &*common::INITRD;
+
+    let mut instance = FirecrackerOption::new(firecracker_bin)
+        .api_sock(&api_sock)
+        .stdin("/dev/null")
+        .stdout("/dev/null")
+        .stderr("/dev/null")
+        .build()?;
+
+    instance.start_vmm()?;
+
+    instance.put_machine_configuration(&MachineConfiguration::new(1, 256))?;
+    instance.put_guest_boot_source(&BootSource::initrd_only(kernel, initrd))?;
+
+    instance.start()?;
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    assert_eq!(instance.describe_instance()?.state, InstanceState::Running);
+
+    instance.stop()?;
+    fs::remove_file(&api_sock)?;
+
+    Ok(())
+}
+
+#[test]
+fn restart_after_crash() -> Result<()> {
+    let api_sock = common::api_sock("std-firecracker-restart-after-crash");
+    let firecracker_bin = &*common::FIRECRACKER;
+
+    let mut instance = FirecrackerOption::new(firecracker_bin)
+        .api_sock(&api_sock)
+        .build()?;
+
+    let _ = fs::remove_file(&api_sock);
+    instance.start_vmm()?;
+
+    // still running, refuse to restart
+    assert!(instance.restart_vmm().is_err());
+
+    // simulate a crash
+    let pid = instance.firecracker_pid().unwrap();
+    std::process::Command::new("kill")
+        .arg("-9")
+        .arg(pid.to_string())
+        .status()?;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    instance.restart_vmm()?;
+    assert_ne!(instance.firecracker_pid(), Some(pid));
+
+    let version = instance.get_firecracker_version()?;
+
+    println!("{:?}", version);
+    fs::remove_file(&api_sock)?;
+
+    Ok(())
+}
+
+#[test]
+fn pause_scope() -> Result<()> {
+    use firecracker_rs_sdk::models::*; // import all models for use
+
+    let api_sock = common::api_sock("std-firecracker-pause-scope");
+    let firecracker_bin = &*common::FIRECRACKER;
+    let kernel = &*common::KERNEL;
+    let rootfs = &*common::ROOTFS;
+
+    let mut instance = FirecrackerOption::new(firecracker_bin)
+        .api_sock(&api_sock)
+        .stdin("/dev/null")
+        .stdout("/dev/null")
+        .stderr("/dev/null")
+        .build()?;
+
+    let _ = fs::remove_file(&api_sock);
+    instance.start_vmm()?;
+
+    instance.put_machine_configuration(&MachineConfiguration::new(1, 1024))?;
+    let mut boot_source = BootSource::new(kernel);
+    boot_source.boot_args = Some("console=ttyS0 reboot=k panic=1 pci=off".into());
+    instance.put_guest_boot_source(&boot_source)?;
+    let mut root_drive = Drive::new("rootfs", rootfs);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive)?;
+
+    instance.start()?;
+    std::thread::sleep(std::time::Duration::from_secs(1));
+
+    {
+        let mut paused = instance.pause_scope()?;
+        assert_eq!(paused.describe_instance()?.state, InstanceState::Paused);
+        // resumed when going out of scope
+    }
+    assert_eq!(instance.describe_instance()?.state, InstanceState::Running);
+
+    let paused = instance.pause_scope()?;
+    paused.resume()?;
+    assert_eq!(instance.describe_instance()?.state, InstanceState::Running);
+
+    instance.stop()?;
 
     Ok(())
 }
@@ -1,4 +1,4 @@
-#![cfg(feature = "_rt-std")]
+#![cfg(all(feature = "_rt-std", feature = "models"))]
 
 use std::fs;
 
@@ -8,7 +8,7 @@ mod common;
 
 #[test]
 fn spawn_plain() -> Result<()> {
-    const API_SOCK: &'static str = "/run/firecracker.socket";
+    let api_sock = common::jailed_api_sock("std-jailer-spawn-plain");
     let firecracker_bin = &*common::FIRECRACKER;
     let jailer_bin = &*common::JAILER;
 
@@ -21,7 +21,7 @@ fn spawn_plain() -> Result<()> {
     )
     .remove_jailer_workspace_dir()
     .firecracker_option(Some(
-        FirecrackerOption::new(firecracker_bin).api_sock(API_SOCK),
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
     ))
     .build()?;
 
@@ -41,7 +41,7 @@ fn spawn_plain() -> Result<()> {
 fn spawn_and_config() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str = "/run/firecracker.socket";
+    let api_sock = common::jailed_api_sock("std-jailer-spawn-and-config");
     let firecracker_bin = &*common::FIRECRACKER;
     let jailer_bin = &*common::JAILER;
 
@@ -54,7 +54,7 @@ fn spawn_and_config() -> Result<()> {
     )
     .remove_jailer_workspace_dir()
     .firecracker_option(Some(
-        FirecrackerOption::new(firecracker_bin).api_sock(API_SOCK),
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
     ))
     .build()?;
 
@@ -63,14 +63,7 @@ fn spawn_and_config() -> Result<()> {
     instance.start_vmm()?;
 
     // put some configuration to it
-    instance.put_machine_configuration(&MachineConfiguration {
-        cpu_template: None,
-        smt: None,
-        mem_size_mib: 1024,
-        track_dirty_pages: None,
-        vcpu_count: 1,
-        huge_pages: None,
-    })?;
+    instance.put_machine_configuration(&MachineConfiguration::new(1, 1024))?;
 
     let version = instance.get_firecracker_version()?;
 
@@ -84,7 +77,7 @@ fn spawn_and_config() -> Result<()> {
 fn basic_launch() -> Result<()> {
     use firecracker_rs_sdk::models::*; // import all models for use
 
-    const API_SOCK: &'static str = "/run/firecracker.socket";
+    let api_sock = common::jailed_api_sock("std-jailer-basic-launch");
     let firecracker_bin = &*common::FIRECRACKER;
     let jailer_bin = &*common::JAILER;
     let kernel = &*common::KERNEL;
@@ -103,7 +96,7 @@ fn basic_launch() -> Result<()> {
     .stderr("/dev/null")
     .remove_jailer_workspace_dir()
     .firecracker_option(Some(
-        FirecrackerOption::new(firecracker_bin).api_sock(API_SOCK),
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
     ))
     .build()?;
 
@@ -112,32 +105,15 @@ fn basic_launch() -> Result<()> {
     instance.start_vmm()?;
 
     // put some configuration to it
-    instance.put_machine_configuration(&MachineConfiguration {
-        cpu_template: None,
-        smt: None,
-        mem_size_mib: 1024,
-        track_dirty_pages: None,
-        vcpu_count: 1,
-        huge_pages: None,
-    })?;
-
-    instance.put_guest_boot_source(&BootSource {
-        boot_args: Some("console=ttyS0 reboot=k panic=1 pci=off".into()),
-        initrd_path: None,
-        kernel_image_path: kernel.into(),
-    })?;
-
-    instance.put_guest_drive_by_id(&Drive {
-        drive_id: "rootfs".into(),
-        partuuid: None,
-        is_root_device: true,
-        cache_type: None,
-        is_read_only: false,
-        path_on_host: rootfs.into(),
-        rate_limiter: None,
-        io_engine: None,
-        socket: None,
-    })?;
+    instance.put_machine_configuration(&MachineConfiguration::new(1, 1024))?;
+
+    let mut boot_source = BootSource::new(kernel);
+    boot_source.boot_args = Some("console=ttyS0 reboot=k panic=1 pci=off".into());
+    instance.put_guest_boot_source(&boot_source)?;
+
+    let mut root_drive = Drive::new("rootfs", rootfs);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive)?;
 
     let version = instance.get_firecracker_version()?;
     println!("{:?}", version);
@@ -164,3 +140,76 @@ fn basic_launch() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn teardown_in_new_pid_ns() -> Result<()> {
+    let api_sock = common::jailed_api_sock("std-jailer-teardown-in-new-pid-ns");
+    let firecracker_bin = &*common::FIRECRACKER;
+    let jailer_bin = &*common::JAILER;
+
+    let mut instance = JailerOption::new(
+        jailer_bin,
+        firecracker_bin,
+        "integration-test-std-jailer-teardown-in-new-pid-ns",
+        100,
+        123,
+    )
+    .new_pid_ns(Some(true))
+    .remove_jailer_workspace_dir()
+    .firecracker_option(Some(
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
+    ))
+    .build()?;
+
+    let jailer_workspace_dir = instance.jailer_workspace_dir().unwrap();
+    let _ = fs::remove_dir_all(&jailer_workspace_dir);
+
+    instance.start_vmm()?;
+
+    // the PID must be host-visible and point to our jailed `firecracker`
+    let firecracker_pid = instance.firecracker_pid().unwrap();
+    let root = fs::read_link(format!("/proc/{firecracker_pid}/root"))?;
+    assert_eq!(root, fs::canonicalize(&jailer_workspace_dir)?);
+
+    drop(instance);
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(!std::path::Path::new(&format!("/proc/{firecracker_pid}")).exists());
+
+    Ok(())
+}
+
+#[test]
+fn jailer_reaped() -> Result<()> {
+    let api_sock = common::jailed_api_sock("std-jailer-reaped");
+    let firecracker_bin = &*common::FIRECRACKER;
+    let jailer_bin = &*common::JAILER;
+
+    // `jailer` forks `firecracker` into the new PID namespace and exits
+    let mut instance = JailerOption::new(
+        jailer_bin,
+        firecracker_bin,
+        "integration-test-std-jailer-jailer-reaped",
+        100,
+        123,
+    )
+    .new_pid_ns(Some(true))
+    .remove_jailer_workspace_dir()
+    .firecracker_option(Some(
+        FirecrackerOption::new(firecracker_bin).api_sock(&api_sock),
+    ))
+    .build()?;
+
+    let _ = fs::remove_dir_all(instance.jailer_workspace_dir().unwrap());
+
+    instance.start_vmm()?;
+
+    let jailer_pid = instance.jailer_pid().unwrap();
+    assert_ne!(Some(jailer_pid), instance.firecracker_pid());
+    // either reaped already, or still running but not a zombie
+    if let Ok(stat) = fs::read_to_string(format!("/proc/{jailer_pid}/stat")) {
+        let state = stat.rsplit(')').next().unwrap().split_whitespace().next();
+        assert_ne!(state, Some("Z"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! End-to-end networking test: a TAP device on the host, a guest NIC backed by it,
+//! and a ping from the host to the guest.
+//!
+//! Requires feature `expensive-tests` and root (or `CAP_NET_ADMIN`) to create the TAP device
+//! with `ip`, besides the `FIRECRACKER`, `KERNEL` and `ROOTFS` environment variables. The guest
+//! kernel must support IP autoconfiguration (`CONFIG_IP_PNP`) to bring up `eth0` from its
+//! boot arguments.
+#![cfg(all(feature = "_rt-std", feature = "models", feature = "expensive-tests"))]
+
+use std::{fs, process::Command, time::Duration};
+
+use firecracker_rs_sdk::{firecracker::FirecrackerOption, Result};
+
+mod common;
+
+const TAP: &str = "fc-sdk-tap0";
+const HOST_IP: &str = "172.16.0.1";
+const GUEST_IP: &str = "172.16.0.2";
+
+/// TAP device on the host, deleted when dropped.
+struct Tap(&'static str);
+
+impl Tap {
+    fn create(name: &'static str) -> Self {
+        let _ = Command::new("ip").args(["link", "del", name]).status();
+        let ip = |args: &[&str]| {
+            let status = Command::new("ip").args(args).status().unwrap();
+            assert!(status.success(), "`ip {}` failed", args.join(" "));
+        };
+        let tap = Self(name);
+        ip(&["tuntap", "add", "dev", name, "mode", "tap"]);
+        ip(&["addr", "add", &format!("{HOST_IP}/30"), "dev", name]);
+        ip(&["link", "set", "dev", name, "up"]);
+        tap
+    }
+}
+
+impl Drop for Tap {
+    fn drop(&mut self) {
+        let _ = Command::new("ip").args(["link", "del", self.0]).status();
+    }
+}
+
+fn is_root() -> bool {
+    fs::read_to_string("/proc/self/status").is_ok_and(|status| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("Uid:"))
+            .and_then(|uids| uids.split_whitespace().nth(1))
+            == Some("0")
+    })
+}
+
+#[test]
+fn guest_network_interface() -> Result<()> {
+    use firecracker_rs_sdk::models::*; // import all models for use
+
+    if !is_root() {
+        eprintln!("skipping `guest_network_interface`: creating a TAP device requires root");
+        return Ok(());
+    }
+
+    let api_sock = common::api_sock("std-network");
+    let firecracker_bin = &*common::FIRECRACKER;
+    let kernel = &*common::KERNEL;
+    let rootfs = &*common::ROOTFS;
+
+    let tap = Tap::create(TAP);
+
+    let mut instance = FirecrackerOption::new(firecracker_bin)
+        .api_sock(&api_sock)
+        .stdout("/dev/null")
+        .build()?;
+
+    let _ = fs::remove_file(&api_sock);
+    instance.start_vmm()?;
+
+    instance.put_machine_configuration(&MachineConfiguration::new(1, 256))?;
+
+    // let the guest kernel bring up `eth0` itself
+    let mut boot_source = BootSource::new(kernel);
+    boot_source.boot_args = Some(format!(
+        "console=ttyS0 reboot=k panic=1 pci=off ip={GUEST_IP}::{HOST_IP}:255.255.255.252::eth0:off"
+    ));
+    instance.put_guest_boot_source(&boot_source)?;
+
+    let mut root_drive = Drive::new("rootfs", rootfs);
+    root_drive.is_root_device = true;
+    instance.put_guest_drive_by_id(&root_drive)?;
+
+    let network_interface = NetworkInterface::new("eth0", tap.0)?.guest_mac("06:00:AC:10:00:02");
+    instance.put_guest_network_interface_by_id(&network_interface)?;
+
+    instance.start()?;
+
+    let reachable = (0..30).any(|_| {
+        std::thread::sleep(Duration::from_secs(1));
+        Command::new("ping")
+            .args(["-c", "1", "-W", "1", GUEST_IP])
+            .status()
+            .is_ok_and(|status| status.success())
+    });
+    assert!(reachable, "guest {GUEST_IP} unreachable from {TAP}");
+
+    // terminate `firecracker` before deleting the TAP device it holds
+    drop(instance);
+    drop(tap);
+    assert!(!std::path::Path::new(&format!("/sys/class/net/{TAP}")).exists());
+
+    Ok(())
+}